@@ -38,6 +38,18 @@ function ai_action(parent, params)
         return end_turn(parent)
     end
 
+    if parent:morale_state() == "Fleeing" then
+        game:log("  Running away due to low morale")
+        attempt_run_away(parent, hostiles:visible():to_table())
+        return end_turn(parent)
+    end
+
+    if parent:is_group_retreating() then
+        game:log("  Running away as the group is retreating")
+        attempt_run_away(parent, hostiles:visible():to_table())
+        return end_turn(parent)
+    end
+
     if check_swap_weapons_to_melee(parent, hostiles).done then
         return parent:state_wait(WAIT_TIME)
     end
@@ -125,6 +137,7 @@ function ai_action(parent, params)
             local result = check_move_for_attack(parent, target, retry)
             if result.attack then
                 game:log("  Perform attack")
+                game:ai_trace_attempt("attack", target, "Performed")
                 parent:anim_weapon_attack(target, nil, true)
                 parent:clear_flag("ai_force_attack")
 
@@ -133,6 +146,7 @@ function ai_action(parent, params)
 
             if result.moved then
                 game:log("  Moved.")
+                game:ai_trace_attempt("attack", target, "Moved")
                 return parent:state_wait(WAIT_TIME)
             end
         end
@@ -291,18 +305,22 @@ function find_and_use_item(parent, items, hostiles, friendlies, failed_use_count
         game:log("    Checking item " .. item:name())
         local result = check_action(parent, item:ai_data(), hostiles, friendlies, failed_use_count)
         if result.done then
+            game:ai_trace_attempt(item:name(), nil, "Moved")
             return { done=true }
         end
 
         if result.target then
             game:log("      Use item")
+            game:ai_trace_candidate(item:name(), result.target, item:ai_data().priority)
             parent:use_item(item)
-            local result = handle_targeter(parent, result.target, item, item:ai_data(),
+            local target_result = handle_targeter(parent, result.target, item, item:ai_data(),
                 hostiles, friendlies, weights)
 
-            if result.done then
+            if target_result.done then
+                game:ai_trace_attempt(item:name(), result.target, "Performed")
                 return { done=true }
             end
+            game:ai_trace_attempt(item:name(), result.target, "Failed")
         end
     end
 
@@ -328,18 +346,22 @@ function find_and_use_ability(parent, params, abilities, hostiles, friendlies,
         game:log("    Checking ability " .. ability:name())
         local result = check_action(parent, ai_data, hostiles, friendlies, failed_use_count)
         if result.done then
+            game:ai_trace_attempt(ability:id(), nil, "Moved")
             return { done=true }
         end
 
         if result.target then
             game:log("      Use ability")
+            game:ai_trace_candidate(ability:id(), result.target, ai_data.priority)
             parent:use_ability(ability)
-            local result = handle_targeter(parent, result.target, ability, ai_data,
+            local target_result = handle_targeter(parent, result.target, ability, ai_data,
                 hostiles, friendlies, weights)
 
-            if result.done then
+            if target_result.done then
+                game:ai_trace_attempt(ability:id(), result.target, "Performed")
                 return { done=true }
             end
+            game:ai_trace_attempt(ability:id(), result.target, "Failed")
         end
     end
 
@@ -651,6 +673,7 @@ function sort_attack_targets(parent, hostiles, weights)
         local target = hostiles[i]
         local score = weights[target:id()]
         game:debug("        Got score of " .. tostring(score) .. " for " .. target:id())
+        game:ai_trace_candidate("attack", target, score)
         ranked[score] = target
         table.insert(scores, score)
     end
@@ -671,6 +694,7 @@ function precompute_weights(parent, targets, weights)
         local target = targets[i]
         local weight = compute_weight(parent, target)
         weights[target:id()] = weight
+        game:ai_trace_weight(target, weight)
     end
 end
 
@@ -701,6 +725,9 @@ function compute_weight(parent, target)
     -- hostiles that are difficult to damage with our regular attack are lower priority
     modifiers = modifiers + parent:get_num_flag("__hard_target_for" .. target:id())
 
+    -- hostiles the encounter tactics call for, such as a shared focus target
+    modifiers = modifiers + parent:tactical_weight(target)
+
     game:debug("        Computed weight of " .. tostring(modifiers) .. " for " .. target:id())
 
     return base * (1 + modifiers)
@@ -0,0 +1,290 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Runs a campaign without opening a window or an audio device, and prints
+//! a JSON report of the final game state.  Intended for automated testing.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::rc::Rc;
+
+use log::{error, info};
+
+use sulis_core::config::Config;
+use sulis_core::io::HeadlessSystem;
+use sulis_core::resource::write_json_to_file;
+use sulis_core::serde_json;
+use sulis_core::util::{self, ReproducibleRandom};
+use sulis_module::{Actor, Module};
+use sulis_state::headless::{self, ModuleArgs};
+use sulis_state::{ai_trace, save_file, HeadlessReport, Replay};
+use sulis_view::HeadlessUpdater;
+
+const USAGE: &str =
+    "Usage: sulis-headless --campaign <dir> (--pc <actor_id> | --save <file> | --replay <file>)
+    [--mod <dir>]... [--seed <n>] [--millis <n>] [--step <n>] [--report <file>]
+    [--ai-trace <file>]";
+
+const DEFAULT_MILLIS: u32 = 60_000;
+
+#[derive(Default)]
+struct Args {
+    module: ModuleArgs,
+    pc: Option<String>,
+    save: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    seed: Option<u64>,
+    millis: Option<u32>,
+    step: Option<u32>,
+    report: Option<PathBuf>,
+    ai_trace: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        Args::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(mut iter: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut args = Args::default();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| format!("Missing value for argument '{arg}'"))
+            };
+
+            match arg.as_str() {
+                "--pc" => args.pc = Some(value()?),
+                "--save" => args.save = Some(PathBuf::from(value()?)),
+                "--replay" => args.replay = Some(PathBuf::from(value()?)),
+                "--seed" => args.seed = Some(parse_u64(&value()?)?),
+                "--millis" => args.millis = Some(parse_u32(&value()?)?),
+                "--step" => args.step = Some(parse_u32(&value()?)?),
+                "--report" => args.report = Some(PathBuf::from(value()?)),
+                "--ai-trace" => args.ai_trace = Some(PathBuf::from(value()?)),
+                _ if args.module.parse_arg(&arg, &mut value)? => (),
+                _ => return Err(format!("Unknown argument '{arg}'")),
+            }
+        }
+
+        args.module.require_campaign()?;
+
+        let starts = [
+            args.pc.is_some(),
+            args.save.is_some(),
+            args.replay.is_some(),
+        ];
+        if args.replay.is_some() && args.seed.is_some() {
+            return Err("A seed may not be specified when running a replay".to_string());
+        }
+
+        if starts.iter().filter(|start| **start).count() != 1 {
+            return Err(
+                "Exactly one of a player character, save, or replay must be specified".to_string(),
+            );
+        }
+
+        Ok(args)
+    }
+}
+
+fn parse_u32(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|e| format!("Invalid number '{value}': {e}"))
+}
+
+fn parse_u64(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|e| format!("Invalid number '{value}': {e}"))
+}
+
+fn find_pc(id: &str) -> Option<Rc<Actor>> {
+    if let Some(actor) = Module::actor(id) {
+        return Some(actor);
+    }
+
+    Module::get_available_characters()
+        .into_iter()
+        .find(|actor| actor.id == id)
+        .map(Rc::new)
+}
+
+fn create_updater(args: &Args) -> Result<HeadlessUpdater, String> {
+    if let Some(path) = &args.save {
+        let mut save_state = save_file::load_state_from_path(path)
+            .map_err(|e| format!("Unable to read save '{}': {e}", path.to_string_lossy()))?;
+        if let Some(seed) = args.seed {
+            save_state.reseed(seed);
+        }
+        return HeadlessUpdater::load_campaign(save_state)
+            .map_err(|e| format!("Unable to load game state: {e}"));
+    }
+
+    if let Some(seed) = args.seed {
+        util::set_random(ReproducibleRandom::new(Some(seed as u128)));
+    }
+
+    let id = args.pc.as_deref().unwrap_or_default();
+    let pc = find_pc(id).ok_or_else(|| format!("Player character '{id}' not found"))?;
+    HeadlessUpdater::new_campaign(pc, Vec::new(), HashMap::new())
+        .map_err(|e| format!("Unable to create game state: {e}"))
+}
+
+fn run(args: &Args, system: &HeadlessSystem) -> Result<HeadlessReport, String> {
+    if let Some(path) = &args.replay {
+        let replay = Replay::read(path)
+            .map_err(|e| format!("Unable to read replay '{}': {e}", path.to_string_lossy()))?;
+        return HeadlessUpdater::replay(&replay, system)
+            .map_err(|e| format!("Unable to load replay state: {e}"));
+    }
+
+    Ok(create_updater(args)?.run(system))
+}
+
+fn main() -> ExitCode {
+    let _logger_handle = util::setup_logger();
+    info!("=========Initializing Headless=========");
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // automated runs should never write autosaves into the user's save directory
+    let mut config = Config::get_clone();
+    config.saves.autosave_slots = 0;
+    Config::set(config);
+
+    if let Err(e) = headless::load_resources(args.module.directories()) {
+        error!("{}", e);
+        error!("Fatal error reading resources.");
+        return ExitCode::FAILURE;
+    }
+
+    let millis = args.millis.unwrap_or(DEFAULT_MILLIS);
+    let system = match args.step {
+        Some(step) => HeadlessSystem::new(step, millis),
+        None => HeadlessSystem::with_frame_rate(millis),
+    };
+
+    let report = match run(&args, &system) {
+        Ok(report) => report,
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let output = match &args.report {
+        Some(path) => write_json_to_file(path, &report),
+        None => serde_json::to_string_pretty(&report)
+            .map(|json| println!("{json}"))
+            .map_err(|e| e.into()),
+    };
+
+    if let Err(e) = output {
+        error!("Unable to write report: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(path) = &args.ai_trace {
+        if let Err(e) = ai_trace::write(path) {
+            error!("Unable to write AI trace: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if report.errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse_from(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_all_arguments() {
+        let args = parse(&[
+            "--campaign",
+            "camp",
+            "--mod",
+            "a",
+            "--mod",
+            "b",
+            "--pc",
+            "hero",
+            "--seed",
+            "42",
+            "--millis",
+            "500",
+            "--step",
+            "10",
+            "--report",
+            "out.json",
+            "--ai-trace",
+            "ai.json",
+        ])
+        .unwrap();
+
+        assert_eq!(args.module.campaign.as_deref(), Some("camp"));
+        assert_eq!(args.module.mods, vec!["a", "b"]);
+        assert_eq!(args.pc.as_deref(), Some("hero"));
+        assert_eq!(args.seed, Some(42));
+        assert_eq!(args.millis, Some(500));
+        assert_eq!(args.step, Some(10));
+        assert_eq!(args.report, Some(PathBuf::from("out.json")));
+        assert_eq!(args.ai_trace, Some(PathBuf::from("ai.json")));
+    }
+
+    #[test]
+    fn requires_campaign() {
+        assert!(parse(&["--pc", "hero"]).is_err());
+    }
+
+    #[test]
+    fn requires_exactly_one_start() {
+        assert!(parse(&["--campaign", "camp"]).is_err());
+        assert!(parse(&["--campaign", "camp", "--pc", "hero", "--save", "s"]).is_err());
+        assert!(parse(&["--campaign", "camp", "--save", "s"]).is_ok());
+        assert!(parse(&["--campaign", "camp", "--replay", "r"]).is_ok());
+    }
+
+    #[test]
+    fn rejects_seed_with_replay() {
+        assert!(parse(&["--campaign", "camp", "--replay", "r", "--seed", "1"]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(parse(&["--campaign", "camp", "--pc", "hero", "--bogus"]).is_err());
+        assert!(parse(&["--campaign", "camp", "--pc"]).is_err());
+        assert!(parse(&["--campaign", "camp", "--pc", "hero", "--millis", "x"]).is_err());
+    }
+}
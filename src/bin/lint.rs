@@ -0,0 +1,89 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Checks the scripts of a campaign, and of any mods, for compile errors,
+//! broken includes, and references to functions that do not exist.
+
+use std::process::ExitCode;
+
+use log::error;
+
+use sulis_core::resource::ResourceSet;
+use sulis_core::util;
+use sulis_module::Module;
+use sulis_state::headless::ModuleArgs;
+use sulis_state::script::script_validator;
+
+const USAGE: &str = "Usage: sulis-lint --campaign <dir> [--mod <dir>]...";
+
+fn parse_args() -> Result<Vec<String>, String> {
+    let mut module = ModuleArgs::default();
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("Missing value for argument '{arg}'"))
+        };
+
+        if !module.parse_arg(&arg, &mut value)? {
+            return Err(format!("Unknown argument '{arg}'"));
+        }
+    }
+
+    module.require_campaign()?;
+    Ok(module.directories())
+}
+
+fn run(dirs: Vec<String>) -> Result<bool, String> {
+    let yaml = ResourceSet::load_resources(dirs.clone())
+        .map_err(|e| format!("Error reading resources: {e}"))?;
+    Module::load_resources(yaml, dirs).map_err(|e| format!("Error loading module: {e}"))?;
+
+    let errors = script_validator::validate();
+    for error in errors.iter() {
+        println!("{error}");
+    }
+    println!(
+        "{} scripts checked, {} problems found.",
+        Module::all_scripts().len(),
+        errors.len()
+    );
+
+    Ok(errors.is_empty())
+}
+
+fn main() -> ExitCode {
+    let _logger_handle = util::setup_logger();
+
+    let dirs = match parse_args() {
+        Ok(dirs) => dirs,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(dirs) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
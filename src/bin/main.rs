@@ -20,14 +20,16 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use log::{error, info};
+use log::{error, info, warn};
 
+use sulis_core::config::Config;
 use sulis_core::io::{ControlFlowUpdater, DisplayConfiguration, System};
 use sulis_core::resource::ResourceSet;
 use sulis_core::ui::{self, Cursor, Widget};
 use sulis_core::util::{self, ActiveResources};
-use sulis_module::{Actor, Module};
-use sulis_state::{GameState, NextGameStep, SaveState};
+use sulis_module::{modification, Actor, Module};
+use sulis_state::script::script_validator;
+use sulis_state::{replay, GameState, NextGameStep, SaveState};
 use sulis_view::{
     main_menu::{self, MainMenu},
     trigger_activator, RootView,
@@ -130,6 +132,12 @@ impl GameControlFlowUpdater {
 
     fn load_campaign(&mut self, save_state: SaveState) {
         info!("Loading game state.");
+        if Config::debug().record_input {
+            if let Err(e) = replay::start_recording(&save_state) {
+                warn!("Unable to start input recording: {}", e);
+            }
+        }
+
         if let Err(e) = GameState::load(save_state) {
             error!("{}", e);
             util::error_and_exit("There was a fatal error loading the game state.");
@@ -140,8 +148,26 @@ impl GameControlFlowUpdater {
         self.mode = UiMode::Game(view);
     }
 
+    fn finish_recording(&self) {
+        if !matches!(self.mode, UiMode::Game(_)) {
+            return;
+        }
+
+        let replay = match replay::finish_recording() {
+            None => return,
+            Some(replay) => replay,
+        };
+
+        match replay.write_to_replay_dir() {
+            Ok(path) => info!("Wrote input replay to '{}'", path.to_string_lossy()),
+            Err(e) => warn!("Unable to write input replay: {}", e),
+        }
+    }
+
     fn handle_next_step(&mut self, step: NextGameStep) {
         use NextGameStep::*;
+        self.finish_recording();
+
         match step {
             Exit => {
                 self.exit = true;
@@ -159,7 +185,7 @@ impl GameControlFlowUpdater {
                 module_dir,
             } => {
                 let mut active = ActiveResources::read();
-                active.campaign = Some(module_dir);
+                active.set_campaign(module_dir);
                 active.write();
                 load_resources();
                 self.new_campaign(pc_actor, party_actors, flags);
@@ -219,7 +245,7 @@ fn load_resources() {
 
     let active = ActiveResources::read();
 
-    let dirs = active.directories();
+    let dirs = modification::active_directories(&active);
 
     let start_main = std::time::Instant::now();
     info!("Reading resources from '{:?}'", dirs);
@@ -238,8 +264,13 @@ fn load_resources() {
 
     if dirs.len() > 1 {
         info!("Loading module '{}'", dirs[1]);
-        if let Err(e) = Module::load_resources(yaml, dirs) {
-            error!("{}", e);
+        match Module::load_resources(yaml, dirs) {
+            Err(e) => error!("{}", e),
+            Ok(()) => {
+                for error in script_validator::validate() {
+                    warn!("{}", error);
+                }
+            }
         }
     }
 
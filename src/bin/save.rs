@@ -0,0 +1,332 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Lists, summarizes, validates, and edits save files, without needing to
+//! hand edit the save JSON.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use log::error;
+
+use sulis_core::serde_json;
+use sulis_core::util;
+use sulis_module::on_trigger::QuestEntryState;
+use sulis_state::headless::{self, ModuleArgs};
+use sulis_state::{save_file, SaveFile};
+
+const USAGE: &str = "Usage: sulis-save list [--campaign-id <id>]
+       sulis-save summary --campaign <dir> [--mod <dir>]... <save>
+       sulis-save validate --campaign <dir> [--mod <dir>]... <save>
+       sulis-save edit --campaign <dir> [--mod <dir>]... <save> [--output <file>] [--force]
+           [--set-flag [<entity_unique_id>:]<flag>=<value>]...
+           [--give-item <item_id>[:<quantity>]]...
+           [--set-quest <quest_id>[/<entry_id>]=<Hidden|Visible|Active|Complete>]...";
+
+enum Edit {
+    SetFlag {
+        entity: Option<String>,
+        flag: String,
+        value: String,
+    },
+    GiveItem {
+        id: String,
+        quantity: u32,
+    },
+    SetQuest {
+        quest: String,
+        entry: Option<String>,
+        state: QuestEntryState,
+    },
+}
+
+#[derive(Default)]
+struct Args {
+    command: String,
+    module: ModuleArgs,
+    campaign_id: Option<String>,
+    save: Option<PathBuf>,
+    output: Option<PathBuf>,
+    force: bool,
+    edits: Vec<Edit>,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut iter = std::env::args().skip(1);
+        let mut args = Args {
+            command: iter.next().ok_or("No command specified")?,
+            ..Default::default()
+        };
+
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| format!("Missing value for argument '{arg}'"))
+            };
+
+            match arg.as_str() {
+                "--campaign-id" => args.campaign_id = Some(value()?),
+                "--output" => args.output = Some(PathBuf::from(value()?)),
+                "--force" => args.force = true,
+                "--set-flag" => args.edits.push(parse_set_flag(&value()?)?),
+                "--give-item" => args.edits.push(parse_give_item(&value()?)?),
+                "--set-quest" => args.edits.push(parse_set_quest(&value()?)?),
+                _ if args.module.parse_arg(&arg, &mut value)? => (),
+                _ if arg.starts_with("--") => return Err(format!("Unknown argument '{arg}'")),
+                _ if args.save.is_none() => args.save = Some(PathBuf::from(arg)),
+                _ => return Err(format!("Unexpected argument '{arg}'")),
+            }
+        }
+
+        match args.command.as_str() {
+            "list" => (),
+            "summary" | "validate" | "edit" => {
+                args.module.require_campaign()?;
+
+                if args.save.is_none() {
+                    return Err("A save file must be specified".to_string());
+                }
+            }
+            _ => return Err(format!("Unknown command '{}'", args.command)),
+        }
+
+        if args.command == "edit" && args.edits.is_empty() {
+            return Err("At least one edit must be specified".to_string());
+        }
+
+        Ok(args)
+    }
+}
+
+fn split_once<'a>(
+    value: &'a str,
+    separator: char,
+    arg: &str,
+) -> Result<(&'a str, &'a str), String> {
+    value
+        .split_once(separator)
+        .ok_or_else(|| format!("Invalid {arg} value '{value}'"))
+}
+
+fn parse_set_flag(value: &str) -> Result<Edit, String> {
+    let (target, value) = split_once(value, '=', "--set-flag")?;
+    let (entity, flag) = match target.split_once(':') {
+        None => (None, target),
+        Some((entity, flag)) => (Some(entity.to_string()), flag),
+    };
+
+    Ok(Edit::SetFlag {
+        entity,
+        flag: flag.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn parse_give_item(value: &str) -> Result<Edit, String> {
+    let (id, quantity) = match value.split_once(':') {
+        None => (value, 1),
+        Some((id, quantity)) => {
+            let quantity = quantity
+                .parse()
+                .map_err(|e| format!("Invalid quantity '{quantity}': {e}"))?;
+            (id, quantity)
+        }
+    };
+
+    Ok(Edit::GiveItem {
+        id: id.to_string(),
+        quantity,
+    })
+}
+
+fn parse_set_quest(value: &str) -> Result<Edit, String> {
+    let (target, state) = split_once(value, '=', "--set-quest")?;
+    let state = match state {
+        "Hidden" => QuestEntryState::Hidden,
+        "Visible" => QuestEntryState::Visible,
+        "Active" => QuestEntryState::Active,
+        "Complete" => QuestEntryState::Complete,
+        _ => return Err(format!("Invalid quest state '{state}'")),
+    };
+
+    let (quest, entry) = match target.split_once('/') {
+        None => (target, None),
+        Some((quest, entry)) => (quest, Some(entry.to_string())),
+    };
+
+    Ok(Edit::SetQuest {
+        quest: quest.to_string(),
+        entry,
+        state,
+    })
+}
+
+fn list(campaign_id: Option<&str>) -> Result<(), String> {
+    let root = save_file::save_root_dir();
+    let mut dirs: Vec<PathBuf> = match campaign_id {
+        Some(id) => vec![root.join(id)],
+        None => fs::read_dir(&root)
+            .map_err(|e| format!("Unable to read '{}': {e}", root.to_string_lossy()))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect(),
+    };
+    dirs.sort();
+
+    for dir in dirs {
+        let saves = save_file::get_save_files_in(&dir)
+            .map_err(|e| format!("Unable to read '{}': {e}", dir.to_string_lossy()))?;
+
+        let campaign = dir.file_name().unwrap_or_default().to_string_lossy();
+        println!("{campaign}:");
+        for meta in saves {
+            let file = meta
+                .path()
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy();
+            match &meta.error {
+                Some(error) => println!("  {file}  INVALID: {error}"),
+                None => println!(
+                    "  {file}  {}  {} (level {})  {}{}",
+                    meta.datetime,
+                    meta.player_name,
+                    meta.level.unwrap_or_default(),
+                    meta.current_area_name,
+                    if meta.autosave { "  [autosave]" } else { "" },
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn apply_edit(save: &mut SaveFile, edit: &Edit) -> Result<(), String> {
+    let result = match edit {
+        Edit::SetFlag {
+            entity,
+            flag,
+            value,
+        } => save.set_flag(entity.as_deref(), flag, value),
+        Edit::GiveItem { id, quantity } => save.give_item(id, *quantity),
+        Edit::SetQuest {
+            quest,
+            entry,
+            state,
+        } => save.set_quest_state(quest, entry.as_deref(), *state),
+    };
+
+    result.map_err(|e| e.to_string())
+}
+
+fn edit(args: &Args, path: &Path, mut save: SaveFile) -> Result<(), String> {
+    for edit in args.edits.iter() {
+        apply_edit(&mut save, edit)?;
+    }
+
+    let errors = save.validate();
+    if !errors.is_empty() {
+        for error in errors.iter() {
+            eprintln!("{error}");
+        }
+
+        if !args.force {
+            return Err("Edited save is not valid; use --force to write it anyway".to_string());
+        }
+    }
+
+    let output = args.output.as_deref().unwrap_or(path);
+    if output == path {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".bak");
+        fs::copy(path, &backup).map_err(|e| format!("Unable to back up save: {e}"))?;
+    }
+
+    save.write(output)
+        .map_err(|e| format!("Unable to write '{}': {e}", output.to_string_lossy()))?;
+
+    // make sure the save we just wrote can be read back in
+    SaveFile::read(output).map_err(|e| {
+        format!(
+            "Written save '{}' is unreadable: {e}",
+            output.to_string_lossy()
+        )
+    })?;
+
+    println!("Wrote {}", output.to_string_lossy());
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    if args.command == "list" {
+        list(args.campaign_id.as_deref())?;
+        return Ok(true);
+    }
+
+    headless::load_resources(args.module.directories())
+        .map_err(|e| format!("Error reading resources: {e}"))?;
+
+    let path = args.save.as_deref().unwrap_or(Path::new(""));
+    let save = SaveFile::read(path)
+        .map_err(|e| format!("Unable to read save '{}': {e}", path.to_string_lossy()))?;
+
+    match args.command.as_str() {
+        "summary" => {
+            let summary = serde_json::to_string_pretty(&save.summary())
+                .map_err(|e| format!("Unable to write summary: {e}"))?;
+            println!("{summary}");
+            Ok(true)
+        }
+        "validate" => {
+            let errors = save.validate();
+            for error in errors.iter() {
+                println!("{error}");
+            }
+            println!("{} problems found.", errors.len());
+            Ok(errors.is_empty())
+        }
+        _ => {
+            edit(args, path, save)?;
+            Ok(true)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let _logger_handle = util::setup_logger();
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
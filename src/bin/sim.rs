@@ -0,0 +1,168 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Pits two sides of actors against each other many times, reporting win
+//! rates, fight lengths, damage per AP, and hit kinds.  Intended as an aid
+//! for balancing classes and encounters.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::rc::Rc;
+
+use log::error;
+
+use sulis_core::resource::write_json_to_file;
+use sulis_core::util::{self, ReproducibleRandom};
+use sulis_module::{Actor, Module};
+use sulis_state::combat_sim;
+use sulis_state::headless::{self, ModuleArgs};
+
+const USAGE: &str = "Usage: sulis-sim --campaign <dir> [--mod <dir>]...
+    --side-a <actor_id>[:<count>]... --side-b <actor_id>[:<count>]...
+    [--fights <n>] [--max-rounds <n>] [--seed <n>] [--report <file>]";
+
+const DEFAULT_FIGHTS: u32 = 1000;
+const DEFAULT_MAX_ROUNDS: u32 = 50;
+
+#[derive(Default)]
+struct Args {
+    module: ModuleArgs,
+    sides: [Vec<(String, u32)>; 2],
+    fights: Option<u32>,
+    max_rounds: Option<u32>,
+    seed: Option<u64>,
+    report: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| format!("Missing value for argument '{arg}'"))
+            };
+
+            match arg.as_str() {
+                "--side-a" => args.sides[0].push(parse_actor(&value()?)?),
+                "--side-b" => args.sides[1].push(parse_actor(&value()?)?),
+                "--fights" => args.fights = Some(parse_u32(&value()?)?),
+                "--max-rounds" => args.max_rounds = Some(parse_u32(&value()?)?),
+                "--seed" => args.seed = Some(parse_u64(&value()?)?),
+                "--report" => args.report = Some(PathBuf::from(value()?)),
+                _ if args.module.parse_arg(&arg, &mut value)? => (),
+                _ => return Err(format!("Unknown argument '{arg}'")),
+            }
+        }
+
+        args.module.require_campaign()?;
+
+        if args.sides.iter().any(|side| side.is_empty()) {
+            return Err("At least one actor must be specified for each side".to_string());
+        }
+
+        Ok(args)
+    }
+}
+
+fn parse_actor(value: &str) -> Result<(String, u32), String> {
+    match value.split_once(':') {
+        None => Ok((value.to_string(), 1)),
+        Some((id, count)) => Ok((id.to_string(), parse_u32(count)?)),
+    }
+}
+
+fn parse_u32(value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|e| format!("Invalid number '{value}': {e}"))
+}
+
+fn parse_u64(value: &str) -> Result<u64, String> {
+    value
+        .parse()
+        .map_err(|e| format!("Invalid number '{value}': {e}"))
+}
+
+fn find_actor(id: &str) -> Option<Rc<Actor>> {
+    if let Some(actor) = Module::actor(id) {
+        return Some(actor);
+    }
+
+    Module::get_available_characters()
+        .into_iter()
+        .find(|actor| actor.id == id)
+        .map(Rc::new)
+}
+
+fn side_actors(side: &[(String, u32)]) -> Result<Vec<Rc<Actor>>, String> {
+    let mut actors = Vec::new();
+    for (id, count) in side.iter() {
+        let actor = find_actor(id).ok_or_else(|| format!("No actor found with ID '{id}'"))?;
+        for _ in 0..*count {
+            actors.push(Rc::clone(&actor));
+        }
+    }
+    Ok(actors)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    headless::load_resources(args.module.directories())
+        .map_err(|e| format!("Error reading resources: {e}"))?;
+
+    if let Some(seed) = args.seed {
+        util::set_random(ReproducibleRandom::new(Some(seed as u128)));
+    }
+
+    let side_a = side_actors(&args.sides[0])?;
+    let side_b = side_actors(&args.sides[1])?;
+
+    let report = combat_sim::run(
+        [&side_a, &side_b],
+        args.fights.unwrap_or(DEFAULT_FIGHTS),
+        args.max_rounds.unwrap_or(DEFAULT_MAX_ROUNDS),
+    );
+
+    print!("{report}");
+
+    if let Some(path) = &args.report {
+        write_json_to_file(path, &report).map_err(|e| format!("Unable to write report: {e}"))?;
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let _logger_handle = util::setup_logger();
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
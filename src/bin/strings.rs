@@ -0,0 +1,153 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Extracts every translatable string from a campaign, and any mods, into a
+//! string table.  With `--language`, existing translations in that
+//! language's string tables are kept, and the untranslated strings are
+//! reported, giving translators a complete table to work from.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use log::error;
+
+use sulis_core::resource::localization::{self, BASE_LANGUAGE};
+use sulis_core::resource::{write_to_file, YamlResourceSet};
+use sulis_core::serde_yaml::{self, Mapping, Value};
+use sulis_core::util;
+use sulis_state::headless::ModuleArgs;
+
+const USAGE: &str =
+    "Usage: sulis-strings --campaign <dir> [--mod <dir>]... [--language <id>] [--output <file>]";
+
+#[derive(Default)]
+struct Args {
+    module: ModuleArgs,
+    language: Option<String>,
+    output: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| format!("Missing value for argument '{arg}'"))
+            };
+
+            match arg.as_str() {
+                "--language" => args.language = Some(value()?),
+                "--output" => args.output = Some(PathBuf::from(value()?)),
+                _ if args.module.parse_arg(&arg, &mut value)? => (),
+                _ => return Err(format!("Unknown argument '{arg}'")),
+            }
+        }
+
+        args.module.require_campaign()?;
+
+        Ok(args)
+    }
+}
+
+fn read_yaml(dirs: &[String]) -> Result<YamlResourceSet, String> {
+    let mut yaml = YamlResourceSet::new(Path::new(&dirs[0]))
+        .map_err(|e| format!("Unable to read '{}': {e}", dirs[0]))?;
+    for dir in dirs.iter().skip(1) {
+        yaml.append(Path::new(dir));
+    }
+    Ok(yaml)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let mut yaml = read_yaml(&args.module.directories())?;
+    let mut strings = localization::translatable_strings(&yaml);
+    let language = args.language.as_deref().unwrap_or(BASE_LANGUAGE);
+    let mut tables = localization::read_string_tables(&mut yaml);
+
+    // text in the base language tables replaces the text in the resources
+    let base = tables.remove(BASE_LANGUAGE).unwrap_or_default();
+    for (id, text) in strings.iter_mut() {
+        if let Some(base_text) = base.get(id) {
+            *text = base_text.to_string();
+        }
+    }
+
+    if language != BASE_LANGUAGE {
+        let table = tables.remove(language).unwrap_or_default();
+
+        let mut untranslated = 0;
+        for (id, text) in strings.iter_mut() {
+            match table.get(id) {
+                Some(translated) => *text = translated.to_string(),
+                None => untranslated += 1,
+            }
+        }
+
+        for id in table.keys().filter(|id| !strings.contains_key(*id)) {
+            eprintln!("String '{id}' in language '{language}' is not used");
+        }
+
+        eprintln!(
+            "{} of {} strings are untranslated in language '{}'",
+            untranslated,
+            strings.len(),
+            language
+        );
+    }
+
+    let mut output = Mapping::new();
+    output.insert("id".into(), language.into());
+    let strings: Mapping = strings
+        .into_iter()
+        .map(|(id, text)| (Value::from(id), Value::from(text)))
+        .collect();
+    output.insert("strings".into(), Value::Mapping(strings));
+
+    match &args.output {
+        None => {
+            let data = serde_yaml::to_string(&output)
+                .map_err(|e| format!("Unable to write strings: {e}"))?;
+            print!("{data}");
+        }
+        Some(path) => write_to_file(path, &output)
+            .map_err(|e| format!("Unable to write '{}': {e}", path.to_string_lossy()))?,
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let _logger_handle = util::setup_logger();
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Loads a campaign, and any mods, and reports every problem found in its
+//! resources and scripts at once.  Intended for use in CI, the exit code is
+//! non-zero if any errors are found.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use log::error;
+
+use sulis_core::resource::{write_json_to_file, ResourceSet};
+use sulis_core::util;
+use sulis_module::module_validator::{self, IssueKind};
+use sulis_module::{Module, ValidationReport};
+use sulis_state::headless::ModuleArgs;
+use sulis_state::script::script_validator;
+
+const USAGE: &str =
+    "Usage: sulis-validate --campaign <dir> [--mod <dir>]... [--report <file>] [--strict] [--overrides]";
+
+#[derive(Default)]
+struct Args {
+    module: ModuleArgs,
+    report: Option<PathBuf>,
+    strict: bool,
+    overrides: bool,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| format!("Missing value for argument '{arg}'"))
+            };
+
+            match arg.as_str() {
+                "--report" => args.report = Some(PathBuf::from(value()?)),
+                "--strict" => args.strict = true,
+                "--overrides" => args.overrides = true,
+                _ if args.module.parse_arg(&arg, &mut value)? => (),
+                _ => return Err(format!("Unknown argument '{arg}'")),
+            }
+        }
+
+        args.module.require_campaign()?;
+
+        Ok(args)
+    }
+}
+
+fn validate(dirs: Vec<String>) -> ValidationReport {
+    let loaded = ResourceSet::load_resources(dirs.clone())
+        .and_then(|yaml| Module::load_resources(yaml, dirs.clone()));
+
+    if let Err(e) = loaded {
+        let mut report = ValidationReport::with_load_errors(dirs);
+        report.add_error(IssueKind::LoadError, format!("Unable to load module: {e}"));
+        return report;
+    }
+
+    let mut report = module_validator::validate();
+    for error in script_validator::validate() {
+        report.add_error(IssueKind::Script, error);
+    }
+    report
+}
+
+fn run(args: &Args) -> Result<bool, String> {
+    let report = validate(args.module.directories());
+    if args.overrides {
+        for overrides in report.overrides.iter() {
+            print!("{overrides}");
+        }
+    }
+    print!("{report}");
+
+    if let Some(path) = &args.report {
+        write_json_to_file(path, &report).map_err(|e| format!("Unable to write report: {e}"))?;
+    }
+
+    Ok(report.errors == 0 && (!args.strict || report.warnings == 0))
+}
+
+fn main() -> ExitCode {
+    let _logger_handle = util::setup_logger();
+
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -48,6 +48,9 @@ pub struct Config {
     pub logging: LoggingConfig,
     pub editor: EditorConfig,
 
+    #[serde(default)]
+    pub saves: SavesConfig,
+
     #[serde(default)]
     pub debug: DebugConfig,
 }
@@ -125,6 +128,10 @@ impl Config {
         CONFIG.with(|c| c.borrow().logging.clone())
     }
 
+    pub fn saves_config() -> SavesConfig {
+        CONFIG.with(|c| c.borrow().saves.clone())
+    }
+
     pub fn debug() -> DebugConfig {
         CONFIG.with(|c| c.borrow().debug.clone())
     }
@@ -189,11 +196,56 @@ impl Config {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SavesConfig {
+    pub compress: bool,
+
+    #[serde(default = "default_autosave_slots")]
+    pub autosave_slots: u32,
+
+    #[serde(default = "default_true")]
+    pub autosave_on_transition: bool,
+
+    #[serde(default = "default_true")]
+    pub autosave_on_combat_end: bool,
+
+    #[serde(default = "default_autosave_interval_minutes")]
+    pub autosave_interval_minutes: u32,
+}
+
+fn default_autosave_slots() -> u32 {
+    3
+}
+
+fn default_autosave_interval_minutes() -> u32 {
+    10
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for SavesConfig {
+    fn default() -> Self {
+        SavesConfig {
+            compress: true,
+            autosave_slots: default_autosave_slots(),
+            autosave_on_transition: true,
+            autosave_on_combat_end: true,
+            autosave_interval_minutes: default_autosave_interval_minutes(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct DebugConfig {
     pub encounter_spawning: bool,
     pub limit_line_of_sight: bool,
+
+    #[serde(default)]
+    pub record_input: bool,
 }
 
 impl Default for DebugConfig {
@@ -201,6 +253,7 @@ impl Default for DebugConfig {
         DebugConfig {
             encounter_spawning: true,
             limit_line_of_sight: true,
+            record_input: false,
         }
     }
 }
@@ -301,6 +354,14 @@ pub struct ResourcesConfig {
     pub directory: String,
     pub campaigns_directory: String,
     pub mods_directory: String,
+
+    /// The language code of the string tables used for player facing text
+    #[serde(default = "default_language")]
+    pub language: String,
+}
+
+fn default_language() -> String {
+    crate::resource::localization::BASE_LANGUAGE.to_string()
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
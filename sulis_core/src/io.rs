@@ -22,9 +22,15 @@ pub use self::event::Event;
 
 mod glium_adapter;
 
+pub mod headless;
+pub use self::headless::{HeadlessResult, HeadlessSystem};
+
 mod input_action;
 pub use self::input_action::{InputAction, InputActionKind, InputActionState};
 
+mod input_recorder;
+pub use self::input_recorder::{InputRecorder, RecordedFrame};
+
 pub mod keyboard_event;
 pub use self::keyboard_event::KeyboardEvent;
 
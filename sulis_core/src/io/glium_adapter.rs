@@ -632,6 +632,7 @@ pub(crate) fn main_loop(system: GliumSystem, mut updater: Box<dyn ControlFlowUpd
                 mouse_move = None;
 
                 root = updater.update(last_elapsed);
+                InputRecorder::end_frame(last_elapsed);
                 if updater.is_exit() {
                     *control_flow = ControlFlow::Exit;
                 } else if updater.recreate_window() {
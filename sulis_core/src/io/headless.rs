@@ -0,0 +1,277 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::time;
+
+use crate::config::Config;
+use crate::io::{Audio, ControlFlowUpdater, InputRecorder, RecordedFrame};
+use crate::util::format_elapsed_secs;
+
+/// A system that drives a `ControlFlowUpdater` on a fixed timestep, without
+/// creating a display or an audio device.  Nothing is rendered and all queued
+/// audio is discarded each frame.
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessSystem {
+    step_millis: u32,
+    max_millis: u32,
+}
+
+/// Statistics on a completed headless main loop
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessResult {
+    pub frames: u32,
+    pub elapsed_millis: u32,
+    pub exited: bool,
+}
+
+impl HeadlessSystem {
+    /// Creates a system that advances the updater `step_millis` per frame,
+    /// until the updater exits or `max_millis` of game time have elapsed.
+    pub fn new(step_millis: u32, max_millis: u32) -> HeadlessSystem {
+        HeadlessSystem {
+            step_millis: step_millis.max(1),
+            max_millis,
+        }
+    }
+
+    /// Creates a system stepping at the configured frame rate
+    pub fn with_frame_rate(max_millis: u32) -> HeadlessSystem {
+        let frame_rate = Config::frame_rate().max(1);
+        HeadlessSystem::new(1000 / frame_rate, max_millis)
+    }
+
+    pub fn step_millis(&self) -> u32 {
+        self.step_millis
+    }
+
+    pub fn max_millis(&self) -> u32 {
+        self.max_millis
+    }
+
+    /// Runs the updater until it exits or the time limit is reached.
+    pub fn main_loop(&self, updater: &mut dyn ControlFlowUpdater) -> HeadlessResult {
+        info!(
+            "Starting headless main loop with {} ms step for {} ms.",
+            self.step_millis, self.max_millis
+        );
+        let start_time = time::Instant::now();
+
+        let mut frames = 0;
+        let mut elapsed_millis = 0;
+        let mut exited = false;
+
+        while elapsed_millis < self.max_millis {
+            updater.update(self.step_millis);
+            InputRecorder::end_frame(self.step_millis);
+            Audio::update(None, self.step_millis);
+
+            frames += 1;
+            elapsed_millis += self.step_millis;
+
+            if updater.is_exit() {
+                exited = true;
+                break;
+            }
+        }
+
+        info!(
+            "Ran {} headless frames ({} ms of game time) in {} secs",
+            frames,
+            elapsed_millis,
+            format_elapsed_secs(start_time.elapsed())
+        );
+
+        HeadlessResult {
+            frames,
+            elapsed_millis,
+            exited,
+        }
+    }
+
+    /// Replays the recorded frames against the updater, delivering each
+    /// frame's input actions and then updating with the recorded frame time.
+    /// The step and time limit of this system are ignored.
+    pub fn replay_loop(
+        &self,
+        updater: &mut dyn ControlFlowUpdater,
+        frames: &[RecordedFrame],
+    ) -> HeadlessResult {
+        info!("Starting headless replay of {} frames.", frames.len());
+        let start_time = time::Instant::now();
+
+        let mut root = updater.root();
+        let mut result = HeadlessResult {
+            frames: 0,
+            elapsed_millis: 0,
+            exited: false,
+        };
+
+        for frame in frames {
+            for action in frame.actions.iter() {
+                action.handle(&root);
+            }
+
+            root = updater.update(frame.millis);
+            Audio::update(None, frame.millis);
+
+            result.frames += 1;
+            result.elapsed_millis += frame.millis;
+
+            if updater.is_exit() {
+                result.exited = true;
+                break;
+            }
+        }
+
+        info!(
+            "Replayed {} headless frames ({} ms of game time) in {} secs",
+            result.frames,
+            result.elapsed_millis,
+            format_elapsed_secs(start_time.elapsed())
+        );
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::resource::ResourceSet;
+    use crate::ui::Widget;
+
+    struct CountingUpdater {
+        root: Rc<RefCell<Widget>>,
+        updates: Vec<u32>,
+        exit_after: Option<usize>,
+    }
+
+    impl CountingUpdater {
+        fn new(exit_after: Option<usize>) -> CountingUpdater {
+            ResourceSet::init_default_theme();
+            CountingUpdater {
+                root: Widget::empty("root"),
+                updates: Vec::new(),
+                exit_after,
+            }
+        }
+    }
+
+    impl ControlFlowUpdater for CountingUpdater {
+        fn update(&mut self, millis: u32) -> Rc<RefCell<Widget>> {
+            self.updates.push(millis);
+            Rc::clone(&self.root)
+        }
+
+        fn root(&self) -> Rc<RefCell<Widget>> {
+            Rc::clone(&self.root)
+        }
+
+        fn recreate_window(&mut self) -> bool {
+            false
+        }
+
+        fn is_exit(&self) -> bool {
+            match self.exit_after {
+                None => false,
+                Some(count) => self.updates.len() >= count,
+            }
+        }
+    }
+
+    #[test]
+    fn main_loop_runs_until_time_limit() {
+        let mut updater = CountingUpdater::new(None);
+        let result = HeadlessSystem::new(16, 1000).main_loop(&mut updater);
+
+        // 62 steps reach 992 ms, the 63rd crosses the limit
+        assert_eq!(result.frames, 63);
+        assert_eq!(result.elapsed_millis, 1008);
+        assert!(!result.exited);
+        assert_eq!(updater.updates.len(), 63);
+        assert!(updater.updates.iter().all(|millis| *millis == 16));
+    }
+
+    #[test]
+    fn main_loop_stops_on_exit() {
+        let mut updater = CountingUpdater::new(Some(5));
+        let result = HeadlessSystem::new(10, 1000).main_loop(&mut updater);
+
+        assert_eq!(result.frames, 5);
+        assert_eq!(result.elapsed_millis, 50);
+        assert!(result.exited);
+    }
+
+    #[test]
+    fn main_loop_zero_time_limit_runs_no_frames() {
+        let mut updater = CountingUpdater::new(None);
+        let result = HeadlessSystem::new(10, 0).main_loop(&mut updater);
+
+        assert_eq!(result.frames, 0);
+        assert_eq!(result.elapsed_millis, 0);
+        assert!(updater.updates.is_empty());
+    }
+
+    #[test]
+    fn zero_step_is_clamped() {
+        let system = HeadlessSystem::new(0, 5);
+        assert_eq!(system.step_millis(), 1);
+
+        let mut updater = CountingUpdater::new(None);
+        let result = system.main_loop(&mut updater);
+        assert_eq!(result.frames, 5);
+        assert_eq!(result.elapsed_millis, 5);
+    }
+
+    #[test]
+    fn replay_loop_uses_recorded_frame_times() {
+        let frames: Vec<RecordedFrame> = [5, 20, 7]
+            .iter()
+            .map(|millis| RecordedFrame {
+                millis: *millis,
+                actions: Vec::new(),
+            })
+            .collect();
+
+        let mut updater = CountingUpdater::new(None);
+        let result = HeadlessSystem::new(16, 1).replay_loop(&mut updater, &frames);
+
+        assert_eq!(result.frames, 3);
+        assert_eq!(result.elapsed_millis, 32);
+        assert!(!result.exited);
+        assert_eq!(updater.updates, vec![5, 20, 7]);
+    }
+
+    #[test]
+    fn replay_loop_stops_on_exit() {
+        let frames: Vec<RecordedFrame> = (0..10)
+            .map(|_| RecordedFrame {
+                millis: 10,
+                actions: Vec::new(),
+            })
+            .collect();
+
+        let mut updater = CountingUpdater::new(Some(4));
+        let result = HeadlessSystem::new(16, 1).replay_loop(&mut updater, &frames);
+
+        assert_eq!(result.frames, 4);
+        assert_eq!(result.elapsed_millis, 40);
+        assert!(result.exited);
+    }
+}
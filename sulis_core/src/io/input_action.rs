@@ -20,15 +20,18 @@ use std::rc::Rc;
 use serde::{Deserialize, Serialize};
 
 use crate::io::event::{ClickKind, Kind};
-use crate::io::{keyboard_event::Key, Event};
+use crate::io::{keyboard_event::Key, Event, InputRecorder};
 use crate::ui::{Cursor, Widget};
 
+#[derive(Debug, Deserialize, Serialize, Copy, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct InputAction {
     pub kind: InputActionKind,
     pub state: InputActionState,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Debug, Deserialize, Serialize, Copy, Clone)]
+#[serde(deny_unknown_fields)]
 pub enum InputActionState {
     Started,
     Stopped,
@@ -45,6 +48,7 @@ pub enum InputActionKind {
     ToggleMap,
     ToggleJournal,
     ToggleFormation,
+    ToggleAITrace,
     Back,
     EndTurn,
     Rest,
@@ -151,6 +155,8 @@ impl InputAction {
 
     pub fn handle(self, root: &Rc<RefCell<Widget>>) {
         use InputActionKind::*;
+        InputRecorder::record_action(self);
+
         // don't spam tons of mouse move actions in the event logs
         match self.kind {
             MouseMove(_, _) => (),
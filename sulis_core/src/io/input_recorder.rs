@@ -0,0 +1,85 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+
+use crate::io::InputAction;
+
+thread_local! {
+    static RECORDER: RefCell<Option<InputRecorder>> = const { RefCell::new(None) };
+}
+
+/// A single frame of recorded input.  Each action is delivered to the
+/// UI tree, and then the `ControlFlowUpdater` is updated with `millis`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RecordedFrame {
+    pub millis: u32,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<InputAction>,
+}
+
+/// Records every `InputAction` handled by the UI, along with the frame
+/// time at which it was delivered, so that the input may later be replayed.
+#[derive(Default)]
+pub struct InputRecorder {
+    frames: Vec<RecordedFrame>,
+    current: Vec<InputAction>,
+}
+
+impl InputRecorder {
+    /// Starts a new recording, discarding any recording already in progress
+    pub fn start() {
+        info!("Starting input recording.");
+        RECORDER.with(|r| *r.borrow_mut() = Some(InputRecorder::default()));
+    }
+
+    /// Stops the current recording, returning all recorded frames, or `None`
+    /// if no recording was in progress
+    pub fn finish() -> Option<Vec<RecordedFrame>> {
+        let recorder = RECORDER.with(|r| r.borrow_mut().take())?;
+        info!(
+            "Finished input recording of {} frames.",
+            recorder.frames.len()
+        );
+        Some(recorder.frames)
+    }
+
+    pub fn is_recording() -> bool {
+        RECORDER.with(|r| r.borrow().is_some())
+    }
+
+    pub(crate) fn record_action(action: InputAction) {
+        RECORDER.with(|r| {
+            if let Some(recorder) = r.borrow_mut().as_mut() {
+                recorder.current.push(action);
+            }
+        });
+    }
+
+    /// Ends the current frame, which was updated with the specified `millis`
+    pub fn end_frame(millis: u32) {
+        RECORDER.with(|r| {
+            if let Some(recorder) = r.borrow_mut().as_mut() {
+                let actions = std::mem::take(&mut recorder.current);
+                recorder.frames.push(RecordedFrame { millis, actions });
+            }
+        });
+    }
+}
@@ -30,6 +30,8 @@ pub use self::spritesheet::Spritesheet;
 mod font;
 pub use self::font::Font;
 
+pub mod localization;
+
 pub mod yaml_resource_set;
 pub use self::yaml_resource_set::YamlResourceKind;
 pub use self::yaml_resource_set::YamlResourceSet;
@@ -57,6 +59,32 @@ use crate::util::{self, invalid_data_error};
 
 thread_local! {
     static RESOURCE_SET: RefCell<ResourceSet> = RefCell::new(ResourceSet::default());
+    static LOAD_ERRORS: RefCell<Vec<LoadError>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A resource that was skipped while loading, because it could not be read
+/// or created
+#[derive(Debug, Clone)]
+pub struct LoadError {
+    pub kind: String,
+    pub id: String,
+    pub message: String,
+}
+
+/// Returns the errors for every resource skipped since resources were last
+/// loaded with `ResourceSet::load_resources`
+pub fn load_errors() -> Vec<LoadError> {
+    LOAD_ERRORS.with(|errors| errors.borrow().clone())
+}
+
+pub(crate) fn add_load_error(kind: &str, id: &str, message: String) {
+    LOAD_ERRORS.with(|errors| {
+        errors.borrow_mut().push(LoadError {
+            kind: kind.to_string(),
+            id: id.to_string(),
+            message,
+        })
+    });
 }
 
 #[derive(Default)]
@@ -78,6 +106,8 @@ impl ResourceSet {
             ));
         }
 
+        LOAD_ERRORS.with(|errors| errors.borrow_mut().clear());
+
         let yaml_start = std::time::Instant::now();
         let root = dirs.remove(0);
         let path = Path::new(&root);
@@ -107,6 +137,8 @@ impl ResourceSet {
             util::format_elapsed_secs(yaml_start.elapsed())
         );
 
+        localization::localize(&mut yaml);
+
         let builder_start = std::time::Instant::now();
         let builder_set = ResourceBuilderSet::from_yaml(&mut yaml)?;
         log::info!(
@@ -277,6 +309,16 @@ impl ResourceSet {
         RESOURCE_SET.with(|r| Rc::clone(r.borrow().themes.default_theme()))
     }
 
+    /// Installs only the default theme, so widgets may be created in tests
+    /// without loading any resources
+    #[cfg(test)]
+    pub(crate) fn init_default_theme() {
+        let theme = Theme::default();
+        let mut themes = HashMap::new();
+        themes.insert(theme.id.clone(), Rc::new(theme));
+        RESOURCE_SET.with(|r| r.borrow_mut().themes = ThemeSet::new(themes));
+    }
+
     pub fn compute_theme_id(parent_id: &str, id: &str) -> String {
         RESOURCE_SET.with(|r| r.borrow().themes.compute_theme_id(parent_id, id))
     }
@@ -426,6 +468,7 @@ fn insert_if_ok_boxed<K: Eq + Hash + Display, V: ?Sized>(
 fn warn_on_insert<K: Display>(type_str: &str, key: K, error: Error) {
     warn!("Error in {} with id '{}'", type_str, key);
     warn!("{}", error);
+    add_load_error(type_str, &key.to_string(), error.to_string());
 }
 
 pub fn subdirs<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>, Error> {
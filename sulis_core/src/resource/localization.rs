@@ -0,0 +1,392 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Translation of player facing text.  Each translatable string in the
+//! YAML resources is identified by a string ID built from the resource kind,
+//! the resource ID, and the path of keys to the string, such as
+//! `conversation.tervald.nodes.start.text`.  String tables in the `strings`
+//! directory of the data, campaign, and mod layers map these IDs to text for
+//! a given language.  When resources are loaded, each translatable string is
+//! replaced with its text in the configured language, falling back to the
+//! base language table and then to the text written in the resource itself.
+//!
+//! Elements of a sequence, such as conversation responses or cutscene frames,
+//! are identified by their `id` if they have one, and otherwise by their
+//! index.  Index based IDs change when a list patch inserts elements before
+//! them.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::config::Config;
+use crate::resource::read_builders;
+use crate::resource::yaml_resource_set::{self, YamlResourceKind, YamlResourceSet};
+
+/// The language that resources are written in
+pub const BASE_LANGUAGE: &str = "en";
+
+/// For each kind of resource, the string ID prefix and the paths of the
+/// translatable strings.  A `*` matches any single key or sequence index,
+/// while `**` matches any number of keys.
+const TRANSLATABLE: [(YamlResourceKind, &str, &[&str]); 13] = [
+    (
+        YamlResourceKind::TopLevel,
+        "",
+        &["name", "description", "hints.*", "hour_names.*"],
+    ),
+    (
+        YamlResourceKind::Theme,
+        "theme",
+        &["themes.**.text", "themes.**.custom.tooltip"],
+    ),
+    (
+        YamlResourceKind::Ability,
+        "ability",
+        &[
+            "name",
+            "description",
+            "active.short_description",
+            "upgrades.*.description",
+        ],
+    ),
+    (YamlResourceKind::Actor, "actor", &["name"]),
+    (
+        YamlResourceKind::Class,
+        "class",
+        &["name", "description", "kits.*.name", "kits.*.description"],
+    ),
+    (YamlResourceKind::Condition, "condition", &["name"]),
+    (
+        YamlResourceKind::Conversation,
+        "conversation",
+        &["nodes.*.text", "nodes.*.responses.*.text"],
+    ),
+    (YamlResourceKind::Cutscene, "cutscene", &["frames.*.text"]),
+    (
+        YamlResourceKind::Item,
+        "item",
+        &["name", "usable.short_description"],
+    ),
+    (YamlResourceKind::ItemAdjective, "item_adjective", &["name"]),
+    (YamlResourceKind::Prop, "prop", &["name"]),
+    (
+        YamlResourceKind::Quest,
+        "quest",
+        &["name", "entries.*.description"],
+    ),
+    (YamlResourceKind::Race, "race", &["name", "description"]),
+];
+
+thread_local! {
+    static STRINGS: RefCell<StringTables> = RefCell::new(StringTables::default());
+}
+
+#[derive(Default)]
+struct StringTables {
+    language: HashMap<String, String>,
+    base: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StringTable {
+    /// The language code, such as `en` or `fr`
+    pub id: String,
+
+    #[serde(default)]
+    pub strings: HashMap<String, String>,
+}
+
+/// Returns the text of the specified string ID in the configured language,
+/// or in the base language if it has not been translated
+pub fn text(id: &str) -> Option<String> {
+    STRINGS.with(|s| {
+        let s = s.borrow();
+        s.language.get(id).or_else(|| s.base.get(id)).cloned()
+    })
+}
+
+/// Reads the string tables from the specified resources for the configured
+/// language, and replaces each translatable string with its translation
+pub(crate) fn localize(yaml: &mut YamlResourceSet) {
+    let language = Config::resources_config().language;
+    let mut tables = read_string_tables(yaml);
+
+    let base = tables.remove(BASE_LANGUAGE).unwrap_or_default();
+    let language = if language == BASE_LANGUAGE {
+        HashMap::new()
+    } else {
+        match tables.remove(&language) {
+            Some(table) => table,
+            None => {
+                warn!("No string table found for language '{}'", language);
+                HashMap::new()
+            }
+        }
+    };
+
+    STRINGS.with(|s| *s.borrow_mut() = StringTables { language, base });
+
+    for (kind, prefix, patterns) in TRANSLATABLE.iter() {
+        let resources = match yaml.resources.get_mut(kind) {
+            None => continue,
+            Some(resources) => resources,
+        };
+
+        for (id, value) in resources.iter_mut() {
+            for pattern in patterns.iter() {
+                let pattern: Vec<&str> = pattern.split('.').collect();
+                visit_strings(value, &pattern, &string_id(prefix, id), &mut |id, text| {
+                    if let Some(translated) = self::text(&id) {
+                        *text = translated;
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Removes the string tables from the specified resources, returning the
+/// table of strings for each language
+pub fn read_string_tables(yaml: &mut YamlResourceSet) -> HashMap<String, HashMap<String, String>> {
+    match read_builders::<StringTable>(yaml, YamlResourceKind::StringTable) {
+        Ok(tables) => tables
+            .into_iter()
+            .map(|(language, table)| (language, table.strings))
+            .collect(),
+        Err(e) => {
+            warn!("Unable to read string tables: {}", e);
+            super::add_load_error("string_table", "", e.to_string());
+            HashMap::new()
+        }
+    }
+}
+
+/// Returns the string ID and text of every translatable string in the
+/// specified resources, as written in the resources
+pub fn translatable_strings(yaml: &YamlResourceSet) -> BTreeMap<String, String> {
+    let mut strings = BTreeMap::new();
+    for (kind, prefix, patterns) in TRANSLATABLE.iter() {
+        let resources = match yaml.resources.get(kind) {
+            None => continue,
+            Some(resources) => resources,
+        };
+
+        for (id, value) in resources.iter() {
+            let mut value = value.clone();
+            for pattern in patterns.iter() {
+                let pattern: Vec<&str> = pattern.split('.').collect();
+                visit_strings(
+                    &mut value,
+                    &pattern,
+                    &string_id(prefix, id),
+                    &mut |id, text| {
+                        strings.insert(id, text.to_string());
+                    },
+                );
+            }
+        }
+    }
+    strings
+}
+
+fn string_id(prefix: &str, id: &str) -> String {
+    if prefix.is_empty() {
+        id.to_string()
+    } else {
+        format!("{prefix}.{id}")
+    }
+}
+
+fn visit_strings(
+    value: &mut Value,
+    pattern: &[&str],
+    path: &str,
+    f: &mut dyn FnMut(String, &mut String),
+) {
+    let (segment, rest) = match pattern.split_first() {
+        None => {
+            if let Value::String(text) = value {
+                f(path.to_string(), text);
+            }
+            return;
+        }
+        Some(split) => split,
+    };
+
+    match *segment {
+        "**" => {
+            visit_strings(value, rest, path, f);
+            visit_children(value, pattern, path, f);
+        }
+        "*" => visit_children(value, rest, path, f),
+        key => {
+            if let Value::Mapping(map) = value {
+                let key = Value::String(key.to_string());
+                if let Some(child) = map.get_mut(&key) {
+                    let path = yaml_resource_set::key_path(path, &key);
+                    visit_strings(child, rest, &path, f);
+                }
+            }
+        }
+    }
+}
+
+fn visit_children(
+    value: &mut Value,
+    pattern: &[&str],
+    path: &str,
+    f: &mut dyn FnMut(String, &mut String),
+) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, child) in map.iter_mut() {
+                let path = yaml_resource_set::key_path(path, key);
+                visit_strings(child, pattern, &path, f);
+            }
+        }
+        Value::Sequence(seq) => {
+            for (index, child) in seq.iter_mut().enumerate() {
+                let key = match child.get("id") {
+                    Some(Value::String(id)) => Value::String(id.to_string()),
+                    _ => Value::from(index),
+                };
+                let path = yaml_resource_set::key_path(path, &key);
+                visit_strings(child, pattern, &path, f);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(src: &str) -> Value {
+        serde_yaml::from_str(src).unwrap()
+    }
+
+    fn strings(value: &str, pattern: &str) -> Vec<(String, String)> {
+        let mut value = yaml(value);
+        let pattern: Vec<&str> = pattern.split('.').collect();
+        let mut strings = Vec::new();
+        visit_strings(&mut value, &pattern, "res", &mut |id, text| {
+            strings.push((id, text.to_string()));
+        });
+        strings.sort();
+        strings
+    }
+
+    fn pair(id: &str, text: &str) -> (String, String) {
+        (id.to_string(), text.to_string())
+    }
+
+    #[test]
+    fn star_matches_one_key_or_index() {
+        assert_eq!(
+            strings("{ kits: [{ name: a }, { name: b }] }", "kits.*.name"),
+            vec![pair("res.kits.0.name", "a"), pair("res.kits.1.name", "b")]
+        );
+        assert_eq!(
+            strings(
+                "{ nodes: { start: { text: a }, end: { text: b } } }",
+                "nodes.*.text"
+            ),
+            vec![
+                pair("res.nodes.end.text", "b"),
+                pair("res.nodes.start.text", "a")
+            ]
+        );
+        assert!(strings("{ a: { b: { text: x } } }", "*.text").is_empty());
+    }
+
+    #[test]
+    fn double_star_matches_any_depth() {
+        assert_eq!(
+            strings("{ text: a, b: { text: b, c: { text: c } } }", "**.text"),
+            vec![
+                pair("res.b.c.text", "c"),
+                pair("res.b.text", "b"),
+                pair("res.text", "a")
+            ]
+        );
+    }
+
+    #[test]
+    fn sequence_elements_use_their_id() {
+        assert_eq!(
+            strings(
+                "{ frames: [{ text: a }, { id: intro, text: b }] }",
+                "frames.*.text"
+            ),
+            vec![
+                pair("res.frames.0.text", "a"),
+                pair("res.frames.intro.text", "b")
+            ]
+        );
+    }
+
+    #[test]
+    fn text_falls_back_to_base_language() {
+        let table = |strings: &[(&str, &str)]| {
+            strings
+                .iter()
+                .map(|(id, text)| (id.to_string(), text.to_string()))
+                .collect()
+        };
+        STRINGS.with(|s| {
+            *s.borrow_mut() = StringTables {
+                language: table(&[("a", "translated")]),
+                base: table(&[("a", "base"), ("b", "base")]),
+            }
+        });
+
+        assert_eq!(text("a").as_deref(), Some("translated"));
+        assert_eq!(text("b").as_deref(), Some("base"));
+        assert_eq!(text("c"), None);
+    }
+
+    #[test]
+    fn localize_keeps_untranslated_text() {
+        let mut set = YamlResourceSet {
+            resources: HashMap::new(),
+            overrides: HashMap::new(),
+        };
+        let mut add = |kind, id: &str, value: &str| {
+            set.resources
+                .entry(kind)
+                .or_default()
+                .insert(id.to_string(), yaml(value));
+        };
+        add(
+            YamlResourceKind::StringTable,
+            BASE_LANGUAGE,
+            "{ id: en, strings: { actor.a.name: Translated } }",
+        );
+        add(YamlResourceKind::Actor, "a", "{ id: a, name: Written }");
+        add(YamlResourceKind::Actor, "b", "{ id: b, name: Written }");
+
+        localize(&mut set);
+
+        let actors = &set.resources[&YamlResourceKind::Actor];
+        assert_eq!(actors["a"]["name"], yaml("Translated"));
+        assert_eq!(actors["b"]["name"], yaml("Written"));
+    }
+}
@@ -130,7 +130,10 @@ fn read_builders_internal<T: serde::de::DeserializeOwned>(
             let builder: T = match read_builder_internal(entry) {
                 Err(e) => {
                     warn!("Error in YAML file merged from {:?}", files);
-                    return Err(e);
+                    return Err(Error::new(
+                        e.kind(),
+                        format!("{kind:?} '{id}' merged from {files:?}: {e}"),
+                    ));
                 }
                 Ok(val) => val,
             };
@@ -15,7 +15,7 @@
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
 use serde_yaml::{self, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::Error;
 use std::path::Path;
@@ -26,8 +26,15 @@ use std::path::Path;
 /// adding new resources or keys to already existing resources.
 pub struct YamlResourceSet {
     pub resources: HashMap<YamlResourceKind, HashMap<String, Value>>,
+
+    /// For each resource that was merged from more than one file, the
+    /// dotted path of each key set by a later file, mapped to the
+    /// directory of the last layer that set it
+    pub overrides: HashMap<YamlResourceKind, HashMap<String, KeyOverrides>>,
 }
 
+pub type KeyOverrides = BTreeMap<String, String>;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum YamlResourceKind {
     TopLevel,
@@ -49,6 +56,7 @@ pub enum YamlResourceKind {
     AiTemplate,
     Area,
     Class,
+    Condition,
     Conversation,
     Cutscene,
     Encounter,
@@ -61,6 +69,8 @@ pub enum YamlResourceKind {
     Size,
     Tile,
     Generator,
+
+    StringTable,
 }
 
 impl YamlResourceKind {
@@ -99,6 +109,7 @@ impl YamlResourceKind {
             "ai" => AiTemplate,
             "areas" => Area,
             "classes" => Class,
+            "conditions" => Condition,
             "conversations" => Conversation,
             "cutscenes" => Cutscene,
             "encounters" => Encounter,
@@ -111,6 +122,7 @@ impl YamlResourceKind {
             "sizes" => Size,
             "tiles" => Tile,
             "generators" => Generator,
+            "strings" => StringTable,
             "scripts" | "theme" => Skip,
             _ => return None,
         })
@@ -119,7 +131,10 @@ impl YamlResourceKind {
 
 impl YamlResourceSet {
     pub fn new(data_dir: &Path) -> Result<YamlResourceSet, Error> {
-        let mut resources = HashMap::new();
+        let mut set = YamlResourceSet {
+            resources: HashMap::new(),
+            overrides: HashMap::new(),
+        };
 
         debug!(
             "Parsing YAML in '{}'",
@@ -130,10 +145,10 @@ impl YamlResourceSet {
             data_dir,
             data_dir,
             Some(YamlResourceKind::TopLevel),
-            &mut resources,
+            &mut set,
         );
 
-        Ok(YamlResourceSet { resources })
+        Ok(set)
     }
 
     pub fn append(&mut self, dir: &Path) {
@@ -142,12 +157,7 @@ impl YamlResourceSet {
             dir.to_string_lossy().to_string()
         );
 
-        read_recursive(
-            dir,
-            dir,
-            Some(YamlResourceKind::TopLevel),
-            &mut self.resources,
-        );
+        read_recursive(dir, dir, Some(YamlResourceKind::TopLevel), self);
     }
 }
 
@@ -155,7 +165,7 @@ fn read_recursive(
     dir: &Path,
     top_level: &Path,
     kind: Option<YamlResourceKind>,
-    resources: &mut HashMap<YamlResourceKind, HashMap<String, Value>>,
+    set: &mut YamlResourceSet,
 ) {
     let dir_str = dir.to_string_lossy().to_string();
     let dir_entries = match fs::read_dir(dir) {
@@ -188,7 +198,7 @@ fn read_recursive(
                 Some(kind) => Some(kind),
             };
 
-            read_recursive(&path, top_level, next_kind, resources);
+            read_recursive(&path, top_level, next_kind, set);
         } else if path.is_file() {
             match kind {
                 None => {
@@ -198,7 +208,7 @@ fn read_recursive(
                     );
                 }
                 Some(kind) => {
-                    read_file(&dir_str, &path, kind, resources);
+                    read_file(&dir_str, top_level, &path, kind, set);
                 }
             }
         }
@@ -207,9 +217,10 @@ fn read_recursive(
 
 fn read_file(
     dir_str: &str,
+    top_level: &Path,
     path: &Path,
     kind: YamlResourceKind,
-    resources: &mut HashMap<YamlResourceKind, HashMap<String, Value>>,
+    set: &mut YamlResourceSet,
 ) {
     let path_str = path.to_string_lossy().to_string();
 
@@ -231,6 +242,7 @@ fn read_file(
         Err(e) => {
             warn!("Error parsing '{}' as YAML:", path_str);
             warn!("{}", e);
+            super::add_load_error("file", &path_str, e.to_string());
             return;
         }
     };
@@ -240,21 +252,41 @@ fn read_file(
             Value::String(ref s) => s.to_string(),
             _ => {
                 warn!("Top level ID is not a string in '{}'", path_str);
+                super::add_load_error("file", &path_str, "Top level ID is not a string".into());
                 return;
             }
         },
         None => {
             warn!("Unable to extract top level ID from '{}'", path_str);
+            super::add_load_error("file", &path_str, "Unable to extract top level ID".into());
             return;
         }
     };
 
-    let map = resources.entry(kind).or_default();
+    let map = set.resources.entry(kind).or_default();
     // use of entry API here seems to require us to clone our value since
     // we want to either append it or insert it
     //map.entry(id).and_modify(|entry| merge_doc(entry, value)).or_insert(value);
     if let Some(ref mut entry) = map.get_mut(&id) {
-        merge_doc(dir_str, &path_str, entry, value);
+        // a resource split across files in one layer does not override itself
+        let same_layer = defined_in_layer(entry, top_level);
+
+        let mut keys = Vec::new();
+        merge_doc(dir_str, &path_str, entry, value, &mut keys);
+        if same_layer {
+            return;
+        }
+
+        let layer = top_level.to_string_lossy().to_string();
+        let overrides = set
+            .overrides
+            .entry(kind)
+            .or_default()
+            .entry(id)
+            .or_default();
+        for key in keys {
+            overrides.insert(key, layer.clone());
+        }
         return;
     }
 
@@ -282,10 +314,22 @@ fn read_file(
     map.insert(id, value);
 }
 
+/// Returns true if the resource was first read from a file in the layer at
+/// `top_level`
+fn defined_in_layer(resource: &Value, top_level: &Path) -> bool {
+    match resource.get(FILE_VAL_STR) {
+        Some(Value::Sequence(files)) => files
+            .first()
+            .and_then(Value::as_str)
+            .is_some_and(|file| Path::new(file).starts_with(top_level)),
+        _ => false,
+    }
+}
+
 pub const DIRECTORY_VAL_STR: &str = "__directory__";
 pub const FILE_VAL_STR: &str = "__file__";
 
-fn merge_doc(dir: &str, name: &str, base: &mut Value, append: Value) {
+fn merge_doc(dir: &str, name: &str, base: &mut Value, append: Value, keys: &mut Vec<String>) {
     let directory_val = Value::String(DIRECTORY_VAL_STR.to_string());
     let file_val = Value::String(FILE_VAL_STR.to_string());
 
@@ -305,7 +349,7 @@ fn merge_doc(dir: &str, name: &str, base: &mut Value, append: Value) {
             }
 
             match append {
-                Value::Mapping(append) => merge_map(dir, name, mapping, append),
+                Value::Mapping(append) => merge_map(dir, name, mapping, append, "", keys),
                 _ => warn!(
                     "Unable to append '{}' to base YAML as it is not a mapping",
                     name
@@ -324,15 +368,19 @@ fn merge_map(
     name: &str,
     map: &mut serde_yaml::Mapping,
     mut append: serde_yaml::Mapping,
+    path: &str,
+    keys: &mut Vec<String>,
 ) {
     let clear_base_keys: Value = Value::String("clear_base_keys".to_string());
     let remove_base_keys: Value = Value::String("remove_base_keys".to_string());
+    let replace_base_keys: Value = Value::String("replace_base_keys".to_string());
 
     if let Some(clear) = append.remove(&clear_base_keys) {
         match clear {
             Value::Bool(val) => {
                 if val {
                     map.clear();
+                    keys.push(key_path(path, &clear_base_keys));
                 }
             }
             _ => warn!("clear_base_keys must be a boolean in '{}'", name),
@@ -344,6 +392,7 @@ fn merge_map(
             Value::Sequence(seq) => {
                 for value in seq {
                     map.remove(&value);
+                    keys.push(key_path(path, &value));
                 }
             }
             _ => warn!(
@@ -353,20 +402,57 @@ fn merge_map(
         }
     }
 
+    let replace = match append.remove(&replace_base_keys) {
+        None => Vec::new(),
+        Some(Value::Sequence(seq)) => seq,
+        Some(_) => {
+            warn!(
+                "replace_base_keys must be a sequence of key-strings in '{}'",
+                name
+            );
+            Vec::new()
+        }
+    };
+
     for (key, value) in append {
+        let key_path = key_path(path, &key);
+        if replace.contains(&key) {
+            keys.push(key_path);
+            map.insert(key, value);
+            continue;
+        }
+
+        if is_list_patch(&value) {
+            match map.get(&key) {
+                // a patch on a missing sequence acts on an empty one
+                None => {
+                    map.insert(key.clone(), Value::Sequence(Vec::new()));
+                }
+                Some(Value::Sequence(_)) | Some(Value::Mapping(_)) => (),
+                Some(_) => {
+                    warn!("Unable to apply list patch to '{:?}' in '{}'", key, name);
+                    continue;
+                }
+            }
+        }
+
         if let Some(ref mut base) = map.get_mut(&key) {
             match base {
                 Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => (),
                 Value::Sequence(ref mut seq) => {
                     match value {
                         Value::Sequence(append) => merge_sequence(dir, name, seq, append),
+                        Value::Mapping(patch) => patch_sequence(dir, name, seq, patch),
                         _ => warn!("Expected sequence for '{:?}' in '{}'", key, name),
                     }
+                    keys.push(key_path);
                     continue;
                 }
                 Value::Mapping(ref mut map) => {
                     match value {
-                        Value::Mapping(append) => merge_map(dir, name, map, append),
+                        Value::Mapping(append) => {
+                            merge_map(dir, name, map, append, &key_path, keys)
+                        }
                         _ => warn!("Expected mapping for '{:?}' in '{}'", key, name),
                     }
                     continue;
@@ -374,10 +460,98 @@ fn merge_map(
             }
         }
 
+        // the top level ID is always repeated to merge into the resource
+        if key_path != "id" {
+            keys.push(key_path);
+        }
         map.insert(key, value);
     }
 }
 
+/// Applies a list patch, a mapping given in place of a sequence.  Elements
+/// equal to any of the `remove` values are removed first, then the `insert`
+/// values are inserted at `index`, and finally the `append` values are added
+/// to the end.
+fn patch_sequence(
+    dir: &str,
+    name: &str,
+    seq: &mut serde_yaml::Sequence,
+    mut patch: serde_yaml::Mapping,
+) {
+    let remove_val = Value::String("remove".to_string());
+    let insert_val = Value::String("insert".to_string());
+    let index_val = Value::String("index".to_string());
+    let append_val = Value::String("append".to_string());
+
+    if let Some(remove) = patch.remove(&remove_val) {
+        match remove {
+            Value::Sequence(remove) => seq.retain(|value| !remove.contains(value)),
+            _ => warn!("List patch remove must be a sequence in '{}'", name),
+        }
+    }
+
+    let index = patch.remove(&index_val);
+    if let Some(insert) = patch.remove(&insert_val) {
+        let index = match index.map(|index| index.as_u64()) {
+            None => 0,
+            Some(Some(index)) => index as usize,
+            Some(None) => {
+                warn!("List patch index must be a positive integer in '{}'", name);
+                0
+            }
+        };
+
+        match insert {
+            Value::Sequence(insert) => {
+                let index = index.min(seq.len());
+                seq.splice(index..index, insert);
+            }
+            _ => warn!("List patch insert must be a sequence in '{}'", name),
+        }
+    }
+
+    if let Some(append) = patch.remove(&append_val) {
+        match append {
+            Value::Sequence(append) => merge_sequence(dir, name, seq, append),
+            _ => warn!("List patch append must be a sequence in '{}'", name),
+        }
+    }
+
+    for (key, _) in patch {
+        warn!("Unknown list patch key '{:?}' in '{}'", key, name);
+    }
+}
+
+/// Returns true if the value is a list patch, a mapping with only the list
+/// patch keys and at least one of `remove`, `insert`, or `append`
+fn is_list_patch(value: &Value) -> bool {
+    const KEYS: [&str; 4] = ["remove", "insert", "index", "append"];
+
+    let map = match value {
+        Value::Mapping(map) => map,
+        _ => return false,
+    };
+
+    let keys: Vec<_> = map.iter().map(|(key, _)| key.as_str()).collect();
+    keys.iter()
+        .all(|key| key.is_some_and(|key| KEYS.contains(&key)))
+        && keys.iter().any(|key| *key != Some("index"))
+}
+
+pub(crate) fn key_path(path: &str, key: &Value) -> String {
+    let key = match key {
+        Value::String(key) => key.to_string(),
+        Value::Number(key) => key.to_string(),
+        _ => format!("{key:?}"),
+    };
+
+    if path.is_empty() {
+        key
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
 fn merge_sequence(
     _dir: &str,
     _name: &str,
@@ -388,3 +562,210 @@ fn merge_sequence(
         seq.push(value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(src: &str) -> Value {
+        serde_yaml::from_str(src).unwrap()
+    }
+
+    fn merge(base: &str, append: &str) -> (Value, Vec<String>) {
+        let mut base = yaml(base);
+        let append = match yaml(append) {
+            Value::Mapping(append) => append,
+            _ => panic!("append must be a mapping"),
+        };
+
+        let mut keys = Vec::new();
+        match base {
+            Value::Mapping(ref mut map) => merge_map("dir", "test", map, append, "", &mut keys),
+            _ => panic!("base must be a mapping"),
+        }
+        // directives are removed from the mapping, which may reorder the keys
+        keys.sort();
+        (base, keys)
+    }
+
+    fn patch(base: &str, patch: &str) -> Value {
+        let mut seq = match yaml(base) {
+            Value::Sequence(seq) => seq,
+            _ => panic!("base must be a sequence"),
+        };
+        match yaml(patch) {
+            Value::Mapping(patch) => patch_sequence("dir", "test", &mut seq, patch),
+            _ => panic!("patch must be a mapping"),
+        }
+        Value::Sequence(seq)
+    }
+
+    #[test]
+    fn merges_mappings_and_appends_sequences() {
+        let (merged, keys) = merge(
+            "{ id: a, value: 1, list: [x], nested: { a: 1, b: 2 } }",
+            "{ id: a, value: 2, list: [y], nested: { b: 3, c: 4 } }",
+        );
+
+        assert_eq!(
+            merged,
+            yaml("{ id: a, value: 2, list: [x, y], nested: { a: 1, b: 3, c: 4 } }")
+        );
+        assert_eq!(keys, vec!["list", "nested.b", "nested.c", "value"]);
+    }
+
+    #[test]
+    fn replace_base_keys() {
+        let (merged, keys) = merge(
+            "{ list: [x], nested: { a: 1 }, other: [z] }",
+            "{ replace_base_keys: [list, nested], list: [y], nested: { b: 2 }, other: [w] }",
+        );
+
+        assert_eq!(
+            merged,
+            yaml("{ list: [y], nested: { b: 2 }, other: [z, w] }")
+        );
+        assert_eq!(keys, vec!["list", "nested", "other"]);
+    }
+
+    #[test]
+    fn replace_base_keys_in_nested_mapping() {
+        let (merged, keys) = merge(
+            "{ outer: { inner: { a: 1 }, list: [x] } }",
+            "{ outer: { replace_base_keys: [inner], inner: { b: 2 }, list: [y] } }",
+        );
+
+        assert_eq!(merged, yaml("{ outer: { inner: { b: 2 }, list: [x, y] } }"));
+        assert_eq!(keys, vec!["outer.inner", "outer.list"]);
+    }
+
+    #[test]
+    fn clear_and_remove_base_keys() {
+        let (merged, _) = merge("{ a: 1, b: 2, c: 3 }", "{ remove_base_keys: [a, b], d: 4 }");
+        assert_eq!(merged, yaml("{ c: 3, d: 4 }"));
+
+        let (merged, _) = merge("{ a: 1, b: 2 }", "{ clear_base_keys: true, c: 3 }");
+        assert_eq!(merged, yaml("{ c: 3 }"));
+    }
+
+    #[test]
+    fn records_overrides_only_from_later_layers() {
+        let root = std::env::temp_dir().join(format!("sulis_yaml_layers_{}", std::process::id()));
+        let write = |layer: &str, file: &str, data: &str| {
+            let dir = root.join(layer).join("actors");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(file), data).unwrap();
+        };
+        write("base", "a1.yml", "{ id: a, x: 1 }");
+        write("base", "a2.yml", "{ id: a, y: 2 }");
+        write("mod", "a.yml", "{ id: a, x: 3 }");
+
+        let mut set = YamlResourceSet::new(&root.join("base")).unwrap();
+        assert!(set.overrides.is_empty());
+
+        set.append(&root.join("mod"));
+        fs::remove_dir_all(&root).unwrap();
+
+        let overrides = &set.overrides[&YamlResourceKind::Actor]["a"];
+        let keys: Vec<_> = overrides.keys().collect();
+        assert_eq!(keys, vec!["x"]);
+        assert_eq!(overrides["x"], root.join("mod").to_string_lossy());
+    }
+
+    #[test]
+    fn patch_sequence_from_mapping() {
+        let (merged, keys) = merge(
+            "{ list: [a, b, c] }",
+            "{ list: { remove: [b], append: [d] } }",
+        );
+
+        assert_eq!(merged, yaml("{ list: [a, c, d] }"));
+        assert_eq!(keys, vec!["list"]);
+    }
+
+    #[test]
+    fn patch_missing_sequence() {
+        let (merged, keys) = merge("{ other: 1 }", "{ list: { insert: [x], append: [y] } }");
+
+        assert_eq!(merged, yaml("{ other: 1, list: [x, y] }"));
+        assert_eq!(keys, vec!["list"]);
+    }
+
+    #[test]
+    fn patch_scalar_is_ignored() {
+        let (merged, keys) = merge("{ list: 1 }", "{ list: { append: [x] } }");
+
+        assert_eq!(merged, yaml("{ list: 1 }"));
+        assert!(keys.is_empty());
+    }
+
+    #[test]
+    fn patch_index_without_insert() {
+        assert_eq!(
+            patch("[a, b]", "{ index: 1, append: [c] }"),
+            yaml("[a, b, c]")
+        );
+    }
+
+    #[test]
+    fn patch_remove() {
+        assert_eq!(
+            patch("[a, b, a, c]", "{ remove: [a, missing] }"),
+            yaml("[b, c]")
+        );
+    }
+
+    #[test]
+    fn patch_insert() {
+        assert_eq!(patch("[a, b]", "{ insert: [x, y] }"), yaml("[x, y, a, b]"));
+        assert_eq!(
+            patch("[a, b]", "{ insert: [x, y], index: 1 }"),
+            yaml("[a, x, y, b]")
+        );
+        assert_eq!(
+            patch("[a, b]", "{ insert: [x], index: 2 }"),
+            yaml("[a, b, x]")
+        );
+    }
+
+    #[test]
+    fn patch_insert_index_past_end() {
+        assert_eq!(
+            patch("[a, b]", "{ insert: [x], index: 10 }"),
+            yaml("[a, b, x]")
+        );
+        assert_eq!(patch("[]", "{ insert: [x], index: 3 }"), yaml("[x]"));
+    }
+
+    #[test]
+    fn patch_invalid_index_inserts_at_start() {
+        assert_eq!(
+            patch("[a, b]", "{ insert: [x], index: -1 }"),
+            yaml("[x, a, b]")
+        );
+    }
+
+    #[test]
+    fn patch_append() {
+        assert_eq!(patch("[a]", "{ append: [b, c] }"), yaml("[a, b, c]"));
+    }
+
+    #[test]
+    fn patch_applies_remove_then_insert_then_append() {
+        // the index refers to the list after removal, and appended values
+        // are not affected by the removal
+        assert_eq!(
+            patch(
+                "[a, b, c]",
+                "{ append: [a], insert: [x], index: 1, remove: [a] }"
+            ),
+            yaml("[b, x, c, a]")
+        );
+
+        // inserted values are not removed
+        assert_eq!(
+            patch("[a, b]", "{ remove: [x], insert: [x] }"),
+            yaml("[x, a, b]")
+        );
+    }
+}
@@ -22,7 +22,9 @@ pub use self::size::Size;
 
 use serde::{Deserialize, Serialize};
 
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::f32;
 use std::fmt;
 use std::fs;
@@ -34,7 +36,7 @@ use std::time::Duration;
 
 use flexi_logger::{opt_format, Duplicate, FileSpec, LogSpecBuilder, Logger, LoggerHandle};
 use log::LevelFilter;
-use rand::{self, distributions::uniform::SampleUniform, seq::SliceRandom, Rng};
+use rand::{self, distributions::uniform::SampleUniform, seq::SliceRandom, Rng, RngCore};
 use rand_pcg::Pcg64Mcg;
 
 use crate::config::{self, Config};
@@ -66,12 +68,46 @@ pub fn approx_eq(a: f32, b: f32) -> bool {
     i32::abs(a_int - b_int) <= MAX_ULPS
 }
 
-#[derive(Clone)]
+thread_local! {
+    static RANDOM: RefCell<ReproducibleRandom> = RefCell::new(ReproducibleRandom::new(None));
+}
+
+/// A seedable random number generator.  The state is tracked as the seed
+/// and the number of steps taken since seeding, so that it may be saved and
+/// later restored exactly.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "RandomState", into = "RandomState")]
 pub struct ReproducibleRandom {
     seed: u128,
+    steps: u128,
     gen: Pcg64Mcg,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RandomState {
+    seed: u128,
+    steps: u128,
+}
+
+impl From<RandomState> for ReproducibleRandom {
+    fn from(state: RandomState) -> ReproducibleRandom {
+        let mut random = ReproducibleRandom::new(Some(state.seed));
+        random.gen.advance(state.steps);
+        random.steps = state.steps;
+        random
+    }
+}
+
+impl From<ReproducibleRandom> for RandomState {
+    fn from(random: ReproducibleRandom) -> RandomState {
+        RandomState {
+            seed: random.seed,
+            steps: random.steps,
+        }
+    }
+}
+
 impl ReproducibleRandom {
     pub fn new(seed: Option<u128>) -> ReproducibleRandom {
         // TODO only seed with u64 for now because serde_yaml doesn't serialize u128 correctly
@@ -79,16 +115,17 @@ impl ReproducibleRandom {
 
         ReproducibleRandom {
             seed,
+            steps: 0,
             gen: Pcg64Mcg::new(seed),
         }
     }
 
     pub fn gen<T: SampleUniform + PartialOrd>(&mut self, min: T, max: T) -> T {
-        self.gen.gen_range(min..max)
+        self.gen_range(min..max)
     }
 
     pub fn shuffle<T>(&mut self, values: &mut [T]) {
-        values.shuffle(&mut self.gen);
+        values.shuffle(self);
     }
 
     pub fn seed(&self) -> u128 {
@@ -96,18 +133,63 @@ impl ReproducibleRandom {
     }
 }
 
+impl RngCore for ReproducibleRandom {
+    fn next_u32(&mut self) -> u32 {
+        self.steps += 1;
+        self.gen.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.steps += 1;
+        self.gen.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
 impl std::fmt::Debug for ReproducibleRandom {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let state = serde_json::to_string(&self.gen).map_err(|_| std::fmt::Error)?;
-        write!(f, "Random: {state}")
+        write!(f, "Random: seed {}, step {}", self.seed, self.steps)
     }
 }
 
+/// Replaces the random number generator used for all gameplay randomness,
+/// such as by `gen_rand` and `shuffle`.  This allows the gameplay random
+/// sequence to be pinned to a specific seed, or restored from a save.
+pub fn set_random(random: ReproducibleRandom) {
+    RANDOM.with(|r| *r.borrow_mut() = random);
+}
+
+/// Returns a copy of the current state of the gameplay random number generator
+pub fn get_random() -> ReproducibleRandom {
+    RANDOM.with(|r| r.borrow().clone())
+}
+
+/// Shuffles the values using the gameplay random number generator
 pub fn shuffle<T>(values: &mut [T]) {
-    values.shuffle(&mut rand::thread_rng());
+    RANDOM.with(|r| r.borrow_mut().shuffle(values));
 }
 
+/// Generates a random value in the range [min, max) using the gameplay
+/// random number generator
 pub fn gen_rand<T: SampleUniform + PartialOrd>(min: T, max: T) -> T {
+    RANDOM.with(|r| r.borrow_mut().gen(min, max))
+}
+
+/// Generates a random value in the range [min, max) without using the
+/// gameplay random number generator.  This should be used for purely
+/// cosmetic effects, which must not change the gameplay random sequence.
+pub fn gen_visual_rand<T: SampleUniform + PartialOrd>(min: T, max: T) -> T {
     rand::thread_rng().gen_range(min..max)
 }
 
@@ -121,9 +203,42 @@ fn active_resources_file_path() -> PathBuf {
 pub struct ActiveResources {
     pub campaign: Option<String>,
     pub mods: Vec<String>,
+
+    /// The mods last selected for each campaign directory
+    #[serde(default)]
+    pub campaign_mods: HashMap<String, Vec<String>>,
 }
 
 impl ActiveResources {
+    /// Sets the active campaign directory.  The mods last selected for that
+    /// campaign become active, or no mods if none have been selected for it
+    /// yet.
+    pub fn set_campaign(&mut self, dir: String) {
+        self.mods = self.campaign_mods.get(&dir).cloned().unwrap_or_default();
+        self.campaign = Some(dir);
+    }
+
+    /// Files written before mods were remembered for each campaign only hold
+    /// the mods of the campaign active at the time, so they are remembered
+    /// for that campaign
+    fn remember_legacy_mods(&mut self) {
+        if let Some(dir) = &self.campaign {
+            if !self.mods.is_empty() && !self.campaign_mods.contains_key(dir) {
+                self.campaign_mods
+                    .insert(dir.to_string(), self.mods.clone());
+            }
+        }
+    }
+
+    /// Sets the active mod directories, remembering them for the active
+    /// campaign
+    pub fn set_mods(&mut self, mods: Vec<String>) {
+        if let Some(dir) = &self.campaign {
+            self.campaign_mods.insert(dir.to_string(), mods.clone());
+        }
+        self.mods = mods;
+    }
+
     pub fn read() -> ActiveResources {
         let path = active_resources_file_path();
 
@@ -135,7 +250,7 @@ impl ActiveResources {
             }
         };
 
-        let active_resources: ActiveResources = match serde_yaml::from_str(&data) {
+        let mut active_resources: ActiveResources = match serde_yaml::from_str(&data) {
             Ok(val) => val,
             Err(e) => {
                 warn!("Error reading active resources file");
@@ -144,6 +259,7 @@ impl ActiveResources {
             }
         };
 
+        active_resources.remember_legacy_mods();
         active_resources
     }
 
@@ -417,3 +533,25 @@ fn create_user_dirs() {
     mods_dir.push(&res.mods_directory);
     config::create_dir_and_warn(&mods_dir);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn campaign_without_remembered_mods_has_none() {
+        let mut active: ActiveResources =
+            serde_yaml::from_str("{ campaign: first, mods: [a, b] }").unwrap();
+        active.remember_legacy_mods();
+
+        active.set_campaign("second".to_string());
+        assert!(active.mods.is_empty());
+
+        active.set_mods(vec!["c".to_string()]);
+        active.set_campaign("first".to_string());
+        assert_eq!(active.mods, vec!["a", "b"]);
+
+        active.set_campaign("second".to_string());
+        assert_eq!(active.mods, vec!["c"]);
+    }
+}
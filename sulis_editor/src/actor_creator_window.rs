@@ -112,6 +112,7 @@ impl ActorCreatorWindow {
             reward: None,
             abilities: Vec::new(),
             ai: None,
+            immunities: Vec::new(),
         };
 
         match write_to_file(&filename, &actor) {
@@ -163,6 +163,10 @@ pub struct Actor {
     pub abilities: Vec<OwnedAbility>,
 
     pub ai: Option<Rc<AITemplate>>,
+
+    /// Categories of effect, such as `fear` or `poison`, this actor cannot
+    /// be affected by, in addition to those of its race
+    pub immunities: Vec<String>,
 }
 
 impl PartialEq for Actor {
@@ -243,6 +247,7 @@ impl Actor {
             reward: other.reward.clone(),
             abilities,
             ai: other.ai.clone(),
+            immunities: other.immunities.clone(),
         }
     }
 
@@ -367,9 +372,17 @@ impl Actor {
             hair_color: builder.hair_color,
             abilities,
             ai,
+            immunities: builder.immunities,
         })
     }
 
+    /// Returns true if this actor or its race is immune to the specified
+    /// category of effect
+    pub fn is_immune(&self, category: &str) -> bool {
+        self.immunities.iter().any(|immunity| immunity == category)
+            || self.race.is_immune(category)
+    }
+
     pub fn faction(&self) -> Faction {
         self.faction
     }
@@ -472,4 +485,7 @@ pub struct ActorBuilder {
     pub reward: Option<RewardBuilder>,
     pub abilities: Vec<String>,
     pub ai: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub immunities: Vec<String>,
 }
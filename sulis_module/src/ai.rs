@@ -29,12 +29,33 @@ pub enum FuncKind {
     AiAction,
 }
 
+/// How an AI template decides on the actions for its entity each turn
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum AIBackend {
+    /// Calls the `ai_action` function, or the `AiAction` hook, in the
+    /// template's script
+    Script,
+
+    /// Scores the available actions natively, using the AI data of the
+    /// entity's abilities and items.  An `AiAction` hook, if present, is
+    /// still used in place of the native AI.
+    Native,
+}
+
+fn default_backend() -> AIBackend {
+    AIBackend::Script
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct AITemplate {
     pub id: String,
     pub script: String,
 
+    #[serde(default = "default_backend")]
+    pub backend: AIBackend,
+
     #[serde(default)]
     pub hooks: HashMap<FuncKind, String>,
 
@@ -0,0 +1,115 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::rules::{BonusList, Damage};
+
+/// A status condition, such as poison or bleeding, applied to an entity as
+/// an effect.  A condition deals its damage at the end of each round it is
+/// active, multiplied by its current number of stacks.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Condition {
+    pub id: String,
+    pub name: String,
+
+    #[serde(default)]
+    pub icon: Option<String>,
+
+    /// The duration in rounds
+    pub duration: u32,
+
+    #[serde(default)]
+    pub damage: Option<Damage>,
+
+    #[serde(default)]
+    pub bonuses: BonusList,
+
+    #[serde(default)]
+    pub stacking: ConditionStacking,
+
+    /// The maximum number of stacks, when stacking is `Intensify`
+    #[serde(default = "default_max_stacks")]
+    pub max_stacks: u32,
+
+    /// The save the target makes to resist the condition when it is applied
+    #[serde(default)]
+    pub save: Option<ConditionSave>,
+
+    /// Tags used to cleanse this and similar conditions, such as `poison`
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_max_stacks() -> u32 {
+    1
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl PartialEq for Condition {
+    fn eq(&self, other: &Condition) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Condition {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// What happens when a condition is applied to an entity that already has it
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConditionStacking {
+    /// The duration is reset
+    #[default]
+    Refresh,
+
+    /// The duration of the new application is added to the remaining duration
+    Extend,
+
+    /// A stack is added, up to `max_stacks`, and the duration is reset
+    Intensify,
+
+    /// The new application has no effect
+    Ignore,
+}
+
+/// A save is rolled with the applier's spell accuracy plus the `accuracy`
+/// bonus against the target's `defense`.  The condition is resisted on a miss.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionSave {
+    pub defense: SaveDefense,
+
+    #[serde(default)]
+    pub accuracy: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveDefense {
+    Fortitude,
+    Reflex,
+    Will,
+}
@@ -14,7 +14,7 @@
 //  You should have received a copy of the GNU General Public License
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Error;
 use std::slice::Iter;
 
@@ -27,6 +27,9 @@ use crate::{Module, OnTrigger};
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct Response {
+    /// Identifies the text for translation in place of the response's index
+    pub id: Option<String>,
+
     pub text: String,
     pub to: Option<String>,
 
@@ -102,6 +105,55 @@ impl Conversation {
         self.initial_nodes.iter()
     }
 
+    /// Returns every trigger in this conversation, paired with the ID of the
+    /// node it belongs to
+    pub fn all_triggers(&self) -> Vec<(&str, &OnTrigger)> {
+        let mut triggers = Vec::new();
+        for (id, to_view) in self.initial_nodes.iter() {
+            triggers.extend(to_view.iter().map(|t| (id.as_str(), t)));
+        }
+
+        for (id, node) in self.nodes.iter() {
+            triggers.extend(node.on_view.iter().map(|t| (id.as_str(), t)));
+            for response in node.responses.iter() {
+                let response_triggers = response.on_select.iter().chain(response.to_view.iter());
+                triggers.extend(response_triggers.map(|t| (id.as_str(), t)));
+            }
+        }
+
+        triggers
+    }
+
+    /// Returns the IDs of all nodes that cannot be reached from any
+    /// initial node, in sorted order
+    pub fn unreachable_nodes(&self) -> Vec<&str> {
+        let mut reached: HashSet<&str> = HashSet::new();
+        let mut to_visit: Vec<&str> = self
+            .initial_nodes
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect();
+
+        while let Some(id) = to_visit.pop() {
+            if !reached.insert(id) {
+                continue;
+            }
+
+            if let Some(node) = self.nodes.get(id) {
+                to_visit.extend(node.responses.iter().filter_map(|r| r.to.as_deref()));
+            }
+        }
+
+        let mut unreachable: Vec<&str> = self
+            .nodes
+            .keys()
+            .map(|id| id.as_str())
+            .filter(|id| !reached.contains(id))
+            .collect();
+        unreachable.sort_unstable();
+        unreachable
+    }
+
     // TODO don't panic when getting a node.
 
     pub fn on_view(&self, node: &str) -> &Vec<OnTrigger> {
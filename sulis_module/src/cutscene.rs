@@ -51,6 +51,9 @@ impl Cutscene {
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct FrameBuilder {
+    /// Identifies the text for translation in place of the frame's index
+    pub id: Option<String>,
+
     pub text: String,
 }
 
@@ -33,10 +33,39 @@ struct Entry {
     limit: Option<u32>,
 }
 
+/// Coordinated behaviour for all members of an encounter, used by the AI.
+/// Each bonus is added to the weight the AI gives a target, in the same units
+/// as the other weight modifiers, and is disabled when zero.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Tactics {
+    /// Bonus for the hostile the group has chosen to focus on.  The group
+    /// picks a single shared target and keeps it until it falls.
+    #[serde(default)]
+    pub focus_fire: f32,
+
+    /// Bonus for melee attacks made while flanking the target.  Members
+    /// also prefer to move to flanking positions when approaching a target
+    /// that another member is already threatening.
+    #[serde(default)]
+    pub flanking: f32,
+
+    /// Bonus for hostiles threatening one of the group's casters, given by
+    /// members that are not themselves casters
+    #[serde(default)]
+    pub protect_casters: f32,
+
+    /// When the combined hit points of the group fall below this fraction of
+    /// their maximum, the whole group retreats from its hostiles
+    #[serde(default)]
+    pub retreat_hp_fraction: Option<f32>,
+}
+
 pub struct Encounter {
     pub id: String,
     pub music: Option<SoundSource>,
     pub auto_spawn: bool,
+    pub tactics: Option<Tactics>,
     min_gen_actors: u32,
     max_gen_actors: u32,
     entries: Vec<Entry>,
@@ -88,6 +117,13 @@ impl Encounter {
             });
         }
 
+        if let Some(frac) = builder.tactics.as_ref().and_then(|t| t.retreat_hp_fraction) {
+            if !(0.0..=1.0).contains(&frac) {
+                warn!("Retreat HP fraction must be between 0 and 1");
+                return Err(unable_to_create_error("encounter", &builder.id));
+            }
+        }
+
         let music = match &builder.music {
             None => None,
             Some(id) => Some(ResourceSet::sound(id)?),
@@ -97,6 +133,7 @@ impl Encounter {
             id: builder.id,
             music,
             auto_spawn: builder.auto_spawn,
+            tactics: builder.tactics,
             min_gen_actors: builder.min_gen_actors,
             max_gen_actors: builder.max_gen_actors,
             entries,
@@ -146,6 +183,11 @@ impl Encounter {
         None
     }
 
+    /// Returns every actor that may be generated by this encounter
+    pub fn actors(&self) -> impl Iterator<Item = &Rc<Actor>> {
+        self.entries.iter().map(|entry| &entry.actor)
+    }
+
     pub fn gen_actors(&self) -> Vec<(Rc<Actor>, Option<String>)> {
         let mut actors = Vec::new();
 
@@ -187,6 +229,9 @@ pub struct EncounterBuilder {
     min_gen_actors: u32,
     max_gen_actors: u32,
     entries: Vec<EntryBuilder>,
+
+    #[serde(default)]
+    tactics: Option<Tactics>,
 }
 
 #[derive(Deserialize, Debug)]
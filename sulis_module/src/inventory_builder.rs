@@ -124,6 +124,15 @@ impl InventoryBuilder {
         }
     }
 
+    /// Returns all items referenced by this inventory, without validating
+    /// that they exist
+    pub fn items_iter(&self) -> impl Iterator<Item = &ItemSaveState> {
+        self.equipped
+            .values()
+            .chain(self.quick.values())
+            .chain(self.pc_starting_items.iter().map(|entry| &entry.item))
+    }
+
     /// Returns the starting currency for players owning this inventory.
     /// This is only relevant for new player characters.
     pub fn pc_starting_coins(&self) -> i32 {
@@ -38,6 +38,9 @@ pub use self::area::Area;
 pub mod class;
 pub use self::class::Class;
 
+pub mod condition;
+pub use self::condition::{Condition, ConditionSave, ConditionStacking, SaveDefense};
+
 pub mod conversation;
 pub use self::conversation::Conversation;
 
@@ -86,7 +89,10 @@ pub mod loot_list;
 pub use self::loot_list::LootList;
 
 pub mod modification;
-pub use self::modification::ModificationInfo;
+pub use self::modification::{LoadOrder, ModificationInfo};
+
+pub mod module_validator;
+pub use self::module_validator::ValidationReport;
 
 pub mod prereq_list;
 pub use self::prereq_list::PrereqList;
@@ -105,12 +111,16 @@ pub mod rules;
 pub use self::rules::bonus;
 pub use self::rules::{
     AccuracyKind, Armor, ArmorKind, Attack, AttackBonuses, AttackKind, Attribute, AttributeList,
-    Bonus, BonusKind, BonusList, Damage, DamageKind, DamageList, HitFlags, HitKind, ItemKind,
-    QuickSlot, Resistance, Rules, Slot, StatList, Time, WeaponKind, WeaponStyle, ROUND_TIME_MILLIS,
+    Bonus, BonusKind, BonusList, CoverKind, Damage, DamageKind, DamageList, ElevationKind,
+    HitFlags, HitKind, ItemKind, QuickSlot, ReactionKind, Resistance, Rules, Slot, StatList, Time,
+    WeaponKind, WeaponStyle, ROUND_TIME_MILLIS,
 };
 
+pub mod script_source;
+pub use self::script_source::{ExpandedScript, ScriptReload};
+
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fmt::{self, Display};
 use std::fs;
@@ -120,6 +130,7 @@ use std::rc::Rc;
 use std::time;
 
 use sulis_core::config::{self, Config};
+use sulis_core::resource::yaml_resource_set::KeyOverrides;
 use sulis_core::resource::*;
 use sulis_core::serde_yaml;
 use sulis_core::util::{self, invalid_data_error};
@@ -158,6 +169,7 @@ pub struct Module {
     ai_templates: HashMap<String, Rc<AITemplate>>,
     areas: HashMap<String, Rc<Area>>,
     classes: HashMap<String, Rc<Class>>,
+    conditions: HashMap<String, Rc<Condition>>,
     conversations: HashMap<String, Rc<Conversation>>,
     cutscenes: HashMap<String, Rc<Cutscene>>,
     encounters: HashMap<String, Rc<Encounter>>,
@@ -170,6 +182,7 @@ pub struct Module {
     sizes: HashMap<String, Rc<ObjectSize>>,
     tiles: HashMap<String, Rc<Tile>>,
     scripts: HashMap<String, String>,
+    expanded_scripts: HashMap<String, Rc<ExpandedScript>>,
 
     features: HashMap<String, Rc<Feature>>,
     terrain_rules: Option<TerrainRules>,
@@ -180,6 +193,10 @@ pub struct Module {
     generators: HashMap<String, Rc<AreaGenerator>>,
 
     root_dir: Option<String>,
+    resource_dirs: Vec<String>,
+    resource_files: HashMap<YamlResourceKind, HashMap<String, Vec<String>>>,
+    key_overrides: HashMap<YamlResourceKind, HashMap<String, KeyOverrides>>,
+    active_mods: Vec<ModificationInfo>,
     init: bool,
 }
 
@@ -193,7 +210,7 @@ pub struct ModuleInfo {
 }
 
 impl ModuleInfo {
-    fn from_dir(path: PathBuf) -> Result<ModuleInfo, Error> {
+    pub(crate) fn from_dir(path: PathBuf) -> Result<ModuleInfo, Error> {
         let path_str = path.to_string_lossy().to_string();
         debug!("Checking module at '{}'", path_str);
 
@@ -412,6 +429,8 @@ impl Module {
 
         let campaign_builder: CampaignBuilder = read_builder(campaign_yaml)?;
 
+        let resource_files = read_resource_files(&yaml);
+        let key_overrides = std::mem::take(&mut yaml.overrides);
         let builder_set = ModuleBuilder::from_yaml(&mut yaml)?;
         let area_builders = MODULE.with(|module| {
             let mut module = module.borrow_mut();
@@ -421,6 +440,7 @@ impl Module {
             module.ai_templates.clear();
             module.areas.clear();
             module.classes.clear();
+            module.conditions.clear();
             module.conversations.clear();
             module.cutscenes.clear();
             module.encounters.clear();
@@ -433,6 +453,7 @@ impl Module {
             module.sizes.clear();
             module.tiles.clear();
             module.scripts.clear();
+            module.expanded_scripts.clear();
             module.generators.clear();
             module.features.clear();
             module.terrain_rules = None;
@@ -442,9 +463,13 @@ impl Module {
 
             module.rules = Some(Rc::new(rules));
             module.scripts = read_to_string(&dirs, "scripts");
-            expand_include_directives(&mut module.scripts);
+            module.expanded_scripts = expand_include_directives(&module.scripts);
 
             module.root_dir = Some(dirs[1].to_string());
+            module.resource_dirs = dirs.clone();
+            module.resource_files = resource_files;
+            module.key_overrides = key_overrides;
+            module.active_mods = read_active_mods(&dirs);
 
             for (id, builder) in builder_set.item_adjectives {
                 insert_if_ok(
@@ -464,6 +489,15 @@ impl Module {
                 module.quests.insert(id, Rc::new(quest));
             }
 
+            for (id, condition) in builder_set.conditions {
+                trace!(
+                    "Inserting resource of type condition with key {} \
+                     into module.",
+                    id
+                );
+                module.conditions.insert(id, Rc::new(condition));
+            }
+
             for (id, builder) in builder_set.size_builders {
                 insert_if_ok("size", id, ObjectSize::new(builder), &mut module.sizes);
             }
@@ -644,6 +678,46 @@ impl Module {
         MODULE.with(|m| m.borrow().root_dir.as_ref().cloned())
     }
 
+    /// Returns the info of each mod that the module was loaded with, in
+    /// load order
+    pub fn active_mods() -> Vec<ModificationInfo> {
+        MODULE.with(|m| m.borrow().active_mods.clone())
+    }
+
+    /// Returns the data directory, the campaign directory, and then each
+    /// mod directory that the module was loaded from
+    pub fn resource_dirs() -> Vec<String> {
+        MODULE.with(|m| m.borrow().resource_dirs.clone())
+    }
+
+    /// Returns the files that the resource of the specified kind and id was
+    /// merged from, in the order they were read
+    pub fn resource_files(kind: YamlResourceKind, id: &str) -> Vec<String> {
+        MODULE.with(|m| {
+            m.borrow()
+                .resource_files
+                .get(&kind)
+                .and_then(|files| files.get(id))
+                .cloned()
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns each resource merged from more than one layer, along with
+    /// the keys set by later layers and the directory of the last layer to
+    /// set each key
+    pub fn key_overrides() -> Vec<(YamlResourceKind, String, KeyOverrides)> {
+        MODULE.with(|m| {
+            let mut result = Vec::new();
+            for (kind, resources) in m.borrow().key_overrides.iter() {
+                for (id, keys) in resources.iter() {
+                    result.push((*kind, id.to_string(), keys.clone()));
+                }
+            }
+            result
+        })
+    }
+
     pub fn is_initialized() -> bool {
         MODULE.with(|m| m.borrow_mut().init)
     }
@@ -745,6 +819,7 @@ impl Module {
     ai_template, ai_templates, AITemplate;
     area, areas, Area;
     class, classes, Class;
+    condition, conditions, Condition;
     conversation, conversations, Conversation;
     cutscene, cutscenes, Cutscene;
     encounter, encounters, Encounter;
@@ -761,10 +836,124 @@ impl Module {
     feature, features, Feature
     );
 
+    /// Returns the source of the specified script, with all include
+    /// directives expanded
     pub fn script(id: &str) -> Option<String> {
         MODULE.with(|r| {
             let module = r.borrow();
-            module.scripts.get(id).cloned()
+            module
+                .expanded_scripts
+                .get(id)
+                .map(|script| script.source.to_string())
+        })
+    }
+
+    pub fn expanded_script(id: &str) -> Option<Rc<ExpandedScript>> {
+        MODULE.with(|r| r.borrow().expanded_scripts.get(id).cloned())
+    }
+
+    /// Returns the source of the specified script as it was read from disk
+    pub fn raw_script(id: &str) -> Option<String> {
+        MODULE.with(|r| r.borrow().scripts.get(id).cloned())
+    }
+
+    /// Re-reads all scripts from the module directories.  Any script that
+    /// has changed, includes a changed script, or previously failed to
+    /// expand an include is expanded again.  The module is not modified;
+    /// the result should be checked and then passed to
+    /// `apply_script_reload`.
+    pub fn reload_scripts() -> ScriptReload {
+        MODULE.with(|r| {
+            let module = r.borrow();
+            let scripts = read_to_string(&module.resource_dirs, "scripts");
+
+            let mut changed: HashSet<&String> = scripts
+                .iter()
+                .filter(|(id, src)| module.scripts.get(*id) != Some(src))
+                .map(|(id, _)| id)
+                .collect();
+            changed.extend(
+                module
+                    .scripts
+                    .keys()
+                    .filter(|id| !scripts.contains_key(*id)),
+            );
+
+            if changed.is_empty() {
+                return ScriptReload {
+                    scripts,
+                    affected: Vec::new(),
+                };
+            }
+
+            let mut affected: Vec<String> = module
+                .expanded_scripts
+                .values()
+                .filter(|expanded| {
+                    !expanded.errors.is_empty() || changed.iter().any(|id| expanded.includes(id))
+                })
+                .map(|expanded| expanded.id.to_string())
+                .chain(changed.iter().map(|id| id.to_string()))
+                .collect();
+            affected.sort();
+            affected.dedup();
+
+            let affected = affected
+                .into_iter()
+                .map(|id| {
+                    if !scripts.contains_key(&id) {
+                        return (id, None);
+                    }
+
+                    let expanded = ExpandedScript::new(&id, &scripts);
+                    for error in expanded.errors.iter() {
+                        error!("{}", error);
+                    }
+                    (id, Some(Rc::new(expanded)))
+                })
+                .collect();
+
+            ScriptReload { scripts, affected }
+        })
+    }
+
+    /// Replaces the scripts in this module with those from `reload`.  The
+    /// scripts with IDs in `failed` keep their previous version, as do the
+    /// sources of any scripts included into them, so that they will be
+    /// reloaded again the next time `reload_scripts` is called.
+    pub fn apply_script_reload(reload: ScriptReload, failed: &[String]) {
+        MODULE.with(|r| {
+            let mut module = r.borrow_mut();
+
+            let mut kept = Vec::new();
+            for (id, expanded) in reload.affected {
+                if failed.contains(&id) {
+                    if let Some(expanded) = expanded {
+                        kept.push(expanded);
+                    }
+                    continue;
+                }
+
+                match expanded {
+                    None => module.expanded_scripts.remove(&id),
+                    Some(expanded) => module.expanded_scripts.insert(id, expanded),
+                };
+            }
+
+            let is_kept = |id: &str| kept.iter().any(|expanded| expanded.includes(id));
+            module
+                .scripts
+                .retain(|id, _| reload.scripts.contains_key(id) || is_kept(id));
+            for (id, src) in reload.scripts {
+                if !is_kept(&id) {
+                    module.scripts.insert(id, src);
+                }
+            }
+
+            info!(
+                "Reloaded scripts, {} failed and kept their previous version",
+                failed.len()
+            );
         })
     }
 
@@ -779,10 +968,42 @@ impl Module {
         })
     }
 
+    pub fn all_abilities() -> Vec<Rc<Ability>> {
+        MODULE.with(|r| all_resources(&r.borrow().abilities))
+    }
+
     pub fn all_actors() -> Vec<Rc<Actor>> {
         MODULE.with(|r| all_resources(&r.borrow().actors))
     }
 
+    pub fn all_ai_templates() -> Vec<Rc<AITemplate>> {
+        MODULE.with(|r| all_resources(&r.borrow().ai_templates))
+    }
+
+    pub fn all_areas() -> Vec<Rc<Area>> {
+        MODULE.with(|r| all_resources(&r.borrow().areas))
+    }
+
+    pub fn all_conditions() -> Vec<Rc<Condition>> {
+        MODULE.with(|r| all_resources(&r.borrow().conditions))
+    }
+
+    pub fn all_conversations() -> Vec<Rc<Conversation>> {
+        MODULE.with(|r| all_resources(&r.borrow().conversations))
+    }
+
+    pub fn all_cutscenes() -> Vec<Rc<Cutscene>> {
+        MODULE.with(|r| all_resources(&r.borrow().cutscenes))
+    }
+
+    pub fn all_items() -> Vec<Rc<Item>> {
+        MODULE.with(|r| all_resources(&r.borrow().items))
+    }
+
+    pub fn all_loot_lists() -> Vec<Rc<LootList>> {
+        MODULE.with(|r| all_resources(&r.borrow().loot_lists))
+    }
+
     pub fn all_object_sizes() -> Vec<Rc<ObjectSize>> {
         MODULE.with(|r| {
             r.borrow()
@@ -842,6 +1063,7 @@ struct ModuleBuilder {
 
     item_adjectives: HashMap<String, ItemAdjectiveBuilder>,
     quests: HashMap<String, Quest>,
+    conditions: HashMap<String, Condition>,
 }
 
 impl ModuleBuilder {
@@ -854,6 +1076,7 @@ impl ModuleBuilder {
             ai_builders: read_builders(resources, AiTemplate)?,
             area_builders: read_builders(resources, Area)?,
             class_builders: read_builders(resources, Class)?,
+            conditions: read_builders(resources, Condition)?,
             conversation_builders: read_builders(resources, Conversation)?,
             cutscene_builders: read_builders(resources, Cutscene)?,
             encounter_builders: read_builders(resources, Encounter)?,
@@ -870,70 +1093,155 @@ impl ModuleBuilder {
     }
 }
 
-struct IncludeExpansion {
-    start_index: usize,
-    end_index: usize,
-    script_id: String,
+fn read_active_mods(dirs: &[String]) -> Vec<ModificationInfo> {
+    dirs.iter()
+        .skip(2)
+        .filter_map(|dir| match ModificationInfo::from_dir(PathBuf::from(dir)) {
+            Ok(modif) => Some(modif),
+            Err(e) => {
+                warn!("Unable to read mod info from '{}': {}", dir, e);
+                None
+            }
+        })
+        .collect()
 }
 
-fn expand_include_directives(scripts: &mut HashMap<String, String>) {
-    let start_time = time::Instant::now();
-
-    // since we need a mutable reference to one entry and immutable refs to any other
-    // entry this would be very difficult without a clone
-    let scripts_src = scripts.clone();
-
-    let include_len = "--INCLUDE".len();
-
-    for (id, script) in scripts {
-        // first find all the expansions for this script
-        let mut expansions = Vec::new();
-        for (index, _) in script.match_indices("--INCLUDE") {
-            expansions.push(IncludeExpansion {
-                start_index: index,
-                end_index: 0,
-                script_id: String::new(),
-            });
-        }
-
-        for expansion in &mut expansions {
-            let substr = &script[(expansion.start_index + include_len)..];
-            let mut iter = substr.lines();
-            let id = match iter.next() {
-                None => {
-                    error!("Invalid --INCLUDE directive, no script specified.");
-                    return;
-                }
-                Some(id) => id,
+fn read_resource_files(
+    yaml: &YamlResourceSet,
+) -> HashMap<YamlResourceKind, HashMap<String, Vec<String>>> {
+    let file_key = serde_yaml::Value::String(yaml_resource_set::FILE_VAL_STR.to_string());
+
+    let mut result = HashMap::new();
+    for (kind, resources) in yaml.resources.iter() {
+        let mut kind_files = HashMap::new();
+        for (id, value) in resources.iter() {
+            let files = match value {
+                serde_yaml::Value::Mapping(map) => match map.get(&file_key) {
+                    Some(serde_yaml::Value::Sequence(seq)) => seq
+                        .iter()
+                        .filter_map(|file| file.as_str().map(|f| f.to_string()))
+                        .collect(),
+                    _ => Vec::new(),
+                },
+                _ => Vec::new(),
             };
-            expansion.end_index = expansion.start_index + include_len + id.len();
-            expansion.script_id = id.trim().to_string();
+            kind_files.insert(id.to_string(), files);
         }
+        result.insert(*kind, kind_files);
+    }
 
-        // start from the last and work forward, expanding each entry
-        for exp in expansions.into_iter().rev() {
-            debug!(
-                "Found script expansion in {}: '{}' at {} to {}",
-                id, exp.script_id, exp.start_index, exp.end_index
-            );
+    result
+}
 
-            let src = match scripts_src.get(&exp.script_id) {
-                None => {
-                    error!(
-                        "Invalid --INCLUDE direction, script '{}' does not exist",
-                        exp.script_id
-                    );
-                    return;
-                }
-                Some(src) => src,
-            };
+fn expand_include_directives(
+    scripts: &HashMap<String, String>,
+) -> HashMap<String, Rc<ExpandedScript>> {
+    let start_time = time::Instant::now();
 
-            script.replace_range(exp.start_index..exp.end_index, src);
+    let mut expanded = HashMap::new();
+    for id in scripts.keys() {
+        let script = ExpandedScript::new(id, scripts);
+        for error in script.errors.iter() {
+            error!("{}", error);
         }
+        expanded.insert(id.to_string(), Rc::new(script));
     }
 
     info!(
         "Expanded scripts in {}",
         util::format_elapsed_secs(start_time.elapsed())
     );
+
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(id, src)| (id.to_string(), src.to_string()))
+            .collect()
+    }
+
+    fn setup(entries: &[(&str, &str)]) {
+        let scripts = scripts(entries);
+        MODULE.with(|r| {
+            let mut module = r.borrow_mut();
+            module.expanded_scripts = expand_include_directives(&scripts);
+            module.scripts = scripts;
+        });
+    }
+
+    fn reload(entries: &[(&str, &str)], affected: &[&str]) -> ScriptReload {
+        let scripts = scripts(entries);
+        let affected = affected
+            .iter()
+            .map(|id| {
+                let expanded = match scripts.contains_key(*id) {
+                    false => None,
+                    true => Some(Rc::new(ExpandedScript::new(id, &scripts))),
+                };
+                (id.to_string(), expanded)
+            })
+            .collect();
+        ScriptReload { scripts, affected }
+    }
+
+    #[test]
+    fn apply_script_reload_replaces_scripts() {
+        setup(&[("a", "old_a"), ("b", "old_b")]);
+
+        let reload = reload(&[("a", "new_a"), ("c", "new_c")], &["a", "b", "c"]);
+        Module::apply_script_reload(reload, &[]);
+
+        assert_eq!(Module::script("a").unwrap(), "new_a\n");
+        assert_eq!(Module::raw_script("a").unwrap(), "new_a");
+        assert!(Module::script("b").is_none());
+        assert!(Module::raw_script("b").is_none());
+        assert_eq!(Module::script("c").unwrap(), "new_c\n");
+        assert_eq!(Module::raw_script("c").unwrap(), "new_c");
+    }
+
+    #[test]
+    fn apply_script_reload_keeps_failed_scripts() {
+        setup(&[
+            ("a", "--INCLUDE lib\nold_a"),
+            ("b", "old_b"),
+            ("lib", "old_lib"),
+        ]);
+
+        let reload = reload(
+            &[
+                ("a", "--INCLUDE lib\nbroken_a"),
+                ("b", "new_b"),
+                ("lib", "new_lib"),
+            ],
+            &["a", "b", "lib"],
+        );
+        Module::apply_script_reload(reload, &["a".to_string()]);
+
+        // the failed script and the scripts it includes keep their old source
+        assert_eq!(Module::script("a").unwrap(), "old_lib\nold_a\n");
+        assert_eq!(Module::raw_script("a").unwrap(), "--INCLUDE lib\nold_a");
+        assert_eq!(Module::raw_script("lib").unwrap(), "old_lib");
+
+        assert_eq!(Module::script("lib").unwrap(), "new_lib\n");
+        assert_eq!(Module::script("b").unwrap(), "new_b\n");
+        assert_eq!(Module::raw_script("b").unwrap(), "new_b");
+    }
+
+    #[test]
+    fn apply_script_reload_does_not_add_failed_new_script() {
+        setup(&[("a", "old_a")]);
+
+        let reload = reload(&[("a", "old_a"), ("new", "broken")], &["new"]);
+        Module::apply_script_reload(reload, &["new".to_string()]);
+
+        assert!(Module::script("new").is_none());
+        assert!(Module::raw_script("new").is_none());
+        assert_eq!(Module::raw_script("a").unwrap(), "old_a");
+    }
 }
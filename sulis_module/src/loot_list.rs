@@ -201,6 +201,12 @@ impl LootList {
         })
     }
 
+    /// Returns the IDs of the loot lists this list draws from.  These are
+    /// looked up when generating, and so may not exist
+    pub fn sub_list_ids(&self) -> impl Iterator<Item = &str> {
+        self.sub_lists.iter().map(|entry| entry.id.as_str())
+    }
+
     pub fn generate_with_chance(&self, chance: u32) -> Vec<(u32, ItemState)> {
         let roll = gen_rand(1, 101);
         if chance >= roll {
@@ -22,6 +22,9 @@ use serde::Deserialize;
 
 use sulis_core::config::{self, Config};
 use sulis_core::resource::{read_single_resource, subdirs};
+use sulis_core::util::ActiveResources;
+
+use crate::ModuleInfo;
 
 pub fn get_available_modifications() -> Vec<ModificationInfo> {
     let root_dir = Config::resources_config().mods_directory;
@@ -51,12 +54,51 @@ pub fn get_available_modifications() -> Vec<ModificationInfo> {
     mods
 }
 
+/// Returns the directories to read resources from for the active campaign
+/// and mods, with the mods in their resolved load order.  Any problems with
+/// the set of mods are logged.  Mods which cannot be read are loaded last.
+pub fn active_directories(active: &ActiveResources) -> Vec<String> {
+    let mut mods = Vec::new();
+    let mut unreadable = Vec::new();
+    for dir in active.mods.iter() {
+        match ModificationInfo::from_dir(PathBuf::from(dir)) {
+            Ok(modif) => mods.push(modif),
+            Err(e) => {
+                warn!("Error reading mod from '{}': {}", dir, e);
+                unreadable.push(dir.to_string());
+            }
+        }
+    }
+
+    let campaign = active
+        .campaign
+        .as_ref()
+        .and_then(|dir| ModuleInfo::from_dir(PathBuf::from(dir)).ok())
+        .map(|info| info.id);
+
+    let order = resolve_load_order(mods, campaign.as_deref());
+    for warning in order.warnings {
+        warn!("{}", warning);
+    }
+
+    let mut active = active.clone();
+    active.mods = order.mods.into_iter().map(|modif| modif.dir).collect();
+    active.mods.append(&mut unreadable);
+    active.directories()
+}
+
 #[derive(Debug, Clone)]
 pub struct ModificationInfo {
     pub id: String,
     pub name: String,
     pub description: String,
     pub dir: String,
+    pub version: String,
+    pub campaigns: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub load_after: Vec<String>,
+    pub load_before: Vec<String>,
+    pub incompatible: Vec<String>,
 }
 
 impl Display for ModificationInfo {
@@ -75,8 +117,86 @@ impl ModificationInfo {
             description: builder.description,
             id: builder.id,
             dir: path_str,
+            version: builder.version,
+            campaigns: builder.campaigns,
+            dependencies: builder.dependencies,
+            load_after: builder.load_after,
+            load_before: builder.load_before,
+            incompatible: builder.incompatible,
         })
     }
+
+    /// Returns true if this mod must be loaded after the specified mod
+    fn loads_after(&self, other: &ModificationInfo) -> bool {
+        self.dependencies.contains(&other.id)
+            || self.load_after.contains(&other.id)
+            || other.load_before.contains(&self.id)
+    }
+}
+
+/// The order to load a set of active mods in, along with any problems found
+/// with the set
+#[derive(Debug, Clone, Default)]
+pub struct LoadOrder {
+    pub mods: Vec<ModificationInfo>,
+    pub warnings: Vec<String>,
+}
+
+/// Orders the active mods so that each is loaded after its dependencies and
+/// any mods it must load after, keeping the given order where there are no
+/// such constraints.  Warnings are generated for missing dependencies,
+/// incompatible mods, mods that do not support the campaign with ID
+/// `campaign`, and circular ordering constraints.
+pub fn resolve_load_order(active: Vec<ModificationInfo>, campaign: Option<&str>) -> LoadOrder {
+    let mut warnings = Vec::new();
+
+    for modif in active.iter() {
+        if let Some(campaign) = campaign {
+            if !modif.campaigns.is_empty() && !modif.campaigns.iter().any(|c| c == campaign) {
+                warnings.push(format!("'{}' does not support this campaign", modif.name));
+            }
+        }
+
+        for dep in modif.dependencies.iter() {
+            if !active.iter().any(|other| &other.id == dep) {
+                warnings.push(format!("'{}' requires mod '{}'", modif.name, dep));
+            }
+        }
+
+        for other in active.iter() {
+            if modif.incompatible.contains(&other.id) {
+                warnings.push(format!(
+                    "'{}' is incompatible with '{}'",
+                    modif.name, other.name
+                ));
+            }
+        }
+    }
+
+    let mut remaining = active;
+    let mut mods = Vec::new();
+    while !remaining.is_empty() {
+        let next = remaining.iter().position(|modif| {
+            !remaining
+                .iter()
+                .any(|other| other.id != modif.id && modif.loads_after(other))
+        });
+
+        let index = match next {
+            Some(index) => index,
+            None => {
+                let names: Vec<&str> = remaining.iter().map(|m| m.name.as_str()).collect();
+                warnings.push(format!(
+                    "Circular load order between '{}'",
+                    names.join("', '")
+                ));
+                0
+            }
+        };
+        mods.push(remaining.remove(index));
+    }
+
+    LoadOrder { mods, warnings }
 }
 
 #[derive(Deserialize, Debug)]
@@ -85,4 +205,141 @@ pub struct ModificationInfoBuilder {
     pub id: String,
     pub name: String,
     pub description: String,
+
+    #[serde(default)]
+    pub version: String,
+
+    /// The IDs of the campaigns this mod supports, or empty for all
+    #[serde(default)]
+    pub campaigns: Vec<String>,
+
+    /// The IDs of mods that must also be active, and are loaded first
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+
+    #[serde(default)]
+    pub load_after: Vec<String>,
+
+    #[serde(default)]
+    pub load_before: Vec<String>,
+
+    /// The IDs of mods that may not be active along with this mod
+    #[serde(default)]
+    pub incompatible: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: &str) -> ModificationInfo {
+        ModificationInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: String::new(),
+            dir: id.to_string(),
+            version: String::new(),
+            campaigns: Vec::new(),
+            dependencies: Vec::new(),
+            load_after: Vec::new(),
+            load_before: Vec::new(),
+            incompatible: Vec::new(),
+        }
+    }
+
+    fn ids(order: &LoadOrder) -> Vec<&str> {
+        order.mods.iter().map(|m| m.id.as_str()).collect()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_unconstrained_order() {
+        let order = resolve_load_order(vec![info("b"), info("a"), info("c")], None);
+        assert_eq!(ids(&order), vec!["b", "a", "c"]);
+        assert!(order.warnings.is_empty());
+    }
+
+    #[test]
+    fn loads_dependencies_first() {
+        let mut a = info("a");
+        a.dependencies = strings(&["b"]);
+
+        let order = resolve_load_order(vec![a, info("b"), info("c")], None);
+        assert_eq!(ids(&order), vec!["b", "a", "c"]);
+        assert!(order.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_on_missing_dependency() {
+        let mut a = info("a");
+        a.dependencies = strings(&["missing"]);
+
+        let order = resolve_load_order(vec![a, info("b")], None);
+        assert_eq!(ids(&order), vec!["a", "b"]);
+        assert_eq!(order.warnings, vec!["'a' requires mod 'missing'"]);
+    }
+
+    #[test]
+    fn follows_load_after_and_load_before() {
+        let mut a = info("a");
+        a.load_after = strings(&["c"]);
+        let mut d = info("d");
+        d.load_before = strings(&["b"]);
+
+        let order = resolve_load_order(vec![a, info("b"), info("c"), d], None);
+        assert_eq!(ids(&order), vec!["c", "a", "d", "b"]);
+        assert!(order.warnings.is_empty());
+    }
+
+    #[test]
+    fn ignores_ordering_for_inactive_mods() {
+        let mut a = info("a");
+        a.load_after = strings(&["missing"]);
+        a.load_before = strings(&["other"]);
+
+        let order = resolve_load_order(vec![a, info("b")], None);
+        assert_eq!(ids(&order), vec!["a", "b"]);
+        assert!(order.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_on_incompatible_mods() {
+        let mut a = info("a");
+        a.incompatible = strings(&["b", "missing"]);
+
+        let order = resolve_load_order(vec![a, info("b")], None);
+        assert_eq!(ids(&order), vec!["a", "b"]);
+        assert_eq!(order.warnings, vec!["'a' is incompatible with 'b'"]);
+    }
+
+    #[test]
+    fn warns_on_unsupported_campaign() {
+        let mut a = info("a");
+        a.campaigns = strings(&["x"]);
+        let mut b = info("b");
+        b.campaigns = strings(&["x", "y"]);
+
+        let order = resolve_load_order(vec![a.clone(), b.clone(), info("c")], Some("y"));
+        assert_eq!(ids(&order), vec!["a", "b", "c"]);
+        assert_eq!(order.warnings, vec!["'a' does not support this campaign"]);
+
+        // campaign support is not checked without a campaign
+        let order = resolve_load_order(vec![a, b], None);
+        assert!(order.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_on_cycle() {
+        let mut a = info("a");
+        a.load_after = strings(&["b"]);
+        let mut b = info("b");
+        b.dependencies = strings(&["a"]);
+
+        let order = resolve_load_order(vec![a, b, info("c")], None);
+        assert_eq!(ids(&order), vec!["c", "a", "b"]);
+        assert_eq!(order.warnings, vec!["Circular load order between 'a', 'b'"]);
+    }
 }
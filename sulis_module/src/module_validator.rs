@@ -0,0 +1,657 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Checks of the currently loaded module for problems that would otherwise
+//! only show up as something missing in game.  Resources that were skipped
+//! while loading, references to IDs that do not exist, conversation nodes
+//! that can never be shown, quest entries that are never referenced, and
+//! campaign or mod resources that are never used are all collected into a
+//! single report.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use sulis_core::resource::{self, YamlResourceKind};
+use sulis_core::util;
+
+use crate::area::ToKind;
+use crate::inventory_builder::ItemSaveState;
+use crate::on_trigger::QuestStateData;
+use crate::prop::Interactive;
+use crate::{Module, OnTrigger};
+
+/// The resource kinds checked for being unused.  Other kinds, such as items
+/// and abilities, are commonly shared between campaigns or only used by
+/// area generators.
+const UNUSED_KINDS: [YamlResourceKind; 6] = [
+    YamlResourceKind::Actor,
+    YamlResourceKind::Area,
+    YamlResourceKind::Conversation,
+    YamlResourceKind::Cutscene,
+    YamlResourceKind::LootList,
+    YamlResourceKind::Quest,
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// A resource that could not be read or created, and was skipped
+    LoadError,
+
+    /// A reference to a resource that does not exist
+    UnresolvedId,
+
+    /// A conversation node that cannot be reached from any initial node
+    UnreachableNode,
+
+    /// A quest entry that no trigger or script refers to
+    UnreferencedQuestEntry,
+
+    /// A campaign or mod resource that nothing refers to
+    UnusedResource,
+
+    /// A problem found when checking the module's scripts
+    Script,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+
+    /// The kind of resource the issue was found in, such as `area`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_kind: Option<String>,
+
+    /// The ID of the resource the issue was found in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<String>,
+    pub message: String,
+
+    /// The files the resource was merged from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: ")?;
+
+        if let (Some(kind), Some(id)) = (&self.resource_kind, &self.resource) {
+            write!(f, "{kind} '{id}': ")?;
+        }
+        write!(f, "{}", self.message)?;
+
+        if let Some(file) = self.files.last() {
+            write!(f, " ({file})")?;
+        }
+        Ok(())
+    }
+}
+
+/// The keys of a resource set by a campaign or mod layer over an earlier
+/// layer, each mapped to the directory of the last layer to set it
+#[derive(Serialize, Debug, Clone)]
+pub struct ResourceOverrides {
+    pub resource_kind: String,
+    pub resource: String,
+    pub keys: BTreeMap<String, String>,
+}
+
+impl fmt::Display for ResourceOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} '{}':", self.resource_kind, self.resource)?;
+        for (key, layer) in self.keys.iter() {
+            writeln!(f, "  {key}: {layer}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ValidationReport {
+    pub dirs: Vec<String>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<Issue>,
+    pub overrides: Vec<ResourceOverrides>,
+}
+
+impl ValidationReport {
+    /// Creates a report containing an error for each resource that was
+    /// skipped when resources were last loaded
+    pub fn with_load_errors(dirs: Vec<String>) -> ValidationReport {
+        let mut report = ValidationReport {
+            dirs,
+            ..Default::default()
+        };
+
+        for error in resource::load_errors() {
+            report.add(Issue {
+                severity: Severity::Error,
+                kind: IssueKind::LoadError,
+                resource_kind: Some(error.kind),
+                resource: Some(error.id),
+                message: error.message,
+                files: Vec::new(),
+            });
+        }
+        report
+    }
+
+    pub fn add(&mut self, issue: Issue) {
+        match issue.severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.issues.push(issue);
+    }
+
+    /// Adds an error that is not associated with a particular resource
+    pub fn add_error(&mut self, kind: IssueKind, message: String) {
+        self.add(Issue {
+            severity: Severity::Error,
+            kind,
+            resource_kind: None,
+            resource: None,
+            message,
+            files: Vec::new(),
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for issue in self.issues.iter() {
+            writeln!(f, "{issue}")?;
+        }
+        writeln!(
+            f,
+            "{} errors, {} warnings in {}",
+            self.errors,
+            self.warnings,
+            self.dirs.join(", ")
+        )
+    }
+}
+
+/// The resource an issue or reference was found in
+#[derive(Clone, Copy)]
+struct Source<'a> {
+    kind: YamlResourceKind,
+    id: &'a str,
+}
+
+impl<'a> Source<'a> {
+    fn new(kind: YamlResourceKind, id: &'a str) -> Source<'a> {
+        Source { kind, id }
+    }
+}
+
+fn kind_name(kind: YamlResourceKind) -> &'static str {
+    use YamlResourceKind::*;
+    match kind {
+        TopLevel => "campaign",
+        Theme => "theme",
+        Ability => "ability",
+        AbilityList => "ability list",
+        Actor => "actor",
+        AiTemplate => "ai template",
+        Area => "area",
+        Class => "class",
+        Condition => "condition",
+        Conversation => "conversation",
+        Cutscene => "cutscene",
+        Encounter => "encounter",
+        Item => "item",
+        ItemAdjective => "item adjective",
+        LootList => "loot list",
+        Prop => "prop",
+        Quest => "quest",
+        Race => "race",
+        _ => "resource",
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    report: ValidationReport,
+    used: HashSet<(YamlResourceKind, String)>,
+    used_entries: HashSet<(String, String)>,
+    script_strings: HashSet<String>,
+}
+
+impl Checker {
+    fn issue(&mut self, severity: Severity, kind: IssueKind, source: Source, message: String) {
+        self.report.add(Issue {
+            severity,
+            kind,
+            resource_kind: Some(kind_name(source.kind).to_string()),
+            resource: Some(source.id.to_string()),
+            message,
+            files: Module::resource_files(source.kind, source.id),
+        });
+    }
+
+    /// Marks the resource as used, and reports an error if it doesn't exist
+    fn reference(&mut self, source: Source, kind: YamlResourceKind, id: &str, exists: bool) {
+        self.used.insert((kind, id.to_string()));
+        if !exists {
+            let message = format!("{} '{}' does not exist", kind_name(kind), id);
+            self.issue(Severity::Error, IssueKind::UnresolvedId, source, message);
+        }
+    }
+
+    fn mark_used(&mut self, kind: YamlResourceKind, id: &str) {
+        self.used.insert((kind, id.to_string()));
+    }
+
+    fn check_item(&mut self, source: Source, item: &ItemSaveState) {
+        use YamlResourceKind::*;
+        self.reference(source, Item, &item.id, Module::item(&item.id).is_some());
+        for adjective in item.adjectives.iter() {
+            let exists = Module::item_adjective(adjective).is_some();
+            self.reference(source, ItemAdjective, adjective, exists);
+        }
+    }
+
+    fn check_area_id(&mut self, source: Source, id: &str) {
+        let exists = Module::area(id).is_some();
+        self.reference(source, YamlResourceKind::Area, id, exists);
+    }
+
+    fn check_triggers<'a>(
+        &mut self,
+        source: Source,
+        triggers: impl Iterator<Item = &'a OnTrigger>,
+    ) {
+        for trigger in triggers {
+            self.check_trigger(source, trigger);
+        }
+    }
+
+    fn check_trigger(&mut self, source: Source, trigger: &OnTrigger) {
+        use YamlResourceKind::*;
+        match trigger {
+            OnTrigger::PartyItem(id) => {
+                self.reference(source, Item, id, Module::item(id).is_some());
+            }
+            OnTrigger::PlayerAbility(id) => {
+                self.reference(source, Ability, id, Module::ability(id).is_some());
+            }
+            OnTrigger::ShowMerchant(data) => {
+                let exists = Module::loot_list(&data.loot_list).is_some();
+                self.reference(source, LootList, &data.loot_list, exists);
+            }
+            OnTrigger::ShowCutscene(id) => {
+                self.reference(source, Cutscene, id, Module::cutscene(id).is_some());
+            }
+            OnTrigger::StartConversation(id) => {
+                let exists = Module::conversation(id).is_some();
+                self.reference(source, Conversation, id, exists);
+            }
+            OnTrigger::QuestState(data) | OnTrigger::NotQuestState(data) => {
+                self.check_quest_state(source, data);
+            }
+            _ => (),
+        }
+    }
+
+    fn check_quest_state(&mut self, source: Source, data: &QuestStateData) {
+        let quest = Module::quest(&data.quest);
+        self.reference(
+            source,
+            YamlResourceKind::Quest,
+            &data.quest,
+            quest.is_some(),
+        );
+
+        let (quest, entry) = match (quest, &data.entry) {
+            (Some(quest), Some(entry)) => (quest, entry),
+            _ => return,
+        };
+
+        self.used_entries
+            .insert((data.quest.to_string(), entry.to_string()));
+        if !quest.entries.contains_key(entry) {
+            let message = format!("quest '{}' has no entry '{}'", data.quest, entry);
+            self.issue(Severity::Error, IssueKind::UnresolvedId, source, message);
+        }
+    }
+
+    /// Returns true if the resource was defined or modified by the campaign
+    /// or a mod, rather than only by the base data
+    fn is_campaign_resource(&self, kind: YamlResourceKind, id: &str) -> bool {
+        let data_dir = match self.report.dirs.first() {
+            None => return true,
+            Some(dir) => dir,
+        };
+
+        Module::resource_files(kind, id)
+            .iter()
+            .any(|file| !file.starts_with(data_dir.as_str()))
+    }
+}
+
+/// Checks the currently loaded module, returning a report of every problem
+/// found.  Script problems are not included.
+pub fn validate() -> ValidationReport {
+    let start = Instant::now();
+    let mut checker = Checker {
+        report: ValidationReport::with_load_errors(Module::resource_dirs()),
+        ..Default::default()
+    };
+
+    for id in Module::all_scripts() {
+        if let Some(script) = Module::raw_script(&id) {
+            checker.script_strings.extend(string_literals(&script));
+        }
+    }
+
+    check_campaign(&mut checker);
+    check_areas(&mut checker);
+    check_actors(&mut checker);
+    check_resources(&mut checker);
+    check_conversations(&mut checker);
+    check_quests(&mut checker);
+    check_unused(&mut checker);
+    add_overrides(&mut checker.report);
+
+    info!(
+        "Validated module in {} secs",
+        util::format_elapsed_secs(start.elapsed())
+    );
+
+    checker.report
+}
+
+fn add_overrides(report: &mut ValidationReport) {
+    for (kind, id, keys) in Module::key_overrides() {
+        report.overrides.push(ResourceOverrides {
+            resource_kind: kind_name(kind).to_string(),
+            resource: id,
+            keys,
+        });
+    }
+
+    report
+        .overrides
+        .sort_by(|a, b| (&a.resource_kind, &a.resource).cmp(&(&b.resource_kind, &b.resource)));
+}
+
+fn check_campaign(checker: &mut Checker) {
+    let campaign = Module::campaign();
+    let source = Source::new(YamlResourceKind::TopLevel, &campaign.id);
+
+    checker.check_area_id(source, &campaign.starting_area);
+    checker.mark_used(
+        YamlResourceKind::Conversation,
+        &campaign.backstory_conversation.id,
+    );
+
+    for location in campaign.world_map.locations.iter() {
+        if let Some(area) = &location.linked_area {
+            checker.check_area_id(source, area);
+        }
+    }
+}
+
+fn check_areas(checker: &mut Checker) {
+    use YamlResourceKind::*;
+    let campaign = Module::campaign();
+
+    for area in Module::all_areas() {
+        let source = Source::new(Area, &area.id);
+
+        for actor in area.actors.iter() {
+            let exists = Module::actor(&actor.id).is_some();
+            checker.reference(source, Actor, &actor.id, exists);
+        }
+
+        for prop in area.props.iter() {
+            checker.mark_used(Prop, &prop.prop.id);
+            for entry in prop.items.iter() {
+                checker.check_item(source, &entry.item);
+            }
+        }
+
+        for encounter in area.encounters.iter() {
+            checker.mark_used(Encounter, &encounter.encounter.id);
+        }
+
+        for transition in area.transitions.iter() {
+            match &transition.to {
+                ToKind::Area { id, .. } | ToKind::FindLink { id, .. } => {
+                    checker.check_area_id(source, id);
+                }
+                ToKind::CurArea { .. } | ToKind::WorldMap => (),
+            }
+        }
+
+        if let Some(generator) = &area.generator {
+            for transition in generator.transitions.iter() {
+                checker.check_area_id(source, &transition.to);
+            }
+        }
+
+        if let Some(location) = &area.world_map_location {
+            if !campaign
+                .world_map
+                .locations
+                .iter()
+                .any(|l| &l.id == location)
+            {
+                let message = format!("world map location '{location}' does not exist");
+                checker.issue(Severity::Error, IssueKind::UnresolvedId, source, message);
+            }
+        }
+
+        for trigger in area.triggers.iter() {
+            checker.check_triggers(source, trigger.on_activate.iter());
+        }
+    }
+}
+
+fn check_actors(checker: &mut Checker) {
+    use YamlResourceKind::*;
+
+    for actor in Module::all_actors() {
+        let source = Source::new(Actor, &actor.id);
+
+        for item in actor.inventory.items_iter() {
+            checker.check_item(source, item);
+        }
+
+        if let Some(convo) = &actor.conversation {
+            checker.mark_used(Conversation, &convo.id);
+        }
+
+        let loot = actor
+            .reward
+            .as_ref()
+            .and_then(|reward| reward.loot.as_ref());
+        if let Some(loot) = loot {
+            checker.mark_used(LootList, &loot.id);
+        }
+    }
+
+    for encounter in Module::all_encounters() {
+        for actor in encounter.actors() {
+            checker.mark_used(Actor, &actor.id);
+        }
+    }
+}
+
+fn check_resources(checker: &mut Checker) {
+    use YamlResourceKind::*;
+
+    for list in Module::all_loot_lists() {
+        let source = Source::new(LootList, &list.id);
+        for id in list.sub_list_ids() {
+            let exists = Module::loot_list(id).is_some();
+            checker.reference(source, LootList, id, exists);
+        }
+    }
+
+    for prop in Module::all_props() {
+        let source = Source::new(Prop, &prop.id);
+        match &prop.interactive {
+            Interactive::Container { loot: Some(loot) } => checker.mark_used(LootList, &loot.id),
+            Interactive::Door { on_activate, .. } => {
+                checker.check_triggers(source, on_activate.iter());
+            }
+            _ => (),
+        }
+    }
+
+    for cutscene in Module::all_cutscenes() {
+        let source = Source::new(Cutscene, &cutscene.id);
+        checker.check_triggers(source, cutscene.on_end.iter());
+    }
+}
+
+fn check_conversations(checker: &mut Checker) {
+    for convo in Module::all_conversations() {
+        let source = Source::new(YamlResourceKind::Conversation, &convo.id);
+
+        let triggers = convo.all_triggers();
+        checker.check_triggers(source, triggers.iter().map(|(_, trigger)| *trigger));
+
+        for node in convo.unreachable_nodes() {
+            let message = format!("node '{node}' cannot be reached from an initial node");
+            checker.issue(
+                Severity::Warning,
+                IssueKind::UnreachableNode,
+                source,
+                message,
+            );
+        }
+    }
+}
+
+fn check_quests(checker: &mut Checker) {
+    for quest in Module::all_quests() {
+        let source = Source::new(YamlResourceKind::Quest, &quest.id);
+
+        let mut entries: Vec<&String> = quest.entries.keys().collect();
+        entries.sort();
+        for entry in entries {
+            let key = (quest.id.to_string(), entry.to_string());
+            if checker.used_entries.contains(&key) || checker.script_strings.contains(entry) {
+                continue;
+            }
+
+            let message = format!("entry '{entry}' is never referenced");
+            checker.issue(
+                Severity::Warning,
+                IssueKind::UnreferencedQuestEntry,
+                source,
+                message,
+            );
+        }
+    }
+}
+
+fn check_unused(checker: &mut Checker) {
+    use YamlResourceKind::*;
+
+    for kind in UNUSED_KINDS {
+        let mut ids: Vec<String> = match kind {
+            Actor => ids(Module::all_actors().iter().map(|r| &r.id)),
+            Area => ids(Module::all_areas().iter().map(|r| &r.id)),
+            Conversation => ids(Module::all_conversations().iter().map(|r| &r.id)),
+            Cutscene => ids(Module::all_cutscenes().iter().map(|r| &r.id)),
+            LootList => ids(Module::all_loot_lists().iter().map(|r| &r.id)),
+            Quest => ids(Module::all_quests().iter().map(|r| &r.id)),
+            _ => Vec::new(),
+        };
+        ids.sort();
+
+        for id in ids.iter() {
+            if checker.used.contains(&(kind, id.to_string()))
+                || checker.script_strings.contains(id)
+                || !checker.is_campaign_resource(kind, id)
+            {
+                continue;
+            }
+
+            let source = Source::new(kind, id);
+            let message = "is never referenced".to_string();
+            checker.issue(
+                Severity::Warning,
+                IssueKind::UnusedResource,
+                source,
+                message,
+            );
+        }
+    }
+}
+
+fn ids<'a>(iter: impl Iterator<Item = &'a String>) -> Vec<String> {
+    iter.map(|id| id.to_string()).collect()
+}
+
+/// Returns the contents of every single line string literal in the script.
+/// Scripts refer to resources by ID, so any resource ID appearing as a
+/// literal is considered referenced.
+fn string_literals(script: &str) -> Vec<String> {
+    let mut literals = Vec::new();
+    for line in script.lines() {
+        let mut chars = line.chars();
+        let mut prev = None;
+        while let Some(c) = chars.next() {
+            if c == '-' && prev == Some('-') {
+                break;
+            }
+            prev = Some(c);
+
+            if c != '"' && c != '\'' {
+                continue;
+            }
+
+            let mut literal = String::new();
+            let mut escaped = false;
+            for next in chars.by_ref() {
+                if escaped {
+                    escaped = false;
+                } else if next == '\\' {
+                    escaped = true;
+                    continue;
+                } else if next == c {
+                    break;
+                }
+                literal.push(next);
+            }
+            literals.push(literal);
+            prev = None;
+        }
+    }
+    literals
+}
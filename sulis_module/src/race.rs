@@ -48,6 +48,10 @@ pub struct Race {
     pub male_random_names: Vec<String>,
     pub female_random_names: Vec<String>,
     pub disabled_slots: Vec<Slot>,
+
+    /// Categories of effect, such as `fear` or `poison`, members of this
+    /// race cannot be affected by
+    pub immunities: Vec<String>,
     pub hair_colors: Vec<Color>,
     pub skin_colors: Vec<Color>,
     pub ticker_offset: (f32, f32),
@@ -146,6 +150,7 @@ impl Race {
             move_anim_rate: builder.move_anim_rate,
             size,
             disabled_slots: builder.disabled_slots,
+            immunities: builder.immunities,
             base_stats: builder.base_stats,
             base_attack: builder.base_attack,
             default_images,
@@ -219,6 +224,10 @@ impl Race {
         }
         false
     }
+
+    pub fn is_immune(&self, category: &str) -> bool {
+        self.immunities.iter().any(|immunity| immunity == category)
+    }
 }
 
 fn float_1() -> f32 {
@@ -271,4 +280,7 @@ pub struct RaceBuilder {
 
     #[serde(default)]
     disabled_slots: Vec<Slot>,
+
+    #[serde(default)]
+    immunities: Vec<String>,
 }
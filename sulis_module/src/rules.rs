@@ -54,7 +54,7 @@ pub use self::stat_list::StatList;
 
 use crate::area::LocationKind;
 use sulis_core::ui::{color, Color};
-use sulis_core::util::{gen_rand, invalid_data_error};
+use sulis_core::util::{gen_rand, gen_visual_rand, invalid_data_error};
 
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
@@ -119,6 +119,231 @@ pub struct Rules {
     pub hints: Vec<String>,
 
     pub main_menu_music: Option<String>,
+
+    #[serde(default)]
+    pub morale: MoraleRules,
+
+    #[serde(default)]
+    pub reactions: ReactionRules,
+
+    #[serde(default)]
+    pub cover: CoverRules,
+
+    #[serde(default)]
+    pub elevation: ElevationRules,
+}
+
+/// How the morale of AI controlled entities changes during combat.  Morale
+/// is measured in points, with entities starting each combat at their
+/// maximum morale, `base` plus any `Morale` bonuses.  The thresholds are
+/// fractions of the entity's maximum morale.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct MoraleRules {
+    pub base: i32,
+
+    /// The morale lost when losing hit points equal to the entity's maximum
+    /// hit points.  Smaller amounts of damage lose a proportional amount.
+    pub hp_loss: f32,
+
+    /// The morale lost when a nearby friendly entity dies
+    pub ally_death: i32,
+
+    /// The additional morale lost by the members of an AI group when the
+    /// group's leader dies.  The leader is the member with a higher level
+    /// than all the others, if there is one.
+    pub leader_death: i32,
+
+    /// The morale regained at the start of each of the entity's turns
+    pub recovery_per_round: i32,
+
+    /// Entities at or below this fraction of their morale flee
+    pub flee_threshold: f32,
+
+    /// Entities at or below this fraction of their morale surrender
+    pub surrender_threshold: f32,
+
+    /// Fleeing entities rally once they regain this fraction of their morale
+    pub rally_threshold: f32,
+}
+
+impl Default for MoraleRules {
+    fn default() -> MoraleRules {
+        MoraleRules {
+            base: 100,
+            hp_loss: 100.0,
+            ally_death: 15,
+            leader_death: 30,
+            recovery_per_round: 5,
+            flee_threshold: 0.25,
+            surrender_threshold: 0.1,
+            rally_threshold: 0.5,
+        }
+    }
+}
+
+/// The reactions entities may take outside of their own turn during combat.
+/// Each reaction uses up one of the entity's reactions for the round, which
+/// are regained at the start of its turn.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ReactionRules {
+    /// The number of reactions each entity has per round, before any
+    /// `Reactions` bonuses
+    pub per_round: i32,
+
+    /// The kinds of reaction every entity may take.  Others may be granted
+    /// with `Reaction` bonuses.
+    pub kinds: Vec<ReactionKind>,
+
+    /// The ability groups, such as spells, whose abilities provoke an
+    /// `Interrupt` reaction when activated
+    pub interrupt_groups: Vec<String>,
+}
+
+impl Default for ReactionRules {
+    fn default() -> ReactionRules {
+        ReactionRules {
+            per_round: 1,
+            kinds: vec![ReactionKind::OpportunityAttack],
+            interrupt_groups: Vec::new(),
+        }
+    }
+}
+
+/// The cover a target has against ranged and spell attacks, from props,
+/// walls, and other entities between it and the attacker.  Cover is graded
+/// by the fraction of lines from the attacker to the target's squares that
+/// are obstructed.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct CoverRules {
+    /// The obstructed fraction at or above which the target has partial cover
+    pub partial_threshold: f32,
+
+    /// The obstructed fraction at or above which the target has heavy cover
+    pub heavy_threshold: f32,
+
+    pub partial: CoverBonus,
+    pub heavy: CoverBonus,
+
+    /// Whether entities other than the attacker and target provide cover
+    pub entities: bool,
+}
+
+impl Default for CoverRules {
+    fn default() -> CoverRules {
+        CoverRules {
+            partial_threshold: 0.5,
+            heavy_threshold: 0.75,
+            partial: CoverBonus {
+                defense: 10,
+                concealment: 0,
+            },
+            heavy: CoverBonus {
+                defense: 20,
+                concealment: 20,
+            },
+            entities: true,
+        }
+    }
+}
+
+impl CoverRules {
+    pub fn cover(&self, obstructed_frac: f32) -> CoverKind {
+        if obstructed_frac >= self.heavy_threshold {
+            CoverKind::Heavy
+        } else if obstructed_frac >= self.partial_threshold {
+            CoverKind::Partial
+        } else {
+            CoverKind::None
+        }
+    }
+
+    pub fn bonus(&self, cover: CoverKind) -> CoverBonus {
+        match cover {
+            CoverKind::None => CoverBonus::default(),
+            CoverKind::Partial => self.partial,
+            CoverKind::Heavy => self.heavy,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct CoverBonus {
+    pub defense: i32,
+    pub concealment: i32,
+}
+
+/// Modifiers for attacking from higher or lower ground than the target,
+/// using the elevation of the squares at the center of each entity.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ElevationRules {
+    /// The minimum difference in elevation for an attacker to be on higher
+    /// or lower ground
+    pub min_difference: u8,
+
+    pub high_ground: ElevationBonus,
+    pub low_ground: ElevationBonus,
+}
+
+impl Default for ElevationRules {
+    fn default() -> ElevationRules {
+        ElevationRules {
+            min_difference: 1,
+            high_ground: ElevationBonus {
+                accuracy: 10,
+                range: 2.0,
+                damage: 0.1,
+            },
+            low_ground: ElevationBonus {
+                accuracy: -10,
+                range: 0.0,
+                damage: 0.0,
+            },
+        }
+    }
+}
+
+impl ElevationRules {
+    pub fn kind(&self, attacker: u8, target: u8) -> ElevationKind {
+        if attacker >= target.saturating_add(self.min_difference) {
+            ElevationKind::High
+        } else if target >= attacker.saturating_add(self.min_difference) {
+            ElevationKind::Low
+        } else {
+            ElevationKind::Level
+        }
+    }
+
+    pub fn bonus(&self, kind: ElevationKind) -> ElevationBonus {
+        match kind {
+            ElevationKind::Level => ElevationBonus::default(),
+            ElevationKind::High => self.high_ground,
+            ElevationKind::Low => self.low_ground,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ElevationBonus {
+    /// Added to melee, ranged, and spell accuracy
+    pub accuracy: i32,
+
+    /// Added to the attack distance of ranged attacks
+    pub range: f32,
+
+    /// Added to the damage multiplier of graze, hit, and crit
+    pub damage: f32,
 }
 
 impl Rules {
@@ -128,12 +353,15 @@ impl Rules {
         }
     }
 
+    /// Picks a hint to show on the main menu.  This is shown outside of
+    /// any game, so it uses the visual random generator; using the gameplay
+    /// generator would shift the seeded sequence each time the menu is shown.
     pub fn random_hint(&self) -> String {
         match self.hints.len() {
             0 => "",
             1 => &self.hints[0],
             _ => {
-                let index = gen_rand(0, self.hints.len() - 1);
+                let index = gen_visual_rand(0, self.hints.len() - 1);
                 &self.hints[index]
             }
         }
@@ -211,6 +439,18 @@ impl Rules {
         millis
     }
 
+    pub fn compute_time(&self, millis: usize) -> Time {
+        let total_rounds = (millis / ROUND_TIME_MILLIS as usize) as u32;
+        let total_hours = total_rounds / self.rounds_per_hour;
+
+        Time {
+            day: total_hours / self.hours_per_day,
+            hour: total_hours % self.hours_per_day,
+            round: total_rounds % self.rounds_per_hour,
+            millis: (millis % ROUND_TIME_MILLIS as usize) as u32,
+        }
+    }
+
     pub fn get_hour_name(&self, hour: u32) -> &str {
         assert!((hour as usize) < self.hour_names.len());
 
@@ -531,6 +771,125 @@ impl FromStr for HitKind {
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Default)]
+pub enum CoverKind {
+    #[default]
+    None,
+    Partial,
+    Heavy,
+}
+
+impl fmt::Display for CoverKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            CoverKind::None => "none",
+            CoverKind::Partial => "partial",
+            CoverKind::Heavy => "heavy",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for CoverKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let val = match s {
+            "none" => CoverKind::None,
+            "partial" => CoverKind::Partial,
+            "heavy" => CoverKind::Heavy,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Unable to parse CoverKind from '{s}'"),
+                ));
+            }
+        };
+
+        Ok(val)
+    }
+}
+
+/// The ground an attacker is on relative to its target
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum ElevationKind {
+    #[default]
+    Level,
+    High,
+    Low,
+}
+
+impl fmt::Display for ElevationKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            ElevationKind::Level => "level",
+            ElevationKind::High => "high",
+            ElevationKind::Low => "low",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl FromStr for ElevationKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let val = match s {
+            "level" => ElevationKind::Level,
+            "high" => ElevationKind::High,
+            "low" => ElevationKind::Low,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Unable to parse ElevationKind from '{s}'"),
+                ));
+            }
+        };
+
+        Ok(val)
+    }
+}
+
+/// A reaction, named for the weapon attack an entity makes in response to
+/// a hostile's action
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum ReactionKind {
+    /// A hostile the entity threatens moves out of its reach
+    OpportunityAttack,
+
+    /// A hostile's weapon attack against the entity misses
+    Counterattack,
+
+    /// A hostile the entity threatens activates an ability
+    Interrupt,
+}
+
+impl fmt::Display for ReactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl FromStr for ReactionKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let val = match s {
+            "opportunity_attack" => ReactionKind::OpportunityAttack,
+            "counterattack" => ReactionKind::Counterattack,
+            "interrupt" => ReactionKind::Interrupt,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Unable to parse ReactionKind from '{s}'"),
+                ));
+            }
+        };
+
+        Ok(val)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum WeaponStyle {
     Ranged,
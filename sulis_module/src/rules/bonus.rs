@@ -19,7 +19,7 @@ use std::mem;
 use serde::{Deserialize, Serialize};
 
 use crate::rules::{
-    ArmorKind, Attribute, Damage, DamageKind, HitKind, Slot, WeaponKind, WeaponStyle,
+    ArmorKind, Attribute, Damage, DamageKind, HitKind, ReactionKind, Slot, WeaponKind, WeaponStyle,
 };
 use sulis_core::util::ExtInt;
 
@@ -59,6 +59,9 @@ pub enum BonusKind {
     AttackCost(i32),
     FlankingAngle(i32),
     CasterLevel(i32),
+    Morale(i32),
+    Reactions(i32),
+    Reaction(ReactionKind),
     AbilityActionPointCost(i32),
     FreeAbilityGroupUse,
     MoveDisabled,
@@ -68,6 +71,7 @@ pub enum BonusKind {
     FlankedImmunity,
     SneakAttackImmunity,
     CritImmunity,
+    MoraleImmunity,
     GroupUsesPerEncounter { group: String, amount: ExtInt },
     GroupUsesPerDay { group: String, amount: ExtInt },
     ClassStat { id: String, amount: i32 },
@@ -218,6 +222,8 @@ fn apply_modifiers(bonus: &mut Bonus, neg: f32, pos: f32) {
         AttackCost(val) => get_mod!(AttackCost(val): i32, neg, pos),
         FlankingAngle(val) => get_mod!(FlankingAngle(val): i32, neg, pos),
         CasterLevel(val) => get_mod!(CasterLevel(val): i32, neg, pos),
+        Morale(val) => get_mod!(Morale(val): i32, neg, pos),
+        Reactions(val) => get_mod!(Reactions(val): i32, neg, pos),
         AbilityActionPointCost(val) => get_mod!(AbilityActionPointCost(val): i32, neg, pos),
         Damage(damage) => Damage(damage.mult_f32(pos)),
         ClassStat { ref id, amount } => ClassStat {
@@ -229,6 +235,7 @@ fn apply_modifiers(bonus: &mut Bonus, neg: f32, pos: f32) {
         Attribute { attribute, amount } => get_mod!(Attribute { attribute, amount }: i8, neg, pos),
         ArmorProficiency(_)
         | WeaponProficiency(_)
+        | Reaction(_)
         | MoveDisabled
         | AttackDisabled
         | Hidden
@@ -237,6 +244,7 @@ fn apply_modifiers(bonus: &mut Bonus, neg: f32, pos: f32) {
         | FlankedImmunity
         | SneakAttackImmunity
         | CritImmunity
+        | MoraleImmunity
         | AbilitiesDisabled
         | FreeAbilityGroupUse => return,
     };
@@ -330,6 +338,7 @@ pub fn merge_if_dup(first: &Bonus, sec: &Bonus) -> Option<Bonus> {
         },
         ArmorProficiency(kind) => merge_dup!(ArmorProficiency(kind): sec, test_name, when),
         WeaponProficiency(kind) => merge_dup!(WeaponProficiency(kind): sec, test_name, when),
+        Reaction(kind) => merge_dup!(Reaction(kind): sec, test_name, when),
 
         MoveDisabled => merge_dup!(MoveDisabled: sec, when),
         AbilitiesDisabled => merge_dup!(AbilitiesDisabled: sec, when),
@@ -338,6 +347,7 @@ pub fn merge_if_dup(first: &Bonus, sec: &Bonus) -> Option<Bonus> {
         FlankedImmunity => merge_dup!(FlankedImmunity: sec, when),
         SneakAttackImmunity => merge_dup!(SneakAttackImmunity: sec, when),
         CritImmunity => merge_dup!(CritImmunity: sec, when),
+        MoraleImmunity => merge_dup!(MoraleImmunity: sec, when),
         FreeAbilityGroupUse => merge_dup!(FreeAbilityGroupUse: sec, when),
 
         GroupUsesPerEncounter { ref group, amount } => {
@@ -375,6 +385,8 @@ pub fn merge_if_dup(first: &Bonus, sec: &Bonus) -> Option<Bonus> {
         AttackCost(val) => merge_dup!(AttackCost(val): sec, when),
         FlankingAngle(val) => merge_dup!(FlankingAngle(val): sec, when),
         CasterLevel(val) => merge_dup!(CasterLevel(val): sec, when),
+        Morale(val) => merge_dup!(Morale(val): sec, when),
+        Reactions(val) => merge_dup!(Reactions(val): sec, when),
     }
 }
 
@@ -19,8 +19,8 @@ use std::rc::Rc;
 
 use crate::rules::bonus::{AttackBonuses, AttackBuilder, Bonus, BonusKind, BonusList};
 use crate::rules::{
-    AccuracyKind, Armor, ArmorKind, Attack, AttributeList, Damage, HitKind, Resistance, Slot,
-    WeaponKind, WeaponStyle,
+    AccuracyKind, Armor, ArmorKind, Attack, AttributeList, Damage, HitKind, ReactionKind,
+    Resistance, Slot, WeaponKind, WeaponStyle,
 };
 use crate::{Actor, Module};
 use sulis_core::image::Image;
@@ -81,6 +81,10 @@ pub struct StatList {
     pub crit_immunity: bool,
     pub free_ability_group_use: bool,
     pub caster_level: i32,
+    pub morale: i32,
+    pub morale_immunity: bool,
+    pub reactions: i32,
+    reaction_kinds: Vec<ReactionKind>,
     has_shield: bool,
     group_uses_per_encounter: HashMap<String, ExtInt>,
     group_uses_per_day: HashMap<String, ExtInt>,
@@ -136,6 +140,10 @@ impl StatList {
             crit_immunity: false,
             free_ability_group_use: false,
             caster_level: 0,
+            morale: 0,
+            morale_immunity: false,
+            reactions: 0,
+            reaction_kinds: Vec::new(),
             has_shield: false,
             group_uses_per_encounter: HashMap::new(),
             group_uses_per_day: HashMap::new(),
@@ -225,6 +233,11 @@ impl StatList {
         self.has_shield
     }
 
+    /// Whether this StatList is able to use the specified kind of reaction
+    pub fn has_reaction(&self, kind: ReactionKind) -> bool {
+        self.reaction_kinds.contains(&kind)
+    }
+
     pub fn attack_is_melee(&self) -> bool {
         if self.attacks.is_empty() {
             return false;
@@ -374,6 +387,13 @@ impl StatList {
             AttackCost(amount) => self.attack_cost -= amount * times_i32,
             FlankingAngle(amount) => self.flanking_angle -= amount * times_i32,
             CasterLevel(amount) => self.caster_level += amount * times_i32,
+            Morale(amount) => self.morale += amount * times_i32,
+            Reactions(amount) => self.reactions += amount * times_i32,
+            Reaction(kind) => {
+                if !self.reaction_kinds.contains(kind) {
+                    self.reaction_kinds.push(*kind);
+                }
+            }
             FreeAbilityGroupUse => self.free_ability_group_use = true,
             AbilitiesDisabled => self.abilities_disabled = true,
             MoveDisabled => self.move_disabled = true,
@@ -382,6 +402,7 @@ impl StatList {
             FlankedImmunity => self.flanked_immunity = true,
             SneakAttackImmunity => self.sneak_attack_immunity = true,
             CritImmunity => self.crit_immunity = true,
+            MoraleImmunity => self.morale_immunity = true,
             GroupUsesPerEncounter { group, amount } => {
                 self.add_single_group_uses_per_encounter(group, *amount)
             }
@@ -525,6 +546,13 @@ impl StatList {
         self.movement_rate += actor.race.movement_rate;
         self.move_anim_rate += actor.race.move_anim_rate;
         self.attack_cost += rules.attack_ap as i32;
+        self.morale = (self.morale + rules.morale.base).max(0);
+        self.reactions = (self.reactions + rules.reactions.per_round).max(0);
+        for kind in rules.reactions.kinds.iter() {
+            if !self.reaction_kinds.contains(kind) {
+                self.reaction_kinds.push(*kind);
+            }
+        }
 
         let size_bonus = actor.race.size.diagonal / 2.0;
         self.touch_range = self.bonus_reach + size_bonus;
@@ -0,0 +1,261 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Expansion of `--INCLUDE <script_id>` directives in scripts.  Each
+//! directive is replaced by the contents of the referenced script, which may
+//! itself contain further directives.  A script is only included once into
+//! any given script; later directives for the same script are left empty.
+//! Directives which would include a script into itself, directly or
+//! through other includes, are reported as errors and not expanded.
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+pub const INCLUDE_DIRECTIVE: &str = "--INCLUDE";
+
+/// Scripts re-read from disk by `Module::reload_scripts`.  These are not
+/// used by the module until passed to `Module::apply_script_reload`.
+#[derive(Debug)]
+pub struct ScriptReload {
+    pub(crate) scripts: HashMap<String, String>,
+
+    /// Each affected script ID, with its newly expanded script, or `None`
+    /// if the script no longer exists
+    pub affected: Vec<(String, Option<Rc<ExpandedScript>>)>,
+}
+
+/// A script with all of its include directives expanded, which keeps track
+/// of the original script and line of each expanded line.
+#[derive(Debug)]
+pub struct ExpandedScript {
+    pub id: String,
+    pub source: String,
+
+    /// A description of each directive that could not be expanded
+    pub errors: Vec<String>,
+
+    // the IDs of this script and all scripts included into it
+    files: Vec<String>,
+
+    // for each expanded line, the index into files and the line within it
+    lines: Vec<(usize, usize)>,
+}
+
+impl ExpandedScript {
+    /// Expands the script with the specified `id`, which must be present
+    /// in `scripts`, including any other scripts it references.
+    pub fn new(id: &str, scripts: &HashMap<String, String>) -> ExpandedScript {
+        let mut script = ExpandedScript {
+            id: id.to_string(),
+            source: String::new(),
+            errors: Vec::new(),
+            files: Vec::new(),
+            lines: Vec::new(),
+        };
+
+        let mut stack = vec![id.to_string()];
+        let mut included = HashSet::new();
+        included.insert(id.to_string());
+        script.expand(id, scripts, &mut stack, &mut included);
+
+        script
+    }
+
+    /// Returns the ID of the script and the line within that script, both
+    /// one based, that the specified one based line of the expanded source
+    /// originally came from.
+    pub fn source_location(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, line) = self.lines.get(line.checked_sub(1)?)?;
+        Some((&self.files[*file], line + 1))
+    }
+
+    /// Returns true if the specified script is this script or is included
+    /// into it
+    pub fn includes(&self, id: &str) -> bool {
+        self.files.iter().any(|file| file == id)
+    }
+
+    fn expand(
+        &mut self,
+        id: &str,
+        scripts: &HashMap<String, String>,
+        stack: &mut Vec<String>,
+        included: &mut HashSet<String>,
+    ) {
+        let file = self.files.len();
+        self.files.push(id.to_string());
+
+        let src = scripts.get(id).map_or("", |src| src.as_str());
+        for (index, line) in src.lines().enumerate() {
+            let target = match line.trim().strip_prefix(INCLUDE_DIRECTIVE) {
+                None => {
+                    self.push_line(line, file, index);
+                    continue;
+                }
+                Some(target) => target.trim(),
+            };
+
+            let location = format!("'{}' line {}", id, index + 1);
+            if target.is_empty() {
+                self.errors.push(format!(
+                    "Invalid {INCLUDE_DIRECTIVE} directive in {location}, no script specified"
+                ));
+                self.push_line(line, file, index);
+            } else if stack.iter().any(|parent| parent == target) {
+                self.errors.push(format!(
+                    "Invalid {INCLUDE_DIRECTIVE} directive in {location}, cyclic include: {} -> {}",
+                    stack.join(" -> "),
+                    target
+                ));
+                self.push_line(line, file, index);
+            } else if !scripts.contains_key(target) {
+                self.errors.push(format!(
+                    "Invalid {INCLUDE_DIRECTIVE} directive in {location}, script '{target}' \
+                    does not exist"
+                ));
+                self.push_line(line, file, index);
+            } else if included.contains(target) {
+                // the script has already been included once
+                self.push_line("", file, index);
+            } else {
+                included.insert(target.to_string());
+                stack.push(target.to_string());
+                self.expand(target, scripts, stack, included);
+                stack.pop();
+            }
+        }
+    }
+
+    fn push_line(&mut self, line: &str, file: usize, index: usize) {
+        self.source.push_str(line);
+        self.source.push('\n');
+        self.lines.push((file, index));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(id, src)| (id.to_string(), src.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn no_includes() {
+        let scripts = scripts(&[("main", "a = 1\nb = 2")]);
+        let script = ExpandedScript::new("main", &scripts);
+
+        assert_eq!(script.source, "a = 1\nb = 2\n");
+        assert!(script.errors.is_empty());
+        assert_eq!(script.source_location(1), Some(("main", 1)));
+        assert_eq!(script.source_location(2), Some(("main", 2)));
+        assert_eq!(script.source_location(0), None);
+        assert_eq!(script.source_location(3), None);
+    }
+
+    #[test]
+    fn nested_includes() {
+        let scripts = scripts(&[
+            ("main", "--INCLUDE lib\nmain_line"),
+            ("lib", "lib_line\n  --INCLUDE util  \nlib_end"),
+            ("util", "util_line"),
+        ]);
+        let script = ExpandedScript::new("main", &scripts);
+
+        assert!(script.errors.is_empty());
+        assert_eq!(script.source, "lib_line\nutil_line\nlib_end\nmain_line\n");
+        assert!(script.includes("main"));
+        assert!(script.includes("lib"));
+        assert!(script.includes("util"));
+        assert!(!script.includes("other"));
+    }
+
+    #[test]
+    fn maps_lines_to_original_files() {
+        let scripts = scripts(&[
+            ("main", "first\n--INCLUDE lib\nlast"),
+            ("lib", "lib_1\nlib_2"),
+        ]);
+        let script = ExpandedScript::new("main", &scripts);
+
+        assert_eq!(script.source, "first\nlib_1\nlib_2\nlast\n");
+        assert_eq!(script.source_location(1), Some(("main", 1)));
+        assert_eq!(script.source_location(2), Some(("lib", 1)));
+        assert_eq!(script.source_location(3), Some(("lib", 2)));
+        assert_eq!(script.source_location(4), Some(("main", 3)));
+    }
+
+    #[test]
+    fn includes_each_script_once() {
+        let scripts = scripts(&[
+            ("main", "--INCLUDE a\n--INCLUDE b\n--INCLUDE a\nend"),
+            ("a", "--INCLUDE common\na_line"),
+            ("b", "--INCLUDE common\nb_line"),
+            ("common", "common_line"),
+        ]);
+        let script = ExpandedScript::new("main", &scripts);
+
+        assert!(script.errors.is_empty());
+        assert_eq!(script.source, "common_line\na_line\n\nb_line\n\nend\n");
+        assert_eq!(script.source.matches("common_line").count(), 1);
+
+        // the skipped directives keep their own lines
+        assert_eq!(script.source_location(3), Some(("b", 1)));
+        assert_eq!(script.source_location(5), Some(("main", 3)));
+        assert_eq!(script.source_location(6), Some(("main", 4)));
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let scripts = scripts(&[
+            ("main", "--INCLUDE a\nmain_line"),
+            ("a", "--INCLUDE main\na_line"),
+        ]);
+        let script = ExpandedScript::new("main", &scripts);
+
+        assert_eq!(script.errors.len(), 1);
+        assert!(script.errors[0].contains("cyclic include: main -> a -> main"));
+
+        // the cyclic directive is left in place, unexpanded
+        assert_eq!(script.source, "--INCLUDE main\na_line\nmain_line\n");
+        assert_eq!(script.source_location(1), Some(("a", 1)));
+    }
+
+    #[test]
+    fn detects_self_include() {
+        let scripts = scripts(&[("main", "--INCLUDE main")]);
+        let script = ExpandedScript::new("main", &scripts);
+
+        assert_eq!(script.errors.len(), 1);
+        assert!(script.errors[0].contains("cyclic include"));
+        assert_eq!(script.source, "--INCLUDE main\n");
+    }
+
+    #[test]
+    fn reports_invalid_directives() {
+        let scripts = scripts(&[("main", "--INCLUDE\n--INCLUDE missing\nline")]);
+        let script = ExpandedScript::new("main", &scripts);
+
+        assert_eq!(script.errors.len(), 2);
+        assert!(script.errors[0].contains("'main' line 1, no script specified"));
+        assert!(script.errors[1].contains("'main' line 2, script 'missing' does not exist"));
+        assert_eq!(script.source, "--INCLUDE\n--INCLUDE missing\nline\n");
+    }
+}
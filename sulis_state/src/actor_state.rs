@@ -22,7 +22,7 @@ use std::rc::Rc;
 use crate::save_state::ActorSaveState;
 use crate::{
     ability_state::DisabledReason, AbilityState, ChangeListenerList, Effect, EntityState,
-    GameState, Inventory, PStats,
+    GameState, Inventory, MoraleState, PStats,
 };
 use sulis_core::image::{Image, LayeredImage};
 use sulis_core::io::GraphicsRenderer;
@@ -646,18 +646,22 @@ impl ActorState {
             }
         };
 
-        debug!(
-            "Adding XP {} to '{}'",
-            reward.xp,
-            parent.borrow().actor.actor.id
-        );
-        if parent.borrow().is_party_member() || parent.borrow().actor.faction() == Faction::Friendly
-        {
-            for member in GameState::party().iter() {
-                member.borrow_mut().add_xp(reward.xp);
+        // the party was already rewarded when the target surrendered
+        if !target.borrow().actor.has_surrendered() {
+            debug!(
+                "Adding XP {} to '{}'",
+                reward.xp,
+                parent.borrow().actor.actor.id
+            );
+            if parent.borrow().is_party_member()
+                || parent.borrow().actor.faction() == Faction::Friendly
+            {
+                for member in GameState::party().iter() {
+                    member.borrow_mut().add_xp(reward.xp);
+                }
+            } else {
+                parent.borrow_mut().add_xp(reward.xp);
             }
-        } else {
-            parent.borrow_mut().add_xp(reward.xp);
         }
 
         let loot = match reward.loot {
@@ -756,6 +760,41 @@ impl ActorState {
         self.listeners.notify(self);
     }
 
+    pub fn morale(&self) -> i32 {
+        self.p_stats.morale(&self.stats)
+    }
+
+    pub fn morale_state(&self) -> MoraleState {
+        self.p_stats.morale_state()
+    }
+
+    pub fn has_surrendered(&self) -> bool {
+        self.p_stats.morale_state() == MoraleState::Surrendered
+    }
+
+    pub(crate) fn change_morale(&mut self, amount: i32) -> Option<MoraleState> {
+        let state = self.p_stats.change_morale(amount, &self.stats);
+        self.listeners.notify(self);
+        state
+    }
+
+    pub(crate) fn reset_morale(&mut self) {
+        self.p_stats.reset_morale();
+        self.listeners.notify(self);
+    }
+
+    pub fn reactions_remaining(&self) -> i32 {
+        self.p_stats.reactions_remaining(&self.stats)
+    }
+
+    /// Uses up one reaction for this round, returning false if there are
+    /// none remaining
+    pub(crate) fn use_reaction(&mut self) -> bool {
+        let used = self.p_stats.use_reaction(&self.stats);
+        self.listeners.notify(self);
+        used
+    }
+
     pub(crate) fn remove_class_stat(&mut self, stat: &str, amount: u32) {
         self.p_stats.remove_class_stat(stat, amount);
         self.listeners.notify(self);
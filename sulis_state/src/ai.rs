@@ -14,13 +14,20 @@
 //  You should have received a copy of the GNU General Public License
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
+mod group_tactics;
+pub(crate) use self::group_tactics::{GroupTactics, GroupTacticsState};
+
+mod utility_ai;
+use self::utility_ai::UtilityAI;
+
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::ai_trace::{self, TraceEvent};
 use crate::script::script_callback;
 use crate::{animation::Anim, EntityState, GameState, Script};
 use sulis_core::config::Config;
-use sulis_module::ai::FuncKind;
+use sulis_module::ai::{AIBackend, FuncKind};
 
 pub struct AI {
     ai: Option<EntityAI>,
@@ -47,7 +54,9 @@ impl AI {
         }
 
         if entity.borrow().is_party_member() {
-            self.ai = None;
+            if self.ai.take().is_some() {
+                ai_trace::end_turn();
+            }
             return;
         }
 
@@ -71,7 +80,13 @@ impl AI {
             }
 
             self.next_state = match self.next_state {
-                State::Run => ai.run_script(),
+                State::Run => {
+                    let state = ai.run();
+                    ai_trace::record(TraceEvent::State {
+                        state: format!("{state:?}"),
+                    });
+                    state
+                }
                 State::Wait(time) => ai.wait(time),
                 State::End => end(ai),
             };
@@ -84,6 +99,7 @@ fn end(ai: &mut EntityAI) -> State {
         "AI for '{}' is ending.",
         ai.entity.borrow().actor.actor.name
     );
+    ai_trace::end_turn();
     let turn_mgr = GameState::turn_manager();
     let cbs = turn_mgr.borrow_mut().next();
     script_callback::fire_round_elapsed(cbs);
@@ -106,14 +122,17 @@ struct EntityAI {
     entity: Rc<RefCell<EntityState>>,
     actions_taken_this_turn: u32,
     cur_wait_time: u32,
+    utility_ai: UtilityAI,
 }
 
 impl EntityAI {
     fn new(entity: &Rc<RefCell<EntityState>>) -> EntityAI {
+        ai_trace::begin_turn(&entity.borrow());
         EntityAI {
             entity: Rc::clone(entity),
             actions_taken_this_turn: 0,
             cur_wait_time: 0,
+            utility_ai: UtilityAI::default(),
         }
     }
 
@@ -123,12 +142,19 @@ impl EntityAI {
             self.entity.borrow().actor.actor.name
         );
         self.cur_wait_time += time;
+        ai_trace::record(TraceEvent::Wait {
+            time,
+            total: self.cur_wait_time,
+        });
 
         if self.cur_wait_time > MAX_WAIT_TIME {
             warn!(
                 "Wait time for {} exceeded maximum",
                 self.entity.borrow().unique_id()
             );
+            ai_trace::record(TraceEvent::Warning {
+                message: format!("Wait time exceeded maximum of {MAX_WAIT_TIME}"),
+            });
             return State::End;
         }
         let wait_time = Config::animation_base_time_millis() * time;
@@ -138,12 +164,15 @@ impl EntityAI {
         State::Run
     }
 
-    fn run_script(&mut self) -> State {
+    fn run(&mut self) -> State {
         if self.actions_taken_this_turn == MAX_ACTIONS {
             warn!(
                 "Action count for {} exceeded maximum",
                 self.entity.borrow().unique_id()
             );
+            ai_trace::record(TraceEvent::Warning {
+                message: format!("Action count exceeded maximum of {MAX_ACTIONS}"),
+            });
             return State::End;
         }
 
@@ -152,14 +181,27 @@ impl EntityAI {
             Some(template) => Rc::clone(template),
         };
 
-        let func = ai_template
-            .hooks
-            .get(&FuncKind::AiAction)
-            .map(|f| f.as_str())
-            .unwrap_or("ai_action");
-
         self.actions_taken_this_turn += 1;
+        ai_trace::record(TraceEvent::Run {
+            action: self.actions_taken_this_turn,
+            ap: self.entity.borrow().actor.ap(),
+        });
+
+        // an AiAction hook overrides the native AI
+        let func = match ai_template.hooks.get(&FuncKind::AiAction) {
+            Some(func) => func.as_str(),
+            None if ai_template.backend == AIBackend::Native => {
+                return self.utility_ai.run(&self.entity, &ai_template);
+            }
+            None => "ai_action",
+        };
+
+        // plan the group tactics once, for the script to query
+        GroupTactics::new(&self.entity);
 
+        ai_trace::record(TraceEvent::Script {
+            func: func.to_string(),
+        });
         Script::ai(&self.entity, func)
     }
 }
@@ -0,0 +1,313 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Coordination between the members of an AI group, as configured by the
+//! `tactics` of the encounter the group was spawned from.  The tactics do
+//! not act on their own; instead each member adjusts the weights it gives
+//! its targets, and where it moves, based on the state of the whole group.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ai_trace::{self, TraceEvent};
+use crate::entity_attack_handler::is_flanking_from;
+use crate::{center, dist, is_threat, EntityState, GameState};
+use sulis_module::area::Destination;
+use sulis_module::encounter::Tactics;
+
+// how far past touching the target a flanking position is placed
+const FLANK_GAP: f32 = 0.5;
+
+// how close a member must get to a flanking position
+const FLANK_THRESHOLD: f32 = 1.0;
+
+/// The state of an AI group using tactics, kept for the duration of a combat
+pub struct GroupTacticsState {
+    // the index of the hostile the group is focusing on
+    target: Option<usize>,
+
+    // the combined maximum hit points of the group when it was first seen in
+    // this combat, so that members who have died count as lost hit points
+    max_hp: i32,
+
+    retreating: bool,
+}
+
+/// The tactics of the group an entity belongs to, as of the time the entity
+/// is deciding on an action
+pub struct GroupTactics {
+    tactics: Tactics,
+    members: Vec<Rc<RefCell<EntityState>>>,
+    target: Option<Rc<RefCell<EntityState>>>,
+    retreating: bool,
+}
+
+impl GroupTactics {
+    /// Updates the shared state of the group of the specified entity, such as
+    /// its focus target and whether it is retreating, and returns the
+    /// resulting tactics.  This should be called once each time the entity
+    /// decides on an action.  Returns None if the entity is not part of a
+    /// group or its encounter has no tactics.
+    pub fn new(parent: &Rc<RefCell<EntityState>>) -> Option<GroupTactics> {
+        let (group, tactics, members) = group_members(parent)?;
+        let mgr = GameState::turn_manager();
+
+        let (hp, max_hp) = members.iter().fold((0, 0), |(hp, max_hp), entity| {
+            let entity = entity.borrow();
+            (
+                hp + entity.actor.hp().max(0),
+                max_hp + entity.actor.stats.max_hp,
+            )
+        });
+
+        let (cur_target, retreating) = {
+            let mut mgr = mgr.borrow_mut();
+            let state = mgr
+                .ai_group_tactics
+                .entry(group)
+                .or_insert(GroupTacticsState {
+                    target: None,
+                    max_hp,
+                    retreating: false,
+                });
+
+            if let Some(frac) = tactics.retreat_hp_fraction {
+                if !state.retreating && (hp as f32) < frac * state.max_hp as f32 {
+                    info!("AI group {} is retreating", group);
+                    state.retreating = true;
+                }
+            }
+
+            (state.target, state.retreating)
+        };
+
+        // keep the shared target until it is no longer valid
+        let mut target = cur_target
+            .and_then(|index| mgr.borrow().entity_checked(index))
+            .filter(|target| is_valid_target(&parent.borrow(), &target.borrow()));
+
+        if target.is_none() && tactics.focus_fire > 0.0 {
+            target = choose_target(parent, &members);
+            if let Some(target) = &target {
+                ai_trace::record(TraceEvent::Tactic {
+                    tactic: "choose focus target".to_string(),
+                    target: Some(target.borrow().unique_id().to_string()),
+                });
+            }
+        }
+
+        if let Some(state) = mgr.borrow_mut().ai_group_tactics.get_mut(&group) {
+            state.target = target.as_ref().map(|target| target.borrow().index());
+        }
+
+        Some(GroupTactics {
+            tactics,
+            members,
+            target,
+            retreating,
+        })
+    }
+
+    /// Returns the tactics for the group of the specified entity as of the
+    /// last call to `new` by any group member, without updating the group's
+    /// state.  Returns None if the entity is not part of a group or its
+    /// encounter has no tactics.
+    pub fn current(parent: &Rc<RefCell<EntityState>>) -> Option<GroupTactics> {
+        let (group, tactics, members) = group_members(parent)?;
+        let mgr = GameState::turn_manager();
+
+        let (target, retreating) = match mgr.borrow().ai_group_tactics.get(&group) {
+            None => (None, false),
+            Some(state) => (state.target, state.retreating),
+        };
+
+        let target = target
+            .and_then(|index| mgr.borrow().entity_checked(index))
+            .filter(|target| is_valid_target(&parent.borrow(), &target.borrow()));
+
+        Some(GroupTactics {
+            tactics,
+            members,
+            target,
+            retreating,
+        })
+    }
+
+    pub fn is_retreating(&self) -> bool {
+        self.retreating
+    }
+
+    /// Returns the amount to add to the weight modifiers of the specified
+    /// hostile target
+    pub fn weight_modifier(&self, parent: &EntityState, target: &EntityState) -> f32 {
+        let mut modifier = 0.0;
+
+        if let Some(focus) = &self.target {
+            if focus.borrow().index() == target.index() {
+                modifier += self.tactics.focus_fire;
+            }
+        }
+
+        if self.tactics.protect_casters > 0.0 && !is_caster(parent) {
+            let threatens_caster = self.members.iter().any(|member| {
+                let member = member.borrow();
+                member.index() != parent.index() && is_caster(&member) && is_threat(target, &member)
+            });
+
+            if threatens_caster {
+                modifier += self.tactics.protect_casters;
+            }
+        }
+
+        modifier
+    }
+
+    /// Returns the amount to add to the score of an attack against the
+    /// target from the parent's current position
+    pub fn flanking_bonus(&self, parent: &EntityState, target: &EntityState) -> f32 {
+        if self.tactics.flanking <= 0.0 || !parent.actor.stats.attack_is_melee() {
+            return 0.0;
+        }
+
+        if is_flanking_from(parent, center(parent), target) {
+            self.tactics.flanking
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns a destination on the far side of the target from another
+    /// group member that is already threatening it, from which the parent
+    /// would be flanking the target.  Returns None if there is no such
+    /// position or flanking is not part of the group's tactics.
+    pub fn flanking_dest(&self, parent: &EntityState, target: &EntityState) -> Option<Destination> {
+        if self.tactics.flanking <= 0.0 || !parent.actor.stats.attack_is_melee() {
+            return None;
+        }
+
+        let p_target = center(target);
+        let reach = (target.size.width + parent.size.width) as f32 / 2.0 + FLANK_GAP;
+
+        let mut best: Option<((f32, f32), f32)> = None;
+        for member in self.members.iter() {
+            let member = member.borrow();
+            if member.index() == parent.index() || !is_threat(&member, target) {
+                continue;
+            }
+
+            let p_member = center(&*member);
+            let (dx, dy) = (p_target.0 - p_member.0, p_target.1 - p_member.1);
+            let len = dx.hypot(dy);
+            if len == 0.0 {
+                continue;
+            }
+
+            let pos = (p_target.0 + dx / len * reach, p_target.1 + dy / len * reach);
+            if !is_flanking_from(parent, pos, target) {
+                continue;
+            }
+
+            let p_parent = center(parent);
+            let move_dist = (pos.0 - p_parent.0).hypot(pos.1 - p_parent.1);
+            if best.is_none_or(|(_, best_dist)| move_dist < best_dist) {
+                best = Some((pos, move_dist));
+            }
+        }
+
+        let ((x, y), _) = best?;
+        let half_w = parent.size.width as f32 / 2.0;
+        let half_h = parent.size.height as f32 / 2.0;
+        let mut dest =
+            GameState::get_point_dest(parent, (x - half_w).round(), (y - half_h).round());
+        dest.dist = FLANK_THRESHOLD;
+        Some(dest)
+    }
+}
+
+type GroupMembers = (usize, Tactics, Vec<Rc<RefCell<EntityState>>>);
+
+/// Returns the group of the specified entity, the tactics of its encounter,
+/// and the members of the group still able to fight
+fn group_members(parent: &Rc<RefCell<EntityState>>) -> Option<GroupMembers> {
+    let group = parent.borrow().ai_group()?;
+    let mgr = GameState::turn_manager();
+    let tactics = mgr.borrow().ai_group_encounter(group)?.tactics.clone()?;
+
+    let members = mgr
+        .borrow()
+        .entity_iter()
+        .filter(|entity| {
+            let entity = entity.borrow();
+            entity.ai_group() == Some(group)
+                && !entity.actor.is_dead()
+                && !entity.actor.has_surrendered()
+        })
+        .collect();
+
+    Some((group, tactics, members))
+}
+
+/// Casters are protected by the other members of their group
+fn is_caster(entity: &EntityState) -> bool {
+    entity.actor.stats.caster_level > 0
+}
+
+fn is_valid_target(parent: &EntityState, target: &EntityState) -> bool {
+    !target.actor.is_dead()
+        && !target.actor.stats.hidden
+        && parent.is_hostile(target)
+        && target.location.is_in_area_id(&parent.location.area_id)
+}
+
+/// Chooses the hostile the group will focus on, preferring hostiles that are
+/// weak and close to the group as a whole
+fn choose_target(
+    parent: &Rc<RefCell<EntityState>>,
+    members: &[Rc<RefCell<EntityState>>],
+) -> Option<Rc<RefCell<EntityState>>> {
+    let mgr = GameState::turn_manager();
+    let parent = parent.borrow();
+
+    let mut best: Option<(Rc<RefCell<EntityState>>, f32)> = None;
+    for target in mgr.borrow().entity_iter() {
+        let score = {
+            let target = target.borrow();
+            if !is_valid_target(&parent, &target) {
+                continue;
+            }
+
+            let max_hp = target.actor.stats.max_hp.max(1);
+            let hp_frac = target.actor.hp() as f32 / max_hp as f32;
+
+            let total_dist: f32 = members
+                .iter()
+                .map(|member| dist(&*member.borrow(), &*target))
+                .sum();
+            let mean_dist = total_dist / members.len().max(1) as f32;
+
+            hp_frac + mean_dist / 20.0
+        };
+
+        if best
+            .as_ref()
+            .is_none_or(|(_, best_score)| score < *best_score)
+        {
+            best = Some((target, score));
+        }
+    }
+
+    best.map(|(target, _)| target)
+}
@@ -0,0 +1,882 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! The native AI backend.  Each time the entity may act, every candidate
+//! action - attacking, activating an ability, using an item, or moving into
+//! range for one of those - is scored, and the best scoring candidate that
+//! can actually be carried out is performed.  Scoring uses the `AIData` of
+//! abilities and items, and otherwise follows the same rules and reads the
+//! same params as the `ai_basic` script.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::ability_state::DisabledReason;
+use crate::ai::{GroupTactics, State};
+use crate::ai_trace::{self, Outcome, TraceEvent};
+use crate::script::{get_on_activate_fn, move_towards_dest, AreaTargeter, ScriptItemKind};
+use crate::{dist, is_within_attack_dist, is_within_touch_dist, EntityState, GameState};
+use crate::{MoraleState, Script};
+use sulis_core::util::gen_rand;
+use sulis_module::ability::{AIData, AIGroup, AIKind, AIRange, AITarget};
+use sulis_module::{AITemplate, QuickSlot, WeaponStyle};
+
+const WAIT_TIME: u32 = 10;
+const MOVE_THRESHOLD: f32 = 0.1;
+const MAX_MOVE_LEN: u32 = 60;
+const HEALING_FRAC: f32 = 0.5;
+const SHORT_RANGE: f32 = 8.0;
+const MIN_MULTIPLE_SCORE: f32 = 1.5;
+const RUN_AWAY_DIST: f32 = 12.0;
+
+// abilities and items score this much plus a bonus based on their priority,
+// so that they are generally preferred over standard attacks
+const ABILITY_BASE_SCORE: f32 = 0.5;
+const PRIORITY_SCORE: f32 = 20.0;
+
+// moving to get in range for an action scores less than performing an
+// action that is already in range
+const MOVE_SCORE_FACTOR: f32 = 0.75;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Action {
+    Ability(String),
+    Item(QuickSlot),
+    Attack,
+}
+
+impl Action {
+    fn name(&self, parent: &Rc<RefCell<EntityState>>) -> String {
+        match self {
+            Action::Ability(id) => id.to_string(),
+            Action::Item(slot) => {
+                let parent = parent.borrow();
+                match parent.actor.inventory().quick(*slot) {
+                    None => format!("{slot:?}"),
+                    Some(item) => item.item.name.to_string(),
+                }
+            }
+            Action::Attack => "attack".to_string(),
+        }
+    }
+}
+
+struct Candidate {
+    action: Action,
+    ai_data: Option<AIData>,
+    target: Rc<RefCell<EntityState>>,
+    score: f32,
+
+    // the distance to move to if the target is not yet in range
+    move_dist: Option<f32>,
+}
+
+fn trace_attempt(action: &str, target: Option<String>, outcome: Outcome) {
+    ai_trace::record(TraceEvent::Attempt {
+        action: action.to_string(),
+        target,
+        outcome,
+    });
+}
+
+/// The set of potential targets and their weights, computed at the start
+/// of each action.  Weights are positive for friendlies and negative for
+/// hostiles, with a larger magnitude for more important targets.
+struct Targets {
+    hostiles: Vec<Rc<RefCell<EntityState>>>,
+    friendlies: Vec<Rc<RefCell<EntityState>>>,
+    weights: HashMap<usize, f32>,
+}
+
+impl Targets {
+    fn new(parent: &Rc<RefCell<EntityState>>, tactics: Option<&GroupTactics>) -> Targets {
+        let mut hostiles = Vec::new();
+        let mut friendlies = Vec::new();
+        let mut weights = HashMap::new();
+
+        let mgr = GameState::turn_manager();
+        let parent_ref = parent.borrow();
+        let area_id = &parent_ref.location.area_id;
+        for entity in mgr.borrow().entity_iter() {
+            {
+                let target = entity.borrow();
+                let hostile = parent_ref.is_hostile(&target);
+                if hostile && target.actor.stats.hidden {
+                    continue;
+                }
+
+                if target.actor.is_dead() || !target.location.is_in_area_id(area_id) {
+                    continue;
+                }
+
+                let mut weight = compute_weight(&parent_ref, &target);
+                if let (true, Some(tactics)) = (hostile, tactics) {
+                    // hostile weights are negative, so increasing the
+                    // priority of a hostile decreases its weight
+                    weight -= tactics.weight_modifier(&parent_ref, &target);
+                }
+                weights.insert(target.index(), weight);
+                if hostile {
+                    hostiles.push(Rc::clone(&entity));
+                    continue;
+                }
+            }
+            friendlies.push(entity);
+        }
+
+        Targets {
+            hostiles,
+            friendlies,
+            weights,
+        }
+    }
+
+    fn weight(&self, target: &EntityState) -> f32 {
+        self.weights.get(&target.index()).copied().unwrap_or(0.0)
+    }
+
+    fn trace(&self) {
+        for target in self.hostiles.iter().chain(self.friendlies.iter()) {
+            let target = target.borrow();
+            ai_trace::record(TraceEvent::Weight {
+                target: target.unique_id().to_string(),
+                weight: self.weight(&target),
+            });
+        }
+    }
+}
+
+/// The native AI for one entity's turn
+#[derive(Default)]
+pub struct UtilityAI {
+    // whether to prefer standard attacks over lower priority abilities,
+    // rolled when needed and reset after each attack
+    force_attack: Option<bool>,
+
+    // abilities and items which were used without any effect this turn
+    failed: HashSet<Action>,
+}
+
+impl UtilityAI {
+    pub fn run(&mut self, parent: &Rc<RefCell<EntityState>>, template: &AITemplate) -> State {
+        let param = |name: &str| template.params.get(name).copied().unwrap_or(0);
+
+        let tactics = GroupTactics::new(parent);
+        let targets = Targets::new(parent, tactics.as_ref());
+        targets.trace();
+
+        if has_effect_with_tag(parent, "fear") {
+            debug!("  Running away due to fear");
+            run_away(parent, &targets.hostiles);
+            trace_attempt("run away", None, Outcome::Moved);
+            return State::End;
+        }
+
+        if parent.borrow().actor.morale_state() == MoraleState::Fleeing {
+            debug!("  Running away due to low morale");
+            run_away(parent, &targets.hostiles);
+            trace_attempt("run away", None, Outcome::Moved);
+            return State::End;
+        }
+
+        if tactics.as_ref().is_some_and(|t| t.is_retreating()) {
+            debug!("  Retreating with group");
+            ai_trace::record(TraceEvent::Tactic {
+                tactic: "retreat".to_string(),
+                target: None,
+            });
+            run_away(parent, &targets.hostiles);
+            return State::End;
+        }
+
+        if swap_weapons_to_melee(parent) {
+            trace_attempt("swap to melee weapons", None, Outcome::Performed);
+            return State::Wait(WAIT_TIME);
+        }
+
+        let force_attack = *self
+            .force_attack
+            .get_or_insert_with(|| gen_rand(0, 100) < param("AttackWhenHasAbilitiesChance"));
+        let max_priority = if force_attack {
+            Some(param("AlwaysUseAbilityPriority").max(0) as u32)
+        } else {
+            None
+        };
+
+        let mut candidates = Vec::new();
+        self.add_item_candidates(parent, &targets, &mut candidates);
+        self.add_ability_candidates(parent, &targets, max_priority, &mut candidates);
+        add_attack_candidates(parent, &targets, tactics.as_ref(), &mut candidates);
+
+        candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+        for candidate in candidates.iter() {
+            ai_trace::record(TraceEvent::Candidate {
+                action: candidate.action.name(parent),
+                target: Some(candidate.target.borrow().unique_id().to_string()),
+                score: candidate.score,
+                move_dist: candidate.move_dist,
+            });
+        }
+
+        let move_tries = param("MeleeAttackMoveTries").clamp(0, 5) as f32;
+        for candidate in candidates {
+            let target = candidate.target.borrow().unique_id().to_string();
+            debug!(
+                "  Checking {:?} against '{}' with score {}",
+                candidate.action, target, candidate.score
+            );
+
+            // find the name now, as using an item may remove it
+            let action = candidate.action.name(parent);
+
+            if let Some(dist) = candidate.move_dist {
+                if candidate.action == Action::Attack && flank(parent, &candidate, tactics.as_ref())
+                {
+                    trace_attempt(&action, Some(target), Outcome::Moved);
+                    return State::Wait(WAIT_TIME);
+                }
+
+                let moved = move_towards(parent, &candidate, dist, move_tries);
+                let outcome = if moved {
+                    Outcome::Moved
+                } else {
+                    Outcome::Failed
+                };
+                trace_attempt(&action, Some(target), outcome);
+                if moved {
+                    return State::Wait(WAIT_TIME);
+                }
+                continue;
+            }
+
+            let performed = self.perform(parent, &candidate, &targets);
+            let outcome = if performed {
+                Outcome::Performed
+            } else {
+                Outcome::Failed
+            };
+            trace_attempt(&action, Some(target), outcome);
+            if performed {
+                if candidate.action == Action::Attack {
+                    self.force_attack = None;
+                }
+                return State::Wait(WAIT_TIME);
+            }
+        }
+
+        if swap_weapons_to_ranged(parent) {
+            trace_attempt("swap to ranged weapons", None, Outcome::Performed);
+            return State::Wait(WAIT_TIME);
+        }
+
+        State::End
+    }
+
+    fn add_item_candidates(
+        &self,
+        parent: &Rc<RefCell<EntityState>>,
+        targets: &Targets,
+        candidates: &mut Vec<Candidate>,
+    ) {
+        for slot in QuickSlot::usable_iter() {
+            let action = Action::Item(*slot);
+            if self.failed.contains(&action) || !parent.borrow().actor.can_use_quick(*slot) {
+                continue;
+            }
+
+            let ai_data = {
+                let parent = parent.borrow();
+                let inventory = parent.actor.inventory();
+                match inventory
+                    .quick(*slot)
+                    .and_then(|item| item.item.usable.as_ref())
+                {
+                    None => continue,
+                    Some(usable) => usable.ai.clone(),
+                }
+            };
+
+            if let Some(candidate) = action_candidate(parent, action, ai_data, targets) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    fn add_ability_candidates(
+        &self,
+        parent: &Rc<RefCell<EntityState>>,
+        targets: &Targets,
+        max_priority: Option<u32>,
+        candidates: &mut Vec<Candidate>,
+    ) {
+        let abilities: Vec<_> = {
+            let parent = parent.borrow();
+            parent
+                .actor
+                .ability_states
+                .iter()
+                .filter(|(id, _)| parent.actor.can_activate(id))
+                .filter_map(|(id, state)| {
+                    let active = state.ability.active.as_ref()?;
+                    Some((id.to_string(), active.ai.clone()))
+                })
+                .collect()
+        };
+
+        for (id, ai_data) in abilities {
+            if ai_data.kind == AIKind::Special {
+                continue;
+            }
+
+            if max_priority.is_some_and(|max| ai_data.priority > max) {
+                continue;
+            }
+
+            let action = Action::Ability(id);
+            if self.failed.contains(&action) {
+                continue;
+            }
+
+            if let Some(candidate) = action_candidate(parent, action, ai_data, targets) {
+                candidates.push(candidate);
+            }
+        }
+    }
+
+    /// Performs the ability or item use or attack, returning true if it
+    /// was performed
+    fn perform(
+        &mut self,
+        parent: &Rc<RefCell<EntityState>>,
+        candidate: &Candidate,
+        targets: &Targets,
+    ) -> bool {
+        // only attacks are without AI data
+        let ai_data = match &candidate.ai_data {
+            None => {
+                EntityState::attack(parent, &candidate.target, None, true);
+                return true;
+            }
+            Some(ai_data) => ai_data,
+        };
+
+        let ap = parent.borrow().actor.ap();
+        match &candidate.action {
+            Action::Ability(id) => {
+                if parent.borrow().actor.can_toggle(id) != DisabledReason::Enabled {
+                    return false;
+                }
+
+                let (index, ability) = {
+                    let parent = parent.borrow();
+                    match parent.actor.ability_states.get(id) {
+                        None => return false,
+                        Some(state) => (parent.index(), Rc::clone(&state.ability)),
+                    }
+                };
+                let func = get_on_activate_fn(false, ai_data);
+                Script::ability_on_activate(index, func, &ability);
+            }
+            Action::Item(slot) => {
+                if !parent.borrow().actor.can_use_quick(*slot) {
+                    return false;
+                }
+
+                let func = get_on_activate_fn(false, ai_data);
+                Script::item_on_activate(parent, func, ScriptItemKind::Quick(*slot));
+            }
+            Action::Attack => unreachable!(),
+        }
+
+        let targeter = GameState::area_state().borrow().targeter();
+        let performed = match targeter {
+            None => parent.borrow().actor.ap() != ap,
+            Some(targeter) => handle_targeter(&targeter, candidate, ai_data, targets),
+        };
+
+        if !performed {
+            self.failed.insert(candidate.action.clone());
+        }
+        performed
+    }
+}
+
+/// Creates the candidate for using the ability or item with the specified
+/// AI data, if it has a usable target
+fn action_candidate(
+    parent: &Rc<RefCell<EntityState>>,
+    action: Action,
+    ai_data: AIData,
+    targets: &Targets,
+) -> Option<Candidate> {
+    let target = usable_target(parent, &ai_data, targets)?;
+
+    let mut score = ABILITY_BASE_SCORE + PRIORITY_SCORE / (ai_data.priority as f32 + 10.0);
+    score += targets.weight(&target.borrow()).abs();
+    if ai_data.kind == AIKind::Heal {
+        score += 2.0 * (1.0 - hp_frac(&target.borrow()));
+    }
+
+    let move_dist = {
+        let parent = parent.borrow();
+        let target = target.borrow();
+        let stats = &parent.actor.stats;
+        match ai_data.range {
+            AIRange::Personal => None,
+            AIRange::Touch if is_within_touch_dist(&parent, &*target) => None,
+            AIRange::Touch => Some(stats.touch_distance() - MOVE_THRESHOLD),
+            AIRange::Attack if is_within_attack_dist(&parent, &*target) => None,
+            AIRange::Attack => Some(stats.attack_distance() - MOVE_THRESHOLD),
+            AIRange::Short => range_move_dist(&parent, &target, SHORT_RANGE),
+            AIRange::Visible => range_move_dist(&parent, &target, vis_dist(&parent) - 1.0),
+        }
+    };
+
+    if move_dist.is_some() {
+        score *= MOVE_SCORE_FACTOR;
+    }
+
+    Some(Candidate {
+        action,
+        ai_data: Some(ai_data),
+        target,
+        score,
+        move_dist,
+    })
+}
+
+fn range_move_dist(parent: &EntityState, target: &EntityState, range: f32) -> Option<f32> {
+    if dist(parent, target) <= range {
+        None
+    } else {
+        Some(range - MOVE_THRESHOLD)
+    }
+}
+
+fn add_attack_candidates(
+    parent: &Rc<RefCell<EntityState>>,
+    targets: &Targets,
+    tactics: Option<&GroupTactics>,
+    candidates: &mut Vec<Candidate>,
+) {
+    let parent_ref = parent.borrow();
+    if !parent_ref.actor.has_ap_to_attack() || parent_ref.actor.stats.attack_disabled {
+        return;
+    }
+
+    let stats = &parent_ref.actor.stats;
+    let area_state = GameState::area_state();
+    for target in targets.hostiles.iter() {
+        let target_ref = target.borrow();
+        let mut score = targets.weight(&target_ref).abs();
+
+        let move_dist = if stats.attack_is_ranged() {
+            let cur_dist = dist(&*parent_ref, &*target_ref);
+            let attack_dist = stats.attack_distance() - 1.0;
+            if cur_dist > attack_dist {
+                Some(attack_dist)
+            } else if !area_state.borrow().has_visibility(&parent_ref, &target_ref) {
+                Some(cur_dist - 2.0)
+            } else {
+                None
+            }
+        } else if is_within_attack_dist(&parent_ref, &*target_ref) {
+            None
+        } else {
+            Some(stats.attack_distance())
+        };
+
+        if move_dist.is_some() {
+            score *= MOVE_SCORE_FACTOR;
+        } else if let Some(tactics) = tactics {
+            score += tactics.flanking_bonus(&parent_ref, &target_ref);
+        }
+
+        candidates.push(Candidate {
+            action: Action::Attack,
+            ai_data: None,
+            target: Rc::clone(target),
+            score,
+            move_dist,
+        });
+    }
+}
+
+/// Attempts to move to a position flanking the candidate's target, if the
+/// group tactics call for it.  Returns true if a move was started.
+fn flank(
+    parent: &Rc<RefCell<EntityState>>,
+    candidate: &Candidate,
+    tactics: Option<&GroupTactics>,
+) -> bool {
+    let dest = match tactics {
+        None => return false,
+        Some(tactics) => tactics.flanking_dest(&parent.borrow(), &candidate.target.borrow()),
+    };
+
+    let mut dest = match dest {
+        None => return false,
+        Some(dest) => dest,
+    };
+    dest.max_path_len = Some(MAX_MOVE_LEN);
+
+    if !move_towards_dest(parent, dest) {
+        return false;
+    }
+
+    ai_trace::record(TraceEvent::Tactic {
+        tactic: "flank".to_string(),
+        target: Some(candidate.target.borrow().unique_id().to_string()),
+    });
+    true
+}
+
+fn move_towards(
+    parent: &Rc<RefCell<EntityState>>,
+    candidate: &Candidate,
+    dist: f32,
+    move_tries: f32,
+) -> bool {
+    let mut dest = GameState::get_target_dest(&parent.borrow(), &candidate.target.borrow());
+    dest.max_path_len = Some(MAX_MOVE_LEN);
+
+    // melee attackers may settle for getting closer to the target, if they
+    // are unable to path all the way to it
+    let increase = {
+        let parent = parent.borrow();
+        if candidate.action == Action::Attack && parent.actor.stats.attack_is_melee() {
+            parent.size.width.max(parent.size.height) as f32
+        } else {
+            0.0
+        }
+    };
+
+    let mut attempt = 0.0;
+    loop {
+        dest.dist = dist + attempt * increase;
+        if move_towards_dest(parent, dest) {
+            return true;
+        }
+
+        attempt += 1.0;
+        if increase == 0.0 || attempt > move_tries {
+            return false;
+        }
+    }
+}
+
+/// Activates the current targeter on the best position for the AI data,
+/// or cancels it if there is no suitable position.  Returns true if the
+/// targeter was activated.
+fn handle_targeter(
+    targeter: &Rc<RefCell<AreaTargeter>>,
+    candidate: &Candidate,
+    ai_data: &AIData,
+    targets: &Targets,
+) -> bool {
+    let position = match ai_data.kind {
+        AIKind::Heal => {
+            let target = candidate.target.borrow();
+            Some((target.location.x, target.location.y))
+        }
+        AIKind::Summon => position_nearby(targeter, &candidate.target.borrow()),
+        _ => best_position(targeter, ai_data, targets),
+    };
+
+    let mut targeter = targeter.borrow_mut();
+    if let Some((x, y)) = position {
+        targeter.on_mouse_move(x, y);
+        if targeter.is_valid_to_activate() {
+            targeter.on_activate();
+            return true;
+        }
+    }
+
+    targeter.on_cancel();
+    false
+}
+
+fn best_position(
+    targeter: &Rc<RefCell<AreaTargeter>>,
+    ai_data: &AIData,
+    targets: &Targets,
+) -> Option<(i32, i32)> {
+    // want hostiles and don't want friendlies for damage or debuff, the
+    // opposite for others
+    let (modifier, wanted) = match ai_data.kind {
+        AIKind::Damage | AIKind::Debuff | AIKind::Summon => (-1.0, &targets.hostiles),
+        _ => (1.0, &targets.friendlies),
+    };
+
+    let mut to_check = Vec::new();
+    {
+        let targeter = targeter.borrow();
+        if !targeter.is_free_select() {
+            to_check.extend(targeter.selectable().iter().map(location));
+        } else if ai_data.target == AITarget::Entity {
+            to_check.extend(wanted.iter().map(location));
+        } else {
+            for (base_x, base_y) in wanted.iter().map(location) {
+                for y in (-4..=4).step_by(2) {
+                    for x in (-4..=4).step_by(2) {
+                        to_check.push((base_x + x, base_y + y));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut best_score = 0.0;
+    let mut best = None;
+    for (x, y) in to_check {
+        let mut targeter = targeter.borrow_mut();
+        targeter.on_mouse_move(x, y);
+        if !targeter.is_valid_to_activate() {
+            continue;
+        }
+
+        let score: f32 = targeter
+            .cur_affected()
+            .iter()
+            .map(|entity| targets.weight(&entity.borrow()) * modifier)
+            .sum();
+
+        if score > best_score {
+            best_score = score;
+            best = Some((x, y));
+        }
+    }
+
+    if ai_data.group == AIGroup::Multiple && best_score < MIN_MULTIPLE_SCORE {
+        debug!("  Minimum score for multi-target ability not reached");
+        return None;
+    }
+
+    best
+}
+
+fn position_nearby(
+    targeter: &Rc<RefCell<AreaTargeter>>,
+    target: &EntityState,
+) -> Option<(i32, i32)> {
+    let base_x = target.location.x - 5;
+    let base_y = target.location.y - 5;
+
+    let mut targeter = targeter.borrow_mut();
+    for y in (1..=9).step_by(2) {
+        for x in (1..=9).step_by(2) {
+            targeter.on_mouse_move(base_x + x, base_y + y);
+            if targeter.is_valid_to_activate() {
+                return Some((base_x + x, base_y + y));
+            }
+        }
+    }
+
+    None
+}
+
+fn usable_target(
+    parent: &Rc<RefCell<EntityState>>,
+    ai_data: &AIData,
+    targets: &Targets,
+) -> Option<Rc<RefCell<EntityState>>> {
+    match (ai_data.range, ai_data.kind) {
+        (AIRange::Personal, AIKind::Heal) => {
+            if hp_frac(&parent.borrow()) < HEALING_FRAC {
+                Some(Rc::clone(parent))
+            } else {
+                None
+            }
+        }
+        (AIRange::Personal, _) => Some(Rc::clone(parent)),
+        (_, AIKind::Damage) | (_, AIKind::Debuff) | (_, AIKind::Summon) => {
+            closest(parent, &targets.hostiles)
+        }
+        (_, AIKind::Heal) => targets
+            .friendlies
+            .iter()
+            .map(|target| (hp_frac(&target.borrow()), target))
+            .filter(|(frac, _)| *frac < HEALING_FRAC)
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+            .map(|(_, target)| Rc::clone(target)),
+        _ => closest(parent, &targets.friendlies),
+    }
+}
+
+fn closest(
+    parent: &Rc<RefCell<EntityState>>,
+    targets: &[Rc<RefCell<EntityState>>],
+) -> Option<Rc<RefCell<EntityState>>> {
+    let parent = parent.borrow();
+    targets
+        .iter()
+        .map(|target| (dist(&*parent, &*target.borrow()), target))
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal))
+        .map(|(_, target)| Rc::clone(target))
+}
+
+fn compute_weight(parent: &EntityState, target: &EntityState) -> f32 {
+    let hostile = parent.is_hostile(target);
+    let stats = &target.actor.stats;
+
+    let mut modifiers = 0.0;
+
+    // threatening hostiles are higher priority
+    if parent.actor.p_stats().is_threatened_by(target.index()) {
+        modifiers += 0.35;
+    }
+
+    // closer targets are higher priority
+    modifiers += (20.0 - dist(parent, target)) / 60.0;
+
+    // weaker hostiles and stronger friendlies are higher priority
+    if hostile {
+        let armor = stats.armor.base() as f32 / 100.0;
+        let hp = target.actor.hp() as f32 / 400.0;
+        modifiers -= armor + hp;
+    } else if stats.caster_level > 0 {
+        modifiers += stats.spell_accuracy as f32 / 100.0;
+    } else {
+        if let Some(attack) = stats.attacks.first() {
+            let damage = (attack.damage.min() + attack.damage.max()) as f32 / 2.0;
+            modifiers += damage / 100.0;
+        }
+
+        if stats.attack_is_melee() {
+            modifiers += stats.melee_accuracy as f32 / 200.0;
+        } else {
+            modifiers += stats.ranged_accuracy as f32 / 200.0;
+        }
+    }
+
+    // hostiles that have hurt us are higher priority, while hostiles that
+    // are difficult to damage with our regular attack are lower priority.
+    // these flags are set by the ai_basic script hooks
+    let id = target.unique_id();
+    modifiers += parent.get_num_flag(&format!("__damage_taken_from_{id}"));
+    modifiers += parent.get_num_flag(&format!("__hard_target_for_{id}"));
+
+    let base = if hostile { -1.0 } else { 1.0 };
+    base * (1.0 + modifiers)
+}
+
+fn hp_frac(entity: &EntityState) -> f32 {
+    let max_hp = entity.actor.stats.max_hp.max(1);
+    entity.actor.hp() as f32 / max_hp as f32
+}
+
+fn vis_dist(entity: &EntityState) -> f32 {
+    match GameState::get_area_state(&entity.location.area_id) {
+        None => 0.0,
+        Some(area) => area.borrow().area.area.vis_dist as f32,
+    }
+}
+
+fn location(entity: &Rc<RefCell<EntityState>>) -> (i32, i32) {
+    let entity = entity.borrow();
+    (entity.location.x, entity.location.y)
+}
+
+fn has_effect_with_tag(entity: &Rc<RefCell<EntityState>>, tag: &str) -> bool {
+    let mgr = GameState::turn_manager();
+    let mgr = mgr.borrow();
+    let entity = entity.borrow();
+    let result = entity
+        .actor
+        .effects_iter()
+        .any(|index| mgr.effect(*index).tag == tag);
+    result
+}
+
+fn run_away(parent: &Rc<RefCell<EntityState>>, hostiles: &[Rc<RefCell<EntityState>>]) {
+    if hostiles.is_empty() {
+        return;
+    }
+
+    let (x, y) = location(parent);
+
+    // move in the direction opposite the mean angle to the hostiles
+    let (mut total_x, mut total_y) = (0.0, 0.0);
+    for (target_x, target_y) in hostiles.iter().map(location) {
+        let angle = ((y - target_y) as f32).atan2((x - target_x) as f32);
+        total_x += angle.cos();
+        total_y += angle.sin();
+    }
+    let angle = total_y.atan2(total_x);
+    let dest_x = x as f32 + (angle.cos() * RUN_AWAY_DIST).round();
+    let dest_y = y as f32 + (angle.sin() * RUN_AWAY_DIST).round();
+
+    // if unable to move directly away, try less and less accurately
+    for thresh in 1..=10 {
+        let mut dest = GameState::get_point_dest(&parent.borrow(), dest_x, dest_y);
+        dest.dist = thresh as f32;
+        if move_towards_dest(parent, dest) {
+            return;
+        }
+    }
+}
+
+fn swap_weapons_to_melee(parent: &Rc<RefCell<EntityState>>) -> bool {
+    {
+        let parent = parent.borrow();
+        let inventory = parent.actor.inventory();
+        if inventory.weapon_style() != WeaponStyle::Ranged || !has_alt_weapons(&parent) {
+            return false;
+        }
+
+        if inventory.alt_weapon_style() == WeaponStyle::Ranged {
+            return false;
+        }
+
+        if !parent.actor.p_stats().is_threatened() {
+            return false;
+        }
+    }
+
+    swap_weapons(parent)
+}
+
+fn swap_weapons_to_ranged(parent: &Rc<RefCell<EntityState>>) -> bool {
+    {
+        let parent = parent.borrow();
+        if parent.actor.stats.attack_is_ranged() || !has_alt_weapons(&parent) {
+            return false;
+        }
+
+        if parent.actor.inventory().alt_weapon_style() != WeaponStyle::Ranged {
+            return false;
+        }
+    }
+
+    swap_weapons(parent)
+}
+
+fn has_alt_weapons(parent: &EntityState) -> bool {
+    let inventory = parent.actor.inventory();
+    inventory.quick(QuickSlot::AltHeldMain).is_some()
+        || inventory.quick(QuickSlot::AltHeldOff).is_some()
+}
+
+fn swap_weapons(parent: &Rc<RefCell<EntityState>>) -> bool {
+    if !parent.borrow().actor.can_swap_weapons() {
+        return false;
+    }
+
+    EntityState::swap_weapon_set(parent);
+    true
+}
@@ -0,0 +1,294 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! A record of the decisions made by the AI during recent turns, so that it
+//! is possible to see why an entity chose the action it did.  A trace is
+//! started when an AI controlled entity begins its turn and closed when the
+//! turn ends.  Events are recorded both by the native AI and, through the
+//! `game:ai_trace_*` script methods, by scripted AI.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use serde::Serialize;
+
+use sulis_core::config;
+use sulis_core::resource::write_json_to_file;
+use sulis_module::ai::AIBackend;
+
+use crate::{EntityState, GameState};
+
+/// The maximum number of turns kept; older turns are discarded
+const MAX_TURNS: usize = 50;
+
+thread_local! {
+    static TRACE: RefCell<Trace> = const { RefCell::new(Trace {
+        turns: VecDeque::new(),
+        open: false,
+        next_id: 0,
+    }) };
+}
+
+struct Trace {
+    turns: VecDeque<TurnTrace>,
+    open: bool,
+    next_id: usize,
+}
+
+/// All events recorded during one turn of one entity
+#[derive(Serialize, Debug, Clone)]
+pub struct TurnTrace {
+    pub id: usize,
+    pub round: u32,
+    pub entity_id: String,
+    pub entity_name: String,
+    pub template: Option<String>,
+    pub backend: Option<String>,
+    pub events: Vec<TraceEvent>,
+}
+
+/// The result of attempting an action
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Performed,
+    Moved,
+    Failed,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Outcome::Performed => "performed",
+            Outcome::Moved => "moved towards target",
+            Outcome::Failed => "failed",
+        };
+        write!(f, "{text}")
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind")]
+pub enum TraceEvent {
+    /// The AI began deciding on its next action
+    Run { action: u32, ap: u32 },
+
+    /// A script function was called to decide the action
+    Script { func: String },
+
+    /// The weight computed for a potential target.  The sign is the
+    /// relationship to the target: hostile targets have a weight below zero
+    /// and friendly targets a weight above zero.  More important targets
+    /// have a larger magnitude, so the most important hostile has the most
+    /// negative weight.
+    Weight { target: String, weight: f32 },
+
+    /// An action that was considered, with the score it was given
+    Candidate {
+        action: String,
+        target: Option<String>,
+        score: f32,
+        move_dist: Option<f32>,
+    },
+
+    /// An action that was attempted
+    Attempt {
+        action: String,
+        target: Option<String>,
+        outcome: Outcome,
+    },
+
+    /// A decision made due to the tactics of the entity's encounter
+    Tactic {
+        tactic: String,
+        target: Option<String>,
+    },
+
+    /// An entity's morale changed enough for it to flee, surrender, or rally
+    Morale {
+        entity: String,
+        state: String,
+        morale: i32,
+    },
+
+    /// The state the AI moved to after deciding on an action
+    State { state: String },
+
+    /// The AI waited before running again
+    Wait { time: u32, total: u32 },
+
+    /// A message logged by the AI script
+    Log { message: String },
+
+    /// The AI was stopped early
+    Warning { message: String },
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TraceEvent::*;
+        match self {
+            Run { action, ap } => write!(f, "Run action {action} with {ap} AP"),
+            Script { func } => write!(f, "Call script function '{func}'"),
+            Weight { target, weight } => write!(f, "  Weight {weight:.2} for {target}"),
+            Candidate {
+                action,
+                target,
+                score,
+                move_dist,
+            } => {
+                write!(f, "  Candidate {action}")?;
+                if let Some(target) = target {
+                    write!(f, " on {target}")?;
+                }
+                write!(f, " with score {score:.2}")?;
+                if let Some(dist) = move_dist {
+                    write!(f, ", move {dist:.1}")?;
+                }
+                Ok(())
+            }
+            Attempt {
+                action,
+                target,
+                outcome,
+            } => {
+                write!(f, "  Attempt {action}")?;
+                if let Some(target) = target {
+                    write!(f, " on {target}")?;
+                }
+                write!(f, ": {outcome}")
+            }
+            Tactic { tactic, target } => {
+                write!(f, "  Tactic: {tactic}")?;
+                if let Some(target) = target {
+                    write!(f, " on {target}")?;
+                }
+                Ok(())
+            }
+            Morale {
+                entity,
+                state,
+                morale,
+            } => write!(f, "  Morale: {entity} is now {state} with morale {morale}"),
+            State { state } => write!(f, "State {state}"),
+            Wait { time, total } => write!(f, "Wait {time} ({total} total)"),
+            Log { message } => write!(f, "  Log: {}", message.trim()),
+            Warning { message } => write!(f, "Warning: {message}"),
+        }
+    }
+}
+
+/// Starts a new turn trace for the specified entity, closing any turn
+/// currently open
+pub fn begin_turn(entity: &EntityState) {
+    let actor = &entity.actor.actor;
+    let (template, backend) = match &actor.ai {
+        None => (None, None),
+        Some(ai) => {
+            let backend = match ai.backend {
+                AIBackend::Script => "Script",
+                AIBackend::Native => "Native",
+            };
+            (Some(ai.id.to_string()), Some(backend.to_string()))
+        }
+    };
+
+    let round = GameState::turn_manager().borrow().current_round();
+
+    TRACE.with(|trace| {
+        let mut trace = trace.borrow_mut();
+        let id = trace.next_id;
+        trace.next_id += 1;
+
+        if trace.turns.len() == MAX_TURNS {
+            trace.turns.pop_front();
+        }
+
+        trace.turns.push_back(TurnTrace {
+            id,
+            round,
+            entity_id: entity.unique_id().to_string(),
+            entity_name: actor.name.to_string(),
+            template,
+            backend,
+            events: Vec::new(),
+        });
+        trace.open = true;
+    });
+}
+
+/// Closes the currently open turn, if any.  Events recorded after this are
+/// discarded until the next turn begins.
+pub fn end_turn() {
+    TRACE.with(|trace| trace.borrow_mut().open = false);
+}
+
+/// Adds the event to the currently open turn.  Does nothing if there is no
+/// open turn.
+pub fn record(event: TraceEvent) {
+    TRACE.with(|trace| {
+        let mut trace = trace.borrow_mut();
+        if !trace.open {
+            return;
+        }
+
+        if let Some(turn) = trace.turns.back_mut() {
+            turn.events.push(event);
+        }
+    });
+}
+
+/// Returns true if there is currently an open turn, in which case events
+/// passed to `record` will be kept
+pub fn is_recording() -> bool {
+    TRACE.with(|trace| trace.borrow().open)
+}
+
+/// Returns a copy of all recorded turns, oldest first
+pub fn turns() -> Vec<TurnTrace> {
+    TRACE.with(|trace| trace.borrow().turns.iter().cloned().collect())
+}
+
+/// Discards all recorded turns
+pub fn clear() {
+    TRACE.with(|trace| {
+        let mut trace = trace.borrow_mut();
+        trace.turns.clear();
+        trace.open = false;
+    });
+}
+
+/// Writes all recorded turns as JSON to the specified file
+pub fn write(path: &Path) -> Result<(), Error> {
+    write_json_to_file(path, &turns())
+}
+
+/// Writes all recorded turns to a new, timestamped file in the user's
+/// `ai_trace` directory, returning the path written to
+pub fn write_to_trace_dir() -> Result<PathBuf, Error> {
+    let mut path = config::USER_DIR.clone();
+    path.push("ai_trace");
+    fs::create_dir_all(&path)?;
+
+    let utc = Utc::now();
+    path.push(format!("ai_trace_{}.json", utc.format("%Y%m%d-%H%M%S%.3f")));
+    write(&path)?;
+    Ok(path)
+}
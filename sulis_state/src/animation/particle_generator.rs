@@ -26,7 +26,7 @@ use sulis_core::config::Config;
 use sulis_core::image::Image;
 use sulis_core::io::{DrawList, GraphicsRenderer};
 use sulis_core::ui::{animation_state, Color};
-use sulis_core::util::{approx_eq, gen_rand, ExtInt, Offset, Rect, Scale};
+use sulis_core::util::{approx_eq, gen_visual_rand, ExtInt, Offset, Rect, Scale};
 
 fn is_zero(val: &f32) -> bool {
     *val == 0.0
@@ -136,13 +136,15 @@ impl Dist {
     fn generate_pair(&self) -> (f32, f32) {
         match self {
             Dist::Fixed { value } => (*value, *value),
-            Dist::Uniform { min, max } => (gen_rand(*min, *max), gen_rand(*min, *max)),
+            Dist::Uniform { min, max } => {
+                (gen_visual_rand(*min, *max), gen_visual_rand(*min, *max))
+            }
             Dist::FixedAngleUniformSpeed {
                 angle,
                 min_speed,
                 max_speed,
             } => {
-                let speed = gen_rand(*min_speed, *max_speed);
+                let speed = gen_visual_rand(*min_speed, *max_speed);
                 radial_to_cart(*angle, speed)
             }
             Dist::UniformAngleFixedSpeed {
@@ -150,7 +152,7 @@ impl Dist {
                 max_angle,
                 speed,
             } => {
-                let angle = gen_rand(*min_angle, *max_angle);
+                let angle = gen_visual_rand(*min_angle, *max_angle);
                 radial_to_cart(angle, *speed)
             }
             Dist::UniformAngleUniformSpeed {
@@ -159,8 +161,8 @@ impl Dist {
                 min_speed,
                 max_speed,
             } => {
-                let speed = gen_rand(*min_speed, *max_speed);
-                let angle = gen_rand(*min_angle, *max_angle);
+                let speed = gen_visual_rand(*min_speed, *max_speed);
+                let angle = gen_visual_rand(*min_angle, *max_angle);
                 radial_to_cart(angle, speed)
             }
         }
@@ -169,7 +171,7 @@ impl Dist {
     fn generate(&self) -> f32 {
         match self {
             Dist::Fixed { value } => *value,
-            Dist::Uniform { min, max } => gen_rand(*min, *max),
+            Dist::Uniform { min, max } => gen_visual_rand(*min, *max),
             _ => {
                 warn!("2D dists should only be used as the sole dist in a position component");
                 0.0
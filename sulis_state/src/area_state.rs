@@ -30,7 +30,9 @@ use sulis_core::config::Config;
 use sulis_core::io::Audio;
 use sulis_core::util::{self, gen_rand, invalid_data_error, Point, Size};
 use sulis_module::area::{Transition, Trigger, TriggerKind};
-use sulis_module::{Actor, Area, LootList, Module, ObjectSize, Time};
+use sulis_module::{
+    Actor, Area, CoverKind, ElevationKind, LootList, Module, ObjectSize, ReactionKind, Time,
+};
 
 pub struct TriggerState {
     pub(crate) fired: bool,
@@ -657,6 +659,38 @@ impl AreaState {
         has_visibility(&self.area, self.props.entire_vis_grid(), parent, target)
     }
 
+    /// Returns the cover the target has against ranged and spell attacks
+    /// from the parent
+    pub fn cover(&self, parent: &EntityState, target: &EntityState) -> CoverKind {
+        compute_cover(
+            &self.area,
+            self.props.entire_vis_grid(),
+            self.props.entire_pass_grid(),
+            &self.entity_grid,
+            parent,
+            target,
+        )
+    }
+
+    /// Returns whether the parent is on higher, lower, or level ground with
+    /// the target, using the elevation at the center of each
+    pub fn elevation_kind(&self, parent: &EntityState, target: &impl Locatable) -> ElevationKind {
+        let layer_set = &self.area.layer_set;
+        let elevation = |x: i32, y: i32| {
+            if self.area.area.coords_valid(x, y) {
+                layer_set.elevation(x, y)
+            } else {
+                0
+            }
+        };
+
+        let (px, py) = center_i32(parent);
+        let (tx, ty) = center_i32(target);
+        Module::rules()
+            .elevation
+            .kind(elevation(px, py), elevation(tx, ty))
+    }
+
     pub fn compute_pc_visibility(
         &mut self,
         entity: &Rc<RefCell<EntityState>>,
@@ -916,6 +950,12 @@ impl AreaState {
         }
     }
 
+    /// Recomputes which entities threaten and are threatened by the
+    /// specified entity, such as after it changes faction
+    pub(crate) fn update_threatened(&self, entity: &Rc<RefCell<EntityState>>, mgr: &TurnManager) {
+        self.compute_threatened(entity, mgr, false);
+    }
+
     fn check_threatened(&self, att: &mut EntityState, def: &mut EntityState, removal: bool) {
         if removal || !is_threat(att, def) {
             att.actor.remove_threatening(def.index());
@@ -988,9 +1028,27 @@ impl AreaState {
         }
 
         let mgr = GameState::turn_manager();
+        let threatened_by = entity.borrow().actor.p_stats().threatened_by().to_vec();
 
         self.update_entity_position(entity, old_x, old_y, &mut mgr.borrow_mut());
 
+        // moving out of a threatening entity's reach, rather than being
+        // pushed or teleported, provokes an opportunity attack
+        if squares > 0 {
+            let entity = entity.borrow();
+            for threatener in threatened_by {
+                if entity.actor.p_stats().is_threatened_by(threatener) {
+                    continue;
+                }
+
+                mgr.borrow_mut().queue_reaction(
+                    ReactionKind::OpportunityAttack,
+                    threatener,
+                    entity.index(),
+                );
+            }
+        }
+
         true
     }
 
@@ -0,0 +1,276 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Simulates many fights between two sides of actors in order to help with
+//! balancing classes and encounters.  Attacks are resolved with the same
+//! rolls used in game, but the fights are otherwise simplified: there is no
+//! area, so there is no movement, flanking, or hiding, and combatants only
+//! use their weapon attacks, each time against a random living hostile.
+
+use std::cmp::Reverse;
+use std::fmt;
+use std::rc::Rc;
+
+use serde::Serialize;
+
+use sulis_core::util::gen_rand;
+use sulis_module::{Actor, CoverKind, ElevationKind, HitKind, Module};
+
+use crate::entity_attack_handler::roll_attack;
+use crate::ActorState;
+
+/// The number of each kind of hit rolled by one side
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct HitKindCounts {
+    pub concealed: u32,
+    pub miss: u32,
+    pub graze: u32,
+    pub hit: u32,
+    pub crit: u32,
+}
+
+impl HitKindCounts {
+    pub fn total(&self) -> u32 {
+        self.concealed + self.miss + self.graze + self.hit + self.crit
+    }
+}
+
+/// The results of all fights for one side
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SideReport {
+    pub actors: Vec<String>,
+    pub wins: u32,
+    pub win_rate: f32,
+    pub attacks: u32,
+
+    /// The damage dealt, not counting damage past a target's remaining hit
+    /// points
+    pub damage: u64,
+
+    /// The AP spent on attacks, in display units
+    pub ap_spent: f32,
+    pub damage_per_ap: f32,
+    pub hit_kinds: HitKindCounts,
+}
+
+/// The results of a simulation
+#[derive(Serialize, Debug, Clone)]
+pub struct CombatSimReport {
+    pub fights: u32,
+    pub max_rounds: u32,
+
+    /// Fights in which neither side won within `max_rounds`
+    pub draws: u32,
+    pub average_rounds: f32,
+    pub sides: Vec<SideReport>,
+}
+
+impl fmt::Display for CombatSimReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} fights, {} draws, {:.2} rounds on average",
+            self.fights, self.draws, self.average_rounds
+        )?;
+
+        for (index, side) in self.sides.iter().enumerate() {
+            let hits = &side.hit_kinds;
+            let total = hits.total().max(1) as f32;
+            let percent = |count: u32| 100.0 * count as f32 / total;
+
+            writeln!(f, "Side {}: {}", index + 1, side.actors.join(", "))?;
+            writeln!(f, "  Wins: {} ({:.1}%)", side.wins, 100.0 * side.win_rate)?;
+            writeln!(
+                f,
+                "  Attacks: {}, damage: {}, AP: {:.0}, damage per AP: {:.2}",
+                side.attacks, side.damage, side.ap_spent, side.damage_per_ap
+            )?;
+            writeln!(
+                f,
+                "  Hit kinds: {:.1}% concealed, {:.1}% miss, {:.1}% graze, {:.1}% hit, {:.1}% crit",
+                percent(hits.concealed),
+                percent(hits.miss),
+                percent(hits.graze),
+                percent(hits.hit),
+                percent(hits.crit),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Combatant {
+    side: usize,
+    actor: ActorState,
+    initiative: i32,
+}
+
+/// Runs the specified number of fights between the two sides.  Each fight
+/// ends when all of one side is dead, or after `max_rounds` as a draw.
+pub fn run(sides: [&[Rc<Actor>]; 2], fights: u32, max_rounds: u32) -> CombatSimReport {
+    let mut reports: Vec<SideReport> = sides
+        .iter()
+        .map(|actors| SideReport {
+            actors: actors.iter().map(|actor| actor.id.to_string()).collect(),
+            ..Default::default()
+        })
+        .collect();
+
+    let mut draws = 0;
+    let mut total_rounds = 0;
+    for _ in 0..fights {
+        let (winner, rounds) = fight(sides, max_rounds, &mut reports);
+        total_rounds += rounds;
+        match winner {
+            None => draws += 1,
+            Some(side) => reports[side].wins += 1,
+        }
+    }
+
+    let display_ap = Module::rules().display_ap as f32;
+    for report in reports.iter_mut() {
+        report.win_rate = report.wins as f32 / fights.max(1) as f32;
+        report.ap_spent /= display_ap;
+        if report.ap_spent > 0.0 {
+            report.damage_per_ap = report.damage as f32 / report.ap_spent;
+        }
+    }
+
+    CombatSimReport {
+        fights,
+        max_rounds,
+        draws,
+        average_rounds: total_rounds as f32 / fights.max(1) as f32,
+        sides: reports,
+    }
+}
+
+/// Runs a single fight, returning the winning side, if any, and the number
+/// of rounds taken
+fn fight(
+    sides: [&[Rc<Actor>]; 2],
+    max_rounds: u32,
+    reports: &mut [SideReport],
+) -> (Option<usize>, u32) {
+    let initiative_roll_max = Module::rules().initiative_roll_max;
+
+    let mut combatants = Vec::new();
+    for (side, actors) in sides.iter().enumerate() {
+        for actor in actors.iter() {
+            let mut actor = ActorState::new(Rc::clone(actor));
+            actor.compute_stats();
+            actor.init_day();
+            let initiative = actor.stats.initiative + gen_rand(0, initiative_roll_max);
+            combatants.push(Combatant {
+                side,
+                actor,
+                initiative,
+            });
+        }
+    }
+    combatants.sort_by_key(|combatant| Reverse(combatant.initiative));
+
+    for round in 1..=max_rounds {
+        for index in 0..combatants.len() {
+            if combatants[index].actor.is_dead() {
+                continue;
+            }
+
+            combatants[index].actor.init_turn();
+            take_turn(&mut combatants, index, reports);
+            combatants[index].actor.end_turn();
+
+            if let Some(winner) = winner(&combatants) {
+                return (Some(winner), round);
+            }
+        }
+    }
+
+    (None, max_rounds)
+}
+
+/// Returns the side that won, if only one side has living combatants
+fn winner(combatants: &[Combatant]) -> Option<usize> {
+    let mut living = combatants
+        .iter()
+        .filter(|combatant| !combatant.actor.is_dead())
+        .map(|combatant| combatant.side);
+
+    let side = living.next()?;
+    if living.all(|other| other == side) {
+        Some(side)
+    } else {
+        None
+    }
+}
+
+/// The combatant at `index` attacks until it runs out of AP
+fn take_turn(combatants: &mut [Combatant], index: usize, reports: &mut [SideReport]) {
+    let side = combatants[index].side;
+
+    while combatants[index].actor.has_ap_to_attack() {
+        let targets: Vec<usize> = combatants
+            .iter()
+            .enumerate()
+            .filter(|(_, other)| other.side != side && !other.actor.is_dead())
+            .map(|(index, _)| index)
+            .collect();
+
+        if targets.is_empty() {
+            return;
+        }
+        let target = targets[gen_rand(0, targets.len())];
+
+        let report = &mut reports[side];
+        for mut attack in combatants[index].actor.stats.attacks.clone() {
+            // any remaining attacks are wasted once the target has fallen
+            if combatants[target].actor.is_dead() {
+                break;
+            }
+
+            let (hit_kind, hit_flags, damage) = roll_attack(
+                &combatants[index].actor.stats,
+                &combatants[target].actor.stats,
+                &mut attack,
+                false,
+                false,
+                CoverKind::None,
+                ElevationKind::Level,
+            );
+
+            report.attacks += 1;
+            match hit_kind {
+                _ if hit_flags.concealment => report.hit_kinds.concealed += 1,
+                HitKind::Miss => report.hit_kinds.miss += 1,
+                HitKind::Graze => report.hit_kinds.graze += 1,
+                HitKind::Hit | HitKind::Auto => report.hit_kinds.hit += 1,
+                HitKind::Crit => report.hit_kinds.crit += 1,
+            }
+
+            // only count the damage needed to bring the target down
+            let amount: u32 = damage.iter().map(|(_, amount)| amount).sum();
+            let remaining = combatants[target].actor.hp().max(0) as u32;
+            report.damage += amount.min(remaining) as u64;
+            combatants[target].actor.remove_hp(amount);
+        }
+
+        // an attack always costs some AP so that the turn ends
+        let cost = combatants[index].actor.stats.attack_cost.max(1) as u32;
+        report.ap_spent += cost as f32;
+        combatants[index].actor.remove_ap(cost);
+    }
+}
@@ -0,0 +1,323 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Conditions, such as poison or bleeding, are defined in the module's
+//! `conditions` directory and applied to entities as effects.  The turn
+//! manager queues a tick each time a round elapses for a condition effect,
+//! and the ticks are fired on the next update, dealing the condition's
+//! damage to the affected entity.  Each tick holds the condition's state, so
+//! the final tick still fires after its effect has been removed.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use sulis_core::util::ExtInt;
+
+use crate::area_feedback_text::ColorKind;
+use crate::script::{ScriptAppliedEffect, ScriptCallback};
+use crate::{effect, AreaFeedbackText, Effect, EffectOutcome, EntityState, GameState, TurnManager};
+use sulis_module::{
+    AccuracyKind, AttackBonuses, Condition, ConditionStacking, DamageList, HitFlags, HitKind,
+    Module, SaveDefense, ROUND_TIME_MILLIS,
+};
+
+/// The condition applied by an effect
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionState {
+    pub(crate) id: String,
+    pub(crate) stacks: u32,
+    pub(crate) applier: Option<usize>,
+}
+
+impl ConditionState {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn stacks(&self) -> u32 {
+        self.stacks
+    }
+}
+
+/// A condition tick waiting to be fired on the next update
+pub(crate) struct PendingTick {
+    pub target: usize,
+    pub state: ConditionState,
+}
+
+/// Applies the condition to the target, unless it is resisted with a save.
+/// If the target already has the condition, it is stacked according to the
+/// condition's stacking rule instead.  Returns true if the condition was
+/// applied or stacked.
+pub fn apply(
+    condition: &Rc<Condition>,
+    target: &Rc<RefCell<EntityState>>,
+    applier: &Rc<RefCell<EntityState>>,
+) -> bool {
+    if target.borrow().actor.is_dead() {
+        return false;
+    }
+
+    let mgr = GameState::turn_manager();
+    let existing = find(&mgr.borrow(), &target.borrow(), &condition.id);
+
+    if existing.is_some() && condition.stacking == ConditionStacking::Ignore {
+        return false;
+    }
+
+    let immunity = std::iter::once(&condition.id)
+        .chain(condition.tags.iter())
+        .find(|category| target.borrow().actor.actor.is_immune(category));
+    if let Some(category) = immunity {
+        effect::fire_resisted(target, &condition.name, category);
+        return false;
+    }
+
+    if !save(condition, &target.borrow(), &applier.borrow()) {
+        info!(
+            "'{}' resisted condition '{}'",
+            target.borrow().unique_id(),
+            condition.id
+        );
+        add_feedback(target, "Resisted");
+        return false;
+    }
+
+    if let Some(index) = existing {
+        stack(condition, mgr.borrow_mut().effect_mut(index));
+        return true;
+    }
+
+    let duration = condition.duration.saturating_mul(ROUND_TIME_MILLIS);
+    let mut effect = Effect::new(
+        &condition.name,
+        &condition.id,
+        ExtInt::Int(duration),
+        condition.bonuses.clone(),
+        None,
+    );
+    if let Some(icon) = &condition.icon {
+        effect.set_icon(icon.to_string(), condition.name.to_string());
+    }
+    effect.set_owning_entity(target.borrow().index());
+    effect.set_source(applier.borrow().index());
+    for tag in condition.tags.iter() {
+        effect.add_category(tag.to_string());
+    }
+    effect.condition = Some(ConditionState {
+        id: condition.id.to_string(),
+        stacks: 1,
+        applier: Some(applier.borrow().index()),
+    });
+
+    info!(
+        "Apply condition '{}' to '{}'",
+        condition.id,
+        target.borrow().unique_id()
+    );
+
+    // get the list of cbs before applying so it doesn't include itself
+    let on_applied_cbs = target.borrow().callbacks(&mgr.borrow());
+    let outcome = mgr
+        .borrow_mut()
+        .add_effect(effect, target, Vec::new(), Vec::new());
+    let index = match outcome {
+        EffectOutcome::Added(index) => index,
+        _ => return false,
+    };
+
+    let sae = ScriptAppliedEffect::new(mgr.borrow().effect(index), index);
+    on_applied_cbs
+        .iter()
+        .for_each(|cb| cb.on_effect_applied(sae.clone()));
+    true
+}
+
+/// Removes all conditions with the specified tag from the target on the
+/// next update.  Returns the number of conditions removed.
+pub fn cleanse(target: &EntityState, tag: &str) -> u32 {
+    let mgr = GameState::turn_manager();
+    let mut mgr = mgr.borrow_mut();
+
+    let mut count = 0;
+    for index in target.actor.effects_iter() {
+        let effect = mgr.effect_mut(*index);
+        let id = match &effect.condition {
+            None => continue,
+            Some(state) => &state.id,
+        };
+
+        match Module::condition(id) {
+            Some(condition) if condition.has_tag(tag) => (),
+            _ => continue,
+        }
+
+        effect.mark_for_removal();
+        count += 1;
+    }
+
+    count
+}
+
+fn find(mgr: &TurnManager, target: &EntityState, id: &str) -> Option<usize> {
+    target
+        .actor
+        .effects_iter()
+        .copied()
+        .find(|index| match &mgr.effect(*index).condition {
+            None => false,
+            Some(state) => state.id == id,
+        })
+}
+
+fn save(condition: &Condition, target: &EntityState, applier: &EntityState) -> bool {
+    let save = match &condition.save {
+        None => return true,
+        Some(save) => save,
+    };
+
+    let target = &target.actor.stats;
+    let defense = match save.defense {
+        SaveDefense::Fortitude => target.fortitude,
+        SaveDefense::Reflex => target.reflex,
+        SaveDefense::Will => target.will,
+    };
+
+    let bonuses = AttackBonuses {
+        spell_accuracy: save.accuracy,
+        ..Default::default()
+    };
+
+    let hit_kind = applier
+        .actor
+        .stats
+        .attack_roll(AccuracyKind::Spell, true, defense, &bonuses);
+    hit_kind != HitKind::Miss
+}
+
+fn stack(condition: &Condition, effect: &mut Effect) {
+    let duration = condition.duration.saturating_mul(ROUND_TIME_MILLIS);
+    match condition.stacking {
+        ConditionStacking::Refresh => {
+            effect.cur_duration = 0;
+            effect.total_duration = ExtInt::Int(duration);
+        }
+        ConditionStacking::Extend => {
+            effect.total_duration = effect.total_duration + duration;
+        }
+        ConditionStacking::Intensify => {
+            if let Some(state) = effect.condition.as_mut() {
+                state.stacks = condition.max_stacks.max(1).min(state.stacks + 1);
+            }
+            effect.cur_duration = 0;
+            effect.total_duration = ExtInt::Int(duration);
+        }
+        ConditionStacking::Ignore => (),
+    }
+}
+
+/// Fires all condition ticks queued since the last update
+pub(crate) fn fire_pending() {
+    let mgr = GameState::turn_manager();
+    let pending = mgr.borrow_mut().drain_condition_ticks();
+
+    for pending in pending {
+        let (target, applier) = {
+            let mgr = mgr.borrow();
+            let target = match mgr.entity_checked(pending.target) {
+                None => continue,
+                Some(target) => target,
+            };
+
+            let applier = pending
+                .state
+                .applier
+                .and_then(|index| mgr.entity_checked(index));
+            (target, applier)
+        };
+
+        tick(
+            &target,
+            &applier.unwrap_or_else(|| Rc::clone(&target)),
+            &pending.state,
+        );
+    }
+}
+
+fn tick(
+    target: &Rc<RefCell<EntityState>>,
+    applier: &Rc<RefCell<EntityState>>,
+    state: &ConditionState,
+) {
+    let condition = match Module::condition(&state.id) {
+        None => {
+            warn!("Invalid condition '{}' on effect", state.id);
+            return;
+        }
+        Some(condition) => condition,
+    };
+
+    let damage = match condition.damage {
+        None => return,
+        Some(damage) => DamageList::from(damage),
+    };
+
+    let damage = {
+        let target = target.borrow();
+        if target.actor.is_dead() {
+            return;
+        }
+
+        let stats = &target.actor.stats;
+        Module::rules().roll_damage(
+            &damage,
+            &stats.armor,
+            &stats.resistance,
+            state.stacks as f32,
+        )
+    };
+
+    info!(
+        "'{}' takes {:?} from condition '{}'",
+        target.borrow().unique_id(),
+        damage,
+        state.id
+    );
+
+    if !damage.is_empty() {
+        EntityState::remove_hp(target, applier, HitKind::Hit, damage.clone());
+    }
+
+    let area_state = GameState::area_state();
+    let feedback = AreaFeedbackText::with_damage(
+        &target.borrow(),
+        &area_state.borrow(),
+        HitKind::Auto,
+        HitFlags::default(),
+        &damage,
+    );
+    area_state.borrow_mut().add_feedback_text(feedback);
+}
+
+fn add_feedback(target: &Rc<RefCell<EntityState>>, text: &str) {
+    let area_state = GameState::area_state();
+    let mut feedback = AreaFeedbackText::with_target(&target.borrow(), &area_state.borrow());
+    feedback.add_entry(text.to_string(), ColorKind::Info);
+    area_state.borrow_mut().add_feedback_text(feedback);
+}
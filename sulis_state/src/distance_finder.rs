@@ -14,9 +14,10 @@
 //  You should have received a copy of the GNU General Public License
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
-use crate::{EntityState, PropState};
+use crate::{EntityState, GameState, PropState};
 use sulis_core::util::Point;
 use sulis_module::area::Transition;
+use sulis_module::Module;
 
 pub trait Locatable {
     fn size(&self) -> (f32, f32);
@@ -111,10 +112,26 @@ pub fn is_within(parent: &impl Locatable, target: &impl Locatable, max_dist: f32
 }
 
 pub fn is_within_attack_dist<T: Locatable>(parent: &EntityState, target: &T) -> bool {
-    let dist = parent.actor.stats.attack_distance();
+    let dist = parent.actor.stats.attack_distance() + elevation_range_bonus(parent, target);
     is_within(parent, target, dist)
 }
 
+/// The extra attack distance a ranged attacker has from being on higher or
+/// lower ground than the target
+fn elevation_range_bonus<T: Locatable>(parent: &EntityState, target: &T) -> f32 {
+    if !parent.actor.stats.attack_is_ranged() {
+        return 0.0;
+    }
+
+    let area_state = match GameState::get_area_state(&parent.location.area_id) {
+        None => return 0.0,
+        Some(area_state) => area_state,
+    };
+
+    let kind = area_state.borrow().elevation_kind(parent, target);
+    Module::rules().elevation.bonus(kind).range
+}
+
 pub fn is_within_touch_dist<T: Locatable>(parent: &EntityState, target: &T) -> bool {
     let dist = parent.actor.stats.touch_distance();
     is_within(parent, target, dist)
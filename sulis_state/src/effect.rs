@@ -16,13 +16,20 @@
 
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use std::rc::Rc;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-use crate::script::{script_callback::FuncKind, CallbackData};
-use crate::{save_state::EffectSaveState, ChangeListenerList, EntityState};
+use crate::area_feedback_text::ColorKind;
+use crate::script::{
+    script_callback::FuncKind, CallbackData, ScriptCallback, ScriptResistedEffect,
+};
+use crate::{
+    condition::ConditionState, save_state::EffectSaveState, AreaFeedbackText, ChangeListenerList,
+    EntityState, GameState,
+};
 use sulis_core::util::{invalid_data_error, ExtInt, Point};
 use sulis_module::{BonusList, ROUND_TIME_MILLIS};
 
@@ -44,6 +51,65 @@ pub struct Icon {
     pub text: String,
 }
 
+/// How an effect being applied to an entity interacts with the effects
+/// already on the entity with the same tag
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EffectStacking {
+    /// The effect is applied alongside any existing effects
+    #[default]
+    Stack,
+
+    /// The duration of an existing effect is reset to that of the new
+    /// effect, which is not applied.  The existing effect keeps its own
+    /// bonuses and callbacks
+    Refresh,
+
+    /// Existing effects with a lower strength are removed and the new effect
+    /// applied.  Otherwise, the new effect is not applied
+    ReplaceIfStronger,
+
+    /// An existing effect from the same source is removed and the new
+    /// effect applied
+    UniquePerSource,
+}
+
+impl FromStr for EffectStacking {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let val = match s {
+            "stack" => EffectStacking::Stack,
+            "refresh" => EffectStacking::Refresh,
+            "replace_if_stronger" => EffectStacking::ReplaceIfStronger,
+            "unique_per_source" => EffectStacking::UniquePerSource,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Unable to parse EffectStacking from '{s}'"),
+                ));
+            }
+        };
+
+        Ok(val)
+    }
+}
+
+/// The result of adding an effect to an entity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EffectOutcome {
+    /// The effect was added at the specified index
+    Added(usize),
+
+    /// The existing effect at the specified index was refreshed instead
+    Refreshed(usize),
+
+    /// A stronger effect was already applied
+    Ignored,
+
+    /// The entity is immune to the specified category of the effect
+    Resisted(String),
+}
+
 pub struct Effect {
     pub name: String,
     pub tag: String,
@@ -58,6 +124,11 @@ pub struct Effect {
     pub(crate) entity: Option<usize>,
     pub(crate) callbacks: Vec<Rc<CallbackData>>,
     pub(crate) icon: Option<Icon>,
+    pub(crate) condition: Option<ConditionState>,
+    pub(crate) stacking: EffectStacking,
+    pub(crate) strength: f32,
+    pub(crate) source: Option<usize>,
+    pub(crate) categories: Vec<String>,
 
     squares_moved: HashMap<usize, u32>,
 
@@ -98,6 +169,19 @@ impl Effect {
             }
         }
 
+        // the applier may have been removed since the save
+        let condition = data.condition.map(|mut condition| {
+            condition.applier = condition
+                .applier
+                .and_then(|applier| entities.get(&applier))
+                .map(|entity| entity.borrow().index());
+            condition
+        });
+        let source = data
+            .source
+            .and_then(|source| entities.get(&source))
+            .map(|entity| entity.borrow().index());
+
         Ok(Effect {
             name: data.name,
             tag: data.tag,
@@ -109,6 +193,11 @@ impl Effect {
             surface,
             entity: data.entity,
             icon: data.icon,
+            condition,
+            stacking: data.stacking,
+            strength: data.strength,
+            source,
+            categories: data.categories,
 
             squares_moved: HashMap::new(),
             callbacks,
@@ -138,6 +227,11 @@ impl Effect {
             surface: None,
             entity: None,
             icon: None,
+            condition: None,
+            stacking: EffectStacking::default(),
+            strength: 0.0,
+            source: None,
+            categories: Vec::new(),
             squares_moved: HashMap::new(),
         }
     }
@@ -188,6 +282,35 @@ impl Effect {
         self.entity = Some(entity);
     }
 
+    /// Sets how this effect stacks with existing effects with the same tag
+    /// when it is applied to an entity
+    pub fn set_stacking(&mut self, stacking: EffectStacking, strength: f32) {
+        self.stacking = stacking;
+        self.strength = strength;
+    }
+
+    /// Sets the entity responsible for applying this effect
+    pub fn set_source(&mut self, entity: usize) {
+        self.source = Some(entity);
+    }
+
+    /// Adds a category, such as `fear` or `poison`, that entities may be
+    /// immune to.  The effect's tag is always a category
+    pub fn add_category(&mut self, category: String) {
+        self.categories.push(category);
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.tag.as_str()).chain(self.categories.iter().map(|c| c.as_str()))
+    }
+
+    pub fn is_marked_for_removal(&self) -> bool {
+        match self.total_duration {
+            ExtInt::Infinity => false,
+            ExtInt::Int(total_duration) => self.cur_duration >= total_duration,
+        }
+    }
+
     pub fn is_surface(&self) -> bool {
         self.surface.is_some()
     }
@@ -257,6 +380,14 @@ impl Effect {
         Vec::new()
     }
 
+    /// Resets the duration of this effect to that of `other`, as when `other`
+    /// is applied with `Refresh` stacking.  The bonuses and callbacks of
+    /// `other` are discarded.
+    pub(crate) fn refresh(&mut self, other: &Effect) {
+        self.cur_duration = 0;
+        self.total_duration = other.total_duration;
+    }
+
     pub fn is_removal(&self) -> bool {
         match self.total_duration {
             ExtInt::Infinity => false,
@@ -276,6 +407,10 @@ impl Effect {
         &self.name
     }
 
+    pub fn condition(&self) -> Option<&ConditionState> {
+        self.condition.as_ref()
+    }
+
     pub fn bonuses(&self) -> &BonusList {
         &self.bonuses
     }
@@ -299,3 +434,59 @@ impl Effect {
         }
     }
 }
+
+/// Shows feedback for the entity resisting the named effect due to its
+/// immunity to `category`, and fires the entity's `OnEffectResisted` scripts
+pub(crate) fn fire_resisted(entity: &Rc<RefCell<EntityState>>, name: &str, category: &str) {
+    info!(
+        "'{}' is immune to '{}' from '{}'",
+        entity.borrow().unique_id(),
+        category,
+        name
+    );
+
+    let area_state = GameState::area_state();
+    let mut feedback = AreaFeedbackText::with_target(&entity.borrow(), &area_state.borrow());
+    feedback.add_entry("Immune".to_string(), ColorKind::Info);
+    area_state.borrow_mut().add_feedback_text(feedback);
+
+    let cbs = entity
+        .borrow()
+        .callbacks(&GameState::turn_manager().borrow());
+    let resisted = ScriptResistedEffect {
+        name: name.to_string(),
+        category: category.to_string(),
+    };
+    cbs.iter()
+        .for_each(|cb| cb.on_effect_resisted(resisted.clone()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(duration: u32, parent: usize) -> Effect {
+        let mut effect = Effect::new(
+            "Effect",
+            "effect",
+            ExtInt::Int(duration),
+            BonusList::default(),
+            None,
+        );
+        effect.add_callback(Rc::new(CallbackData::new_entity(parent)));
+        effect
+    }
+
+    #[test]
+    fn refresh_keeps_original_callbacks() {
+        let mut existing = effect(ROUND_TIME_MILLIS, 1);
+        existing.cur_duration = ROUND_TIME_MILLIS / 2;
+
+        existing.refresh(&effect(3 * ROUND_TIME_MILLIS, 2));
+
+        assert_eq!(existing.cur_duration, 0);
+        assert_eq!(existing.total_duration, ExtInt::Int(3 * ROUND_TIME_MILLIS));
+        let parents: Vec<_> = existing.callbacks().iter().map(|cb| cb.parent()).collect();
+        assert_eq!(parents, vec![1]);
+    }
+}
@@ -20,7 +20,8 @@ use std::rc::Rc;
 use crate::{center, is_threat, ActorState, EntityState, GameState};
 use sulis_core::io::Audio;
 use sulis_module::{
-    AccuracyKind, Attack, AttackKind, DamageKind, HitFlags, HitKind, Module, OnTrigger,
+    rules::CoverBonus, AccuracyKind, Attack, AttackKind, CoverKind, DamageKind, ElevationKind,
+    HitFlags, HitKind, Module, OnTrigger, ReactionKind, StatList,
 };
 
 fn is_sneak_attack(parent: &EntityState, target: &EntityState) -> bool {
@@ -28,6 +29,16 @@ fn is_sneak_attack(parent: &EntityState, target: &EntityState) -> bool {
 }
 
 fn is_flanking(parent: &EntityState, target: &EntityState) -> bool {
+    is_flanking_from(parent, center(parent), target)
+}
+
+/// Returns true if the parent would be flanking the target when attacking
+/// from a position with the specified center
+pub(crate) fn is_flanking_from(
+    parent: &EntityState,
+    p_parent: (f32, f32),
+    target: &EntityState,
+) -> bool {
     if target.actor.stats.flanked_immunity {
         return false;
     }
@@ -48,7 +59,6 @@ fn is_flanking(parent: &EntityState, target: &EntityState) -> bool {
         }
 
         let p_target = center(target);
-        let p_parent = center(parent);
         let p_other = center(&*entity);
 
         let p1 = (p_target.0 - p_parent.0, p_target.1 - p_parent.1);
@@ -122,6 +132,19 @@ pub fn weapon_attack(
     }
 
     ActorState::check_death(parent, target);
+
+    if result
+        .iter()
+        .any(|(hit_kind, _, _)| *hit_kind == HitKind::Miss)
+    {
+        let mgr = GameState::turn_manager();
+        mgr.borrow_mut().queue_reaction(
+            ReactionKind::Counterattack,
+            target.borrow().index(),
+            parent.borrow().index(),
+        );
+    }
+
     result
 }
 
@@ -158,12 +181,76 @@ fn attack_internal(
     flanking: bool,
     sneak_attack: bool,
 ) -> (HitKind, HitFlags, Vec<(DamageKind, u32)>) {
-    let rules = Module::rules();
+    let (cover, elevation) = {
+        let parent = parent.borrow();
+        let target = target.borrow();
+        match GameState::get_area_state(&parent.location.area_id) {
+            None => (CoverKind::None, ElevationKind::Level),
+            Some(area_state) => {
+                let area_state = area_state.borrow();
+                let cover = if uses_cover(&attack.kind) {
+                    area_state.cover(&parent, &target)
+                } else {
+                    CoverKind::None
+                };
+                (cover, area_state.elevation_kind(&parent, &*target))
+            }
+        }
+    };
 
-    let concealment = 0.max(
-        target.borrow().actor.stats.concealment - parent.borrow().actor.stats.concealment_ignore,
+    let (hit_kind, hit_flags, damage) = roll_attack(
+        &parent.borrow().actor.stats,
+        &target.borrow().actor.stats,
+        attack,
+        flanking,
+        sneak_attack,
+        cover,
+        elevation,
     );
 
+    if !damage.is_empty() {
+        EntityState::remove_hp(target, parent, hit_kind, damage.clone());
+    }
+
+    (hit_kind, hit_flags, damage)
+}
+
+/// Only ranged and spell attacks are affected by the target's cover
+fn uses_cover(kind: &AttackKind) -> bool {
+    match kind {
+        AttackKind::Ranged { .. } => true,
+        AttackKind::Fortitude { accuracy }
+        | AttackKind::Reflex { accuracy }
+        | AttackKind::Will { accuracy } => !matches!(accuracy, AccuracyKind::Melee),
+        AttackKind::Melee { .. } | AttackKind::Dummy => false,
+    }
+}
+
+/// Rolls the specified attack by an attacker with the `parent` stats against
+/// a target with the `target` stats, `cover`, and the attacker's `elevation`
+/// relative to the target, returning the kind of hit and the damage that
+/// should be applied to the target.  No state is modified other than the
+/// random number generator.
+pub fn roll_attack(
+    parent: &StatList,
+    target: &StatList,
+    attack: &mut Attack,
+    flanking: bool,
+    sneak_attack: bool,
+    cover: CoverKind,
+    elevation: ElevationKind,
+) -> (HitKind, HitFlags, Vec<(DamageKind, u32)>) {
+    let rules = Module::rules();
+    let elevation = rules.elevation.bonus(elevation);
+
+    let cover = if uses_cover(&attack.kind) {
+        rules.cover.bonus(cover)
+    } else {
+        CoverBonus::default()
+    };
+
+    let concealment = 0.max(target.concealment + cover.concealment - parent.concealment_ignore);
+
     if !rules.concealment_roll(concealment) {
         debug!("Concealment miss");
         return (
@@ -176,20 +263,17 @@ fn attack_internal(
         );
     }
 
-    let (accuracy_kind, defense) = {
-        let target_stats = &target.borrow().actor.stats;
-        match attack.kind {
-            AttackKind::Fortitude { accuracy } => (accuracy, target_stats.fortitude),
-            AttackKind::Reflex { accuracy } => (accuracy, target_stats.reflex),
-            AttackKind::Will { accuracy } => (accuracy, target_stats.will),
-            AttackKind::Melee { .. } => (AccuracyKind::Melee, target_stats.defense),
-            AttackKind::Ranged { .. } => (AccuracyKind::Ranged, target_stats.defense),
-            AttackKind::Dummy => {
-                return (HitKind::Hit, HitFlags::default(), Vec::new());
-            }
+    let (accuracy_kind, defense) = match attack.kind {
+        AttackKind::Fortitude { accuracy } => (accuracy, target.fortitude),
+        AttackKind::Reflex { accuracy } => (accuracy, target.reflex),
+        AttackKind::Will { accuracy } => (accuracy, target.will),
+        AttackKind::Melee { .. } => (AccuracyKind::Melee, target.defense),
+        AttackKind::Ranged { .. } => (AccuracyKind::Ranged, target.defense),
+        AttackKind::Dummy => {
+            return (HitKind::Hit, HitFlags::default(), Vec::new());
         }
     };
-    let crit_immunity = target.borrow().actor.stats.crit_immunity;
+    let defense = defense + cover.defense;
 
     if flanking {
         attack.bonuses.melee_accuracy += rules.flanking_accuracy_bonus;
@@ -201,45 +285,41 @@ fn attack_internal(
         attack.bonuses.spell_accuracy += rules.hidden_accuracy_bonus;
     }
 
+    attack.bonuses.melee_accuracy += elevation.accuracy;
+    attack.bonuses.ranged_accuracy += elevation.accuracy;
+    attack.bonuses.spell_accuracy += elevation.accuracy;
+
     let hit_flags = HitFlags {
         flanking,
         sneak_attack,
         concealment: false,
     };
 
-    let (hit_kind, damage_multiplier) = {
-        let parent_stats = &parent.borrow().actor.stats;
-        let hit_kind =
-            parent_stats.attack_roll(accuracy_kind, crit_immunity, defense, &attack.bonuses);
-        let damage_multiplier = match hit_kind {
-            HitKind::Miss => {
-                debug!("Miss");
-                return (HitKind::Miss, hit_flags, Vec::new());
-            }
-            HitKind::Graze => parent_stats.graze_multiplier + attack.bonuses.graze_multiplier,
-            HitKind::Hit => parent_stats.hit_multiplier + attack.bonuses.hit_multiplier,
-            HitKind::Crit => parent_stats.crit_multiplier + attack.bonuses.crit_multiplier,
-            HitKind::Auto => panic!(),
-        };
-        (hit_kind, damage_multiplier)
-    };
-
-    let damage = {
-        let target = &target.borrow().actor.stats;
-        let damage = &attack.damage;
-        rules.roll_damage(damage, &target.armor, &target.resistance, damage_multiplier)
-    };
-
-    debug!("{:?}. {:?} damage", hit_kind, damage);
-
-    if !damage.is_empty() {
-        let mut total = 0;
-        for (_kind, amount) in damage.iter() {
-            total += amount;
+    let hit_kind = parent.attack_roll(
+        accuracy_kind,
+        target.crit_immunity,
+        defense,
+        &attack.bonuses,
+    );
+    let damage_multiplier = match hit_kind {
+        HitKind::Miss => {
+            debug!("Miss");
+            return (HitKind::Miss, hit_flags, Vec::new());
         }
+        HitKind::Graze => parent.graze_multiplier + attack.bonuses.graze_multiplier,
+        HitKind::Hit => parent.hit_multiplier + attack.bonuses.hit_multiplier,
+        HitKind::Crit => parent.crit_multiplier + attack.bonuses.crit_multiplier,
+        HitKind::Auto => panic!(),
+    } + elevation.damage;
+
+    let damage = rules.roll_damage(
+        &attack.damage,
+        &target.armor,
+        &target.resistance,
+        damage_multiplier,
+    );
 
-        EntityState::remove_hp(target, parent, hit_kind, damage.clone());
-    }
+    debug!("{:?}. {:?} damage", hit_kind, damage);
 
     (hit_kind, hit_flags, damage)
 }
@@ -27,8 +27,8 @@ use crate::save_state::EntitySaveState;
 use crate::script::{self, CallbackData, ScriptEntitySet};
 use crate::{
     entity_attack_handler::weapon_attack, entity_texture_cache::Slot, is_within_attack_dist,
-    ActorState, AreaState, ChangeListenerList, EntityTextureCache, EntityTextureSlot, GameState,
-    Location, ScriptCallback, TurnManager,
+    morale, ActorState, AreaState, ChangeListenerList, EntityTextureCache, EntityTextureSlot,
+    GameState, Location, ScriptCallback, TurnManager,
 };
 use sulis_core::io::GraphicsRenderer;
 use sulis_core::ui::{color, Color};
@@ -494,6 +494,8 @@ impl EntityState {
         cbs.iter()
             .for_each(|cb| cb.on_damaged(&targets, hit_kind, damage.clone()));
 
+        morale::on_damaged(entity, hp_amount);
+
         let hp = entity.borrow().actor.hp();
         if hp <= 0 {
             debug!(
@@ -21,7 +21,7 @@ use std::rc::Rc;
 
 use sulis_core::config::Config;
 use sulis_core::io::GraphicsRenderer;
-use sulis_core::util::{invalid_data_error, ExtInt, Offset, Point, Scale};
+use sulis_core::util::{self, invalid_data_error, ExtInt, Offset, Point, Scale};
 use sulis_module::on_trigger::QuestEntryState;
 use sulis_module::{
     area::{Destination, PathFinder, Trigger, TriggerKind},
@@ -31,9 +31,9 @@ use sulis_module::{
 use crate::animation::{particle_generator::Param, Anim, AnimSaveState, AnimState};
 use crate::script::{script_cache, script_callback, Script, ScriptCallback, ScriptEntity};
 use crate::{
-    path_finder, transition_handler, AreaState, ChangeListener, ChangeListenerList, Effect,
-    EntityState, Formation, ItemList, Location, PartyStash, QuestStateSet, SaveState, TurnManager,
-    UICallback, WorldMapState, AI,
+    condition, path_finder, reaction, save_file, transition_handler, AreaState, ChangeListener,
+    ChangeListenerList, Effect, EntityState, Formation, ItemList, Location, PartyStash,
+    QuestStateSet, SaveState, TurnManager, UICallback, WorldMapState, AI,
 };
 
 thread_local! {
@@ -73,6 +73,7 @@ const MAX_ZOOM: f32 = 2.0;
 
 impl GameState {
     pub fn load(save_state: SaveState) -> Result<(), Error> {
+        util::set_random(save_state.random);
         TURN_MANAGER.with(|mgr| {
             mgr.borrow_mut().load(save_state.total_elapsed_millis);
         });
@@ -82,6 +83,7 @@ impl GameState {
         MODAL_LOCKED.with(|c| c.set(false));
         ANIMS_TO_ADD.with(|anims| anims.borrow_mut().clear());
         AI.with(|ai| *ai.borrow_mut() = AI::new());
+        save_file::reset_autosave();
         script_cache::setup().map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
 
         let game_state: Result<GameState, Error> = {
@@ -138,7 +140,11 @@ impl GameState {
                 })
                 .collect::<Result<Vec<_>, _>>()?;
 
-            for entity in entities.values() {
+            // load entities in a consistent order, so that a given save always
+            // results in the same entity indices
+            let mut indices: Vec<usize> = entities.keys().copied().collect();
+            indices.sort_unstable();
+            for entity in indices.iter().map(|index| &entities[index]) {
                 let area_state = match areas.get(&entity.borrow().location.area_id) {
                     Some(state) => state,
                     None => unreachable!(),
@@ -183,7 +189,7 @@ impl GameState {
 
                     let new_idx =
                         mgr.borrow_mut()
-                            .add_effect(effect, entity, Vec::new(), Vec::new());
+                            .add_loaded_effect(effect, entity, Vec::new(), Vec::new());
                     assert_eq!(new_index, new_idx);
                     effects.insert(old_index, new_index);
                     continue;
@@ -285,6 +291,7 @@ impl GameState {
         MODAL_LOCKED.with(|c| c.set(false));
         ANIMS_TO_ADD.with(|anims| anims.borrow_mut().clear());
         AI.with(|ai| *ai.borrow_mut() = AI::new());
+        save_file::reset_autosave();
 
         TURN_MANAGER.with(|mgr| {
             let rules = Module::rules();
@@ -940,6 +947,9 @@ impl GameState {
         let cbs = mgr.borrow_mut().update_entity_move_callbacks();
         script_callback::fire_on_moved(cbs);
 
+        reaction::fire_pending();
+        condition::fire_pending();
+
         {
             let area_state = GameState::area_state();
             let mut area_state = area_state.borrow_mut();
@@ -995,6 +1005,8 @@ impl GameState {
             script_cache::set_report_enabled(true);
         }
 
+        save_file::update_autosave(millis);
+
         ui_cb
     }
 
@@ -0,0 +1,270 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Support for running the game without a display or audio device.  The
+//! resources and module are loaded as normal, and the `GameState` may then be
+//! driven by a `sulis_core::io::HeadlessSystem`.
+
+use std::io::Error;
+
+use serde::{Deserialize, Serialize};
+
+use sulis_core::config::Config;
+use sulis_core::io::HeadlessResult;
+use sulis_core::resource::ResourceSet;
+use sulis_core::util;
+use sulis_module::{Module, Time};
+
+use crate::script::script_validator;
+use crate::GameState;
+
+/// The `--campaign <dir>` and `--mod <dir>` arguments shared by the command
+/// line tools
+#[derive(Default, Debug)]
+pub struct ModuleArgs {
+    pub campaign: Option<String>,
+    pub mods: Vec<String>,
+}
+
+impl ModuleArgs {
+    /// Parses `arg` if it is one of the module arguments, reading its value
+    /// with `value`.  Returns false if `arg` is some other argument.
+    pub fn parse_arg(
+        &mut self,
+        arg: &str,
+        value: impl FnOnce() -> Result<String, String>,
+    ) -> Result<bool, String> {
+        match arg {
+            "--campaign" => self.campaign = Some(value()?),
+            "--mod" => self.mods.push(value()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Returns an error if no campaign was specified
+    pub fn require_campaign(&self) -> Result<(), String> {
+        match self.campaign {
+            None => Err("A campaign directory must be specified".to_string()),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Returns the directories to pass to `load_resources`
+    pub fn directories(&self) -> Vec<String> {
+        let mut dirs = vec![Config::resources_config().directory];
+        dirs.extend(self.campaign.iter().cloned());
+        dirs.extend(self.mods.iter().cloned());
+        dirs
+    }
+}
+
+/// Loads the base resources and the campaign and mods specified by `dirs`.
+/// The first directory is the base data directory, the second the campaign,
+/// and any following directories are mods.
+pub fn load_resources(dirs: Vec<String>) -> Result<(), Error> {
+    let start = std::time::Instant::now();
+
+    info!("Reading resources from '{:?}'", dirs);
+    let yaml = ResourceSet::load_resources(dirs.clone())?;
+
+    if dirs.len() > 1 {
+        info!("Loading module '{}'", dirs[1]);
+        Module::load_resources(yaml, dirs)?;
+
+        for error in script_validator::validate() {
+            warn!("{}", error);
+        }
+    }
+
+    info!(
+        "Loaded all resources in {}s",
+        util::format_elapsed_secs(start.elapsed())
+    );
+
+    Ok(())
+}
+
+/// A summary of the game state at the end of a headless run
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HeadlessReport {
+    pub campaign: String,
+    pub frames: u32,
+    pub elapsed_millis: u32,
+    pub exited: bool,
+    pub total_elapsed_millis: usize,
+    pub time: Time,
+    pub current_area: String,
+    pub combat_active: bool,
+    pub coins: i32,
+    pub party: Vec<PartyMemberReport>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PartyMemberReport {
+    pub id: String,
+    pub name: String,
+    pub level: u32,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub dead: bool,
+}
+
+impl HeadlessReport {
+    /// Creates a report from the current `GameState` and the result of the
+    /// main loop.  The game state must have been initialized.
+    pub fn create(result: HeadlessResult, errors: Vec<String>) -> HeadlessReport {
+        let mgr = GameState::turn_manager();
+        let mgr = mgr.borrow();
+
+        let area_state = GameState::area_state();
+        let current_area = area_state.borrow().area.area.id.to_string();
+
+        let party = GameState::party()
+            .iter()
+            .map(|entity| {
+                let entity = entity.borrow();
+                PartyMemberReport {
+                    id: entity.actor.actor.id.to_string(),
+                    name: entity.actor.actor.name.to_string(),
+                    level: entity.actor.actor.total_level,
+                    hp: entity.actor.hp(),
+                    max_hp: entity.actor.stats.max_hp,
+                    dead: entity.actor.is_dead(),
+                }
+            })
+            .collect();
+
+        HeadlessReport {
+            campaign: Module::campaign().id.to_string(),
+            frames: result.frames,
+            elapsed_millis: result.elapsed_millis,
+            exited: result.exited,
+            total_elapsed_millis: mgr.total_elapsed_millis(),
+            time: mgr.current_time(),
+            current_area,
+            combat_active: mgr.is_combat_active(),
+            coins: GameState::party_coins(),
+            party,
+            errors,
+        }
+    }
+
+    /// Returns true if every member of the party is dead
+    pub fn is_party_defeated(&self) -> bool {
+        self.party.iter().all(|member| member.dead)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sulis_core::serde_json;
+
+    use super::*;
+
+    fn member(id: &str, dead: bool) -> PartyMemberReport {
+        PartyMemberReport {
+            id: id.to_string(),
+            name: id.to_string(),
+            level: 1,
+            hp: if dead { 0 } else { 10 },
+            max_hp: 10,
+            dead,
+        }
+    }
+
+    fn report(party: Vec<PartyMemberReport>) -> HeadlessReport {
+        HeadlessReport {
+            campaign: "test".to_string(),
+            frames: 60,
+            elapsed_millis: 1000,
+            exited: false,
+            total_elapsed_millis: 1000,
+            time: Time {
+                day: 1,
+                hour: 2,
+                round: 3,
+                millis: 400,
+            },
+            current_area: "area".to_string(),
+            combat_active: true,
+            coins: 25,
+            party,
+            errors: vec!["error".to_string()],
+        }
+    }
+
+    #[test]
+    fn parses_module_args() {
+        let mut args = ModuleArgs::default();
+        assert!(args.require_campaign().is_err());
+
+        let value = |value: &str| {
+            let value = value.to_string();
+            move || Ok(value)
+        };
+        assert!(args.parse_arg("--campaign", value("camp")).unwrap());
+        assert!(args.parse_arg("--mod", value("a")).unwrap());
+        assert!(args.parse_arg("--mod", value("b")).unwrap());
+        assert!(!args.parse_arg("--other", value("x")).unwrap());
+        assert!(args
+            .parse_arg("--mod", || Err("missing".to_string()))
+            .is_err());
+
+        assert!(args.require_campaign().is_ok());
+        assert_eq!(args.campaign.as_deref(), Some("camp"));
+        assert_eq!(args.mods, vec!["a", "b"]);
+
+        let dirs = args.directories();
+        assert_eq!(&dirs[1..], &["camp", "a", "b"]);
+    }
+
+    #[test]
+    fn party_defeated_only_when_all_dead() {
+        assert!(!report(vec![member("a", false), member("b", false)]).is_party_defeated());
+        assert!(!report(vec![member("a", true), member("b", false)]).is_party_defeated());
+        assert!(report(vec![member("a", true), member("b", true)]).is_party_defeated());
+    }
+
+    #[test]
+    fn report_json_round_trip() {
+        let report = report(vec![member("a", false), member("b", true)]);
+        let json = serde_json::to_string(&report).unwrap();
+        let read: HeadlessReport = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(read.campaign, report.campaign);
+        assert_eq!(read.frames, report.frames);
+        assert_eq!(read.elapsed_millis, report.elapsed_millis);
+        assert_eq!(read.time, report.time);
+        assert_eq!(read.current_area, report.current_area);
+        assert!(read.combat_active);
+        assert_eq!(read.coins, report.coins);
+        assert_eq!(read.errors, report.errors);
+        assert_eq!(read.party.len(), 2);
+        assert_eq!(read.party[1].id, "b");
+        assert!(read.party[1].dead);
+    }
+
+    #[test]
+    fn report_rejects_unknown_fields() {
+        let mut value = serde_json::to_value(report(Vec::new())).unwrap();
+        value["unknown"] = serde_json::Value::Bool(true);
+        assert!(serde_json::from_value::<HeadlessReport>(value).is_err());
+    }
+}
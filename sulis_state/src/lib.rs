@@ -20,6 +20,8 @@ extern crate log;
 mod ai;
 pub use self::ai::AI;
 
+pub mod ai_trace;
+
 pub mod ability_state;
 pub use self::ability_state::AbilityState;
 
@@ -38,6 +40,12 @@ mod change_listener;
 pub use self::change_listener::ChangeListener;
 pub use self::change_listener::ChangeListenerList;
 
+pub mod combat_sim;
+pub use self::combat_sim::CombatSimReport;
+
+pub mod condition;
+pub use self::condition::ConditionState;
+
 mod distance_finder;
 pub use self::distance_finder::{
     can_attack, center, center_i32, dist, is_threat, is_within, is_within_attack_dist,
@@ -45,7 +53,7 @@ pub use self::distance_finder::{
 };
 
 mod effect;
-pub use self::effect::Effect;
+pub use self::effect::{Effect, EffectOutcome, EffectStacking};
 
 mod entity_attack_handler;
 
@@ -66,6 +74,9 @@ pub use self::game_state::GameState;
 mod generated_area;
 pub use self::generated_area::{GeneratedArea, PregenOutput};
 
+pub mod headless;
+pub use self::headless::HeadlessReport;
+
 pub mod inventory;
 pub use self::inventory::Inventory;
 
@@ -77,6 +88,7 @@ pub use self::location::Location;
 
 mod los_calculator;
 pub use self::los_calculator::calculate_los;
+pub use self::los_calculator::compute_cover;
 pub use self::los_calculator::has_visibility;
 
 mod merchant_state;
@@ -92,6 +104,9 @@ pub use self::party_stash::PartyStash;
 mod prop_state;
 pub use self::prop_state::PropState;
 
+pub mod morale;
+pub use self::morale::MoraleState;
+
 mod p_stats;
 pub use self::p_stats::PStats;
 
@@ -99,12 +114,26 @@ pub mod quest_state;
 pub use self::quest_state::QuestState;
 pub use self::quest_state::QuestStateSet;
 
+mod reaction;
+
 mod range_indicator;
 pub use self::range_indicator::{RangeIndicator, RangeIndicatorHandler, RangeIndicatorImageSet};
 
 pub mod save_file;
 pub use self::save_file::SaveFile;
 pub use self::save_file::SaveFileMetaData;
+pub use self::save_file::{ManifestCheck, SaveManifest};
+
+pub mod save_container;
+
+pub mod save_editor;
+pub use self::save_editor::SaveSummary;
+
+pub mod save_migration;
+pub use self::save_migration::SAVE_FORMAT_VERSION;
+
+pub mod replay;
+pub use self::replay::Replay;
 
 mod save_state;
 pub use self::save_state::SaveState;
@@ -16,6 +16,8 @@
 
 use std::collections::HashSet;
 
+use sulis_module::{CoverKind, Module};
+
 use crate::{EntityState, GeneratedArea};
 
 #[must_use]
@@ -84,6 +86,98 @@ pub fn has_visibility(
     false
 }
 
+/// Computes the cover the target has against attacks from the entity.  A
+/// line is drawn from the center of the entity to each of the target's
+/// squares, and the line is obstructed if the square is not visible or the
+/// line passes through an impassable prop or, if the rules allow, another
+/// entity.  The cover is graded by the fraction of lines obstructed.
+pub fn compute_cover(
+    area: &GeneratedArea,
+    prop_vis_grid: &[bool],
+    prop_pass_grid: &[bool],
+    entity_grid: &[Vec<usize>],
+    entity: &EntityState,
+    target: &EntityState,
+) -> CoverKind {
+    let rules = &Module::rules().cover;
+    let start_x = entity.location.x + entity.size.width / 2;
+    let start_y = entity.location.y + entity.size.height / 2;
+    let src_elev = area.layer_set.elevation(start_x, start_y);
+
+    let obstructs = |x: i32, y: i32| {
+        if contains(entity, x, y) || contains(target, x, y) {
+            return false;
+        }
+
+        let index = (x + y * area.width) as usize;
+        if !prop_pass_grid[index] {
+            return true;
+        }
+
+        rules.entities
+            && entity_grid[index]
+                .iter()
+                .any(|i| *i != entity.index() && *i != target.index())
+    };
+
+    let mut lines = 0;
+    let mut obstructed = 0;
+    for p in target.location_points() {
+        lines += 1;
+
+        if !check_vis(area, prop_vis_grid, start_x, start_y, p.x, p.y, src_elev)
+            || line_points(start_x, start_y, p.x, p.y)
+                .into_iter()
+                .any(|(x, y)| obstructs(x, y))
+        {
+            obstructed += 1;
+        }
+    }
+
+    if lines == 0 {
+        return CoverKind::None;
+    }
+
+    rules.cover(obstructed as f32 / lines as f32)
+}
+
+fn contains(entity: &EntityState, x: i32, y: i32) -> bool {
+    x >= entity.location.x
+        && y >= entity.location.y
+        && x < entity.location.x + entity.size.width
+        && y < entity.location.y + entity.size.height
+}
+
+/// Returns all points on the line from start to end, inclusive
+fn line_points(start_x: i32, start_y: i32, end_x: i32, end_y: i32) -> Vec<(i32, i32)> {
+    let delta_x = (end_x - start_x).abs();
+    let delta_y = -(end_y - start_y).abs();
+    let xi = if start_x < end_x { 1 } else { -1 };
+    let yi = if start_y < end_y { 1 } else { -1 };
+
+    let mut points = Vec::new();
+    let mut err = delta_x + delta_y;
+    let (mut x, mut y) = (start_x, start_y);
+    loop {
+        points.push((x, y));
+        if x == end_x && y == end_y {
+            break;
+        }
+
+        let err2 = 2 * err;
+        if err2 >= delta_y {
+            err += delta_y;
+            x += xi;
+        }
+        if err2 <= delta_x {
+            err += delta_x;
+            y += yi;
+        }
+    }
+
+    points
+}
+
 fn check_vis(
     area: &GeneratedArea,
     prop_vis_grid: &[bool],
@@ -0,0 +1,205 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! The morale of hostile entities.  Morale is lost when an entity is
+//! damaged and when nearby allies or the leader of its group die, and is
+//! slowly regained each round.  An entity with low morale flees, rallying
+//! once it has regained enough morale, and one with very low morale
+//! surrenders, becoming neutral for the rest of its life.  The amounts are
+//! set by the `morale` section of the rules.  The party and its allies are
+//! not affected by morale.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai_trace::{self, TraceEvent};
+use crate::area_feedback_text::ColorKind;
+use crate::{dist, AreaFeedbackText, EntityState, GameState, TurnManager};
+use sulis_module::{Faction, Module};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MoraleState {
+    #[default]
+    Steady,
+    Fleeing,
+    Surrendered,
+}
+
+impl fmt::Display for MoraleState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+fn is_affected(entity: &EntityState) -> bool {
+    entity.actor.faction() == Faction::Hostile && !entity.actor.is_dead()
+}
+
+/// Called after the entity has lost the specified number of hit points
+pub(crate) fn on_damaged(entity: &Rc<RefCell<EntityState>>, hp: u32) {
+    let loss = {
+        let entity = entity.borrow();
+        if !is_affected(&entity) {
+            return;
+        }
+
+        let frac = hp as f32 / entity.actor.stats.max_hp.max(1) as f32;
+        (frac * Module::rules().morale.hp_loss).round() as i32
+    };
+
+    change(entity, -loss);
+}
+
+/// Called when the entity has died or been removed, reducing the morale of
+/// its nearby allies.  The members of its AI group lose additional morale
+/// if it was the group's leader.
+pub(crate) fn on_death(mgr: &TurnManager, dead: &Rc<RefCell<EntityState>>) {
+    let rules = &Module::rules().morale;
+    let is_leader = is_leader(mgr, &dead.borrow());
+
+    let mut losses = Vec::new();
+    {
+        let dead = dead.borrow();
+        let area_state = match GameState::get_area_state(&dead.location.area_id) {
+            None => return,
+            Some(area_state) => area_state,
+        };
+        let vis_dist = area_state.borrow().area.area.vis_dist as f32;
+
+        for entity in mgr.entity_iter() {
+            let loss = {
+                let other = entity.borrow();
+                if other.index() == dead.index() {
+                    continue;
+                }
+
+                if !is_affected(&other) || !other.is_friendly(&dead) {
+                    continue;
+                }
+
+                if !other.location.is_in_area_id(&dead.location.area_id)
+                    || dist(&*other, &*dead) > vis_dist
+                {
+                    continue;
+                }
+
+                let same_group = other.ai_group().is_some() && other.ai_group() == dead.ai_group();
+                if is_leader && same_group {
+                    rules.ally_death + rules.leader_death
+                } else {
+                    rules.ally_death
+                }
+            };
+            losses.push((entity, loss));
+        }
+    }
+
+    for (entity, loss) in losses {
+        change(&entity, -loss);
+    }
+}
+
+/// The leader of a group is the member with a higher level than all of the
+/// other members, if there is one
+fn is_leader(mgr: &TurnManager, entity: &EntityState) -> bool {
+    let group = match entity.ai_group() {
+        None => return false,
+        Some(group) => group,
+    };
+    let level = entity.actor.actor.total_level;
+
+    let mut has_members = false;
+    for other in mgr.entity_iter() {
+        let other = other.borrow();
+        if other.index() == entity.index() || other.ai_group() != Some(group) {
+            continue;
+        }
+
+        if other.actor.actor.total_level >= level {
+            return false;
+        }
+        has_members = true;
+    }
+
+    has_members
+}
+
+/// Called at the start of the entity's turn to regain some morale
+pub(crate) fn recover(entity: &Rc<RefCell<EntityState>>) {
+    change(entity, Module::rules().morale.recovery_per_round);
+}
+
+/// Changes the morale of the entity by the specified amount, which is
+/// negative for a loss, causing it to flee, surrender, or rally as needed.
+/// Does nothing if the entity is not hostile.
+pub fn change(entity: &Rc<RefCell<EntityState>>, amount: i32) {
+    if amount == 0 || !is_affected(&entity.borrow()) {
+        return;
+    }
+
+    let state = match entity.borrow_mut().actor.change_morale(amount) {
+        None => return,
+        Some(state) => state,
+    };
+
+    let (id, morale) = {
+        let entity = entity.borrow();
+        (entity.unique_id().to_string(), entity.actor.morale())
+    };
+    info!("'{}' is now {} with morale {}", id, state, morale);
+    ai_trace::record(TraceEvent::Morale {
+        entity: id,
+        state: state.to_string(),
+        morale,
+    });
+
+    let text = match state {
+        MoraleState::Steady => "Rallies",
+        MoraleState::Fleeing => "Flees",
+        MoraleState::Surrendered => {
+            surrender(entity);
+            "Surrenders"
+        }
+    };
+
+    let area_state = GameState::area_state();
+    let mut feedback = AreaFeedbackText::with_target(&entity.borrow(), &area_state.borrow());
+    feedback.add_entry(text.to_string(), ColorKind::Info);
+    area_state.borrow_mut().add_feedback_text(feedback);
+}
+
+/// The entity becomes neutral, and the party is rewarded as if it had been
+/// killed.  The turn manager ends its AI on the next update.
+fn surrender(entity: &Rc<RefCell<EntityState>>) {
+    let xp = {
+        let mut entity = entity.borrow_mut();
+        entity.actor.set_faction(Faction::Neutral);
+
+        match &entity.actor.actor.reward {
+            None => 0,
+            Some(reward) => reward.xp,
+        }
+    };
+
+    if xp > 0 {
+        for member in GameState::party() {
+            member.borrow_mut().add_xp(xp);
+        }
+    }
+}
@@ -19,6 +19,7 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
+use crate::MoraleState;
 use sulis_core::util::ExtInt;
 use sulis_module::{Ability, Actor, Class, Faction, Module, StatList};
 
@@ -52,6 +53,16 @@ pub struct PStats {
     #[serde(default)]
     disabled: bool,
 
+    // stored as the amount lost so that it is full by default
+    #[serde(default)]
+    morale_lost: i32,
+
+    #[serde(default)]
+    morale_state: MoraleState,
+
+    #[serde(default)]
+    reactions_used: i32,
+
     #[serde(skip)]
     base_class: Option<Rc<Class>>,
 }
@@ -72,6 +83,9 @@ impl PStats {
             current_class_stats: HashMap::new(),
             faction: actor.faction(),
             disabled: false,
+            morale_lost: 0,
+            morale_state: MoraleState::Steady,
+            reactions_used: 0,
             base_class: Some(actor.base_class()),
         }
     }
@@ -155,6 +169,11 @@ impl PStats {
         !self.threatened_by.is_empty()
     }
 
+    /// Returns the indices of all entities currently threatening the parent
+    pub fn threatened_by(&self) -> &[usize] {
+        &self.threatened_by
+    }
+
     pub fn add_threatening(&mut self, index: usize) {
         if !self.threatening.contains(&index) {
             self.threatening.push(index);
@@ -235,6 +254,68 @@ impl PStats {
         }
     }
 
+    pub fn morale(&self, stats: &StatList) -> i32 {
+        stats.morale - self.morale_lost
+    }
+
+    pub fn morale_state(&self) -> MoraleState {
+        self.morale_state
+    }
+
+    /// Changes the current morale by the specified amount, which is negative
+    /// for a loss, and updates the morale state.  Returns the new state if it
+    /// changed.
+    pub fn change_morale(&mut self, amount: i32, stats: &StatList) -> Option<MoraleState> {
+        if stats.morale_immunity || self.morale_state == MoraleState::Surrendered {
+            return None;
+        }
+
+        self.morale_lost = (self.morale_lost - amount).clamp(0, stats.morale);
+
+        let rules = &Module::rules().morale;
+        let frac = self.morale(stats) as f32 / stats.morale.max(1) as f32;
+        // a fleeing entity keeps fleeing until it has regained enough morale to rally
+        let is_fleeing = self.morale_state == MoraleState::Fleeing && frac < rules.rally_threshold;
+        let state = if frac <= rules.surrender_threshold {
+            MoraleState::Surrendered
+        } else if frac <= rules.flee_threshold || is_fleeing {
+            MoraleState::Fleeing
+        } else {
+            MoraleState::Steady
+        };
+
+        if state == self.morale_state {
+            None
+        } else {
+            self.morale_state = state;
+            Some(state)
+        }
+    }
+
+    /// Restores full morale, rallying the entity if it was fleeing.  An
+    /// entity that has surrendered remains surrendered.
+    pub fn reset_morale(&mut self) {
+        self.morale_lost = 0;
+        if self.morale_state == MoraleState::Fleeing {
+            self.morale_state = MoraleState::Steady;
+        }
+    }
+
+    pub fn reactions_remaining(&self, stats: &StatList) -> i32 {
+        (stats.reactions - self.reactions_used).max(0)
+    }
+
+    /// Uses up one of the reactions remaining this round, returning false if
+    /// there are none remaining
+    pub fn use_reaction(&mut self, stats: &StatList) -> bool {
+        if self.reactions_remaining(stats) == 0 {
+            return false;
+        }
+
+        self.reactions_used += 1;
+        true
+    }
+
     pub fn add_class_stat(&mut self, stat: &str, amount: u32, max: ExtInt) {
         let amount = match self.current_class_stats.get(stat) {
             None => ExtInt::Int(amount),
@@ -346,6 +427,7 @@ impl PStats {
         }
 
         self.ap = ap;
+        self.reactions_used = 0;
     }
 
     pub fn end_turn(&mut self) {
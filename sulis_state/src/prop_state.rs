@@ -128,7 +128,7 @@ impl PropState {
         let millis_offset = if millis_offset_range == 0 {
             0
         } else {
-            util::gen_rand(0, millis_offset_range)
+            util::gen_visual_rand(0, millis_offset_range)
         };
 
         PropState {
@@ -170,6 +170,10 @@ impl QuestState {
         }
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
     pub fn entry_state(&self, entry: &str) -> QuestEntryState {
         self.entries
             .iter()
@@ -0,0 +1,240 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Reactions are actions entities take outside of their own turn in response
+//! to an action by a hostile.  Reactions are queued in the turn manager as
+//! the triggering actions happen and fired on the next update, so that the
+//! triggering action always completes first.  Each reaction uses up one of
+//! the reactor's reactions for the round.
+//!
+//! Interrupts are the exception: they are resolved immediately as a hostile
+//! begins to activate an ability in one of the rules' `interrupt_groups`, and
+//! a hit stops the ability from being activated.
+//!
+//! An entity able to take a kind of reaction, as set by the `reactions`
+//! section of the rules and `Reaction` bonuses, makes a weapon attack against
+//! the hostile.  Scripts may define their own reactions with `OnReaction`
+//! callbacks, which are called whenever the entity has reactions remaining.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::area_feedback_text::ColorKind;
+use crate::entity_attack_handler::weapon_attack;
+use crate::script::{ScriptCallback, ScriptEntitySet};
+use crate::{is_within_attack_dist, AreaFeedbackText, EntityState, GameState, TurnManager};
+use sulis_module::{Ability, HitKind, Module, ReactionKind};
+
+/// A reaction waiting to be fired on the next update
+pub(crate) struct PendingReaction {
+    pub kind: ReactionKind,
+    pub reactor: usize,
+    pub target: usize,
+}
+
+/// Fires all reactions queued since the last update
+pub(crate) fn fire_pending() {
+    let mgr = GameState::turn_manager();
+    let pending = mgr.borrow_mut().drain_reactions();
+
+    for reaction in pending {
+        let (reactor, target) = {
+            let mgr = mgr.borrow();
+            match (
+                mgr.entity_checked(reaction.reactor),
+                mgr.entity_checked(reaction.target),
+            ) {
+                (Some(reactor), Some(target)) => (reactor, target),
+                _ => continue,
+            }
+        };
+
+        if !can_react(&mgr.borrow(), &reactor.borrow(), &target.borrow()) {
+            continue;
+        }
+
+        fire(&mgr, &reactor, &target, reaction.kind);
+    }
+}
+
+/// Resolves the interrupts of the hostiles threatening `caster` as it begins
+/// to activate `ability`.  Returns true if the activation was interrupted, in
+/// which case the caster still spends the ability's action points.
+pub(crate) fn interrupt(caster: &Rc<RefCell<EntityState>>, ability: &Ability) -> bool {
+    let active = match ability.active {
+        None => return false,
+        Some(ref active) => active,
+    };
+
+    let mgr = GameState::turn_manager();
+    if !mgr.borrow().is_combat_active() {
+        return false;
+    }
+
+    let group = active.group.name();
+    if !Module::rules().reactions.interrupt_groups.contains(&group) {
+        return false;
+    }
+
+    let threateners = caster.borrow().actor.p_stats().threatened_by().to_vec();
+    let mut interrupted = false;
+    for index in threateners {
+        let reactor = match mgr.borrow().entity_checked(index) {
+            None => continue,
+            Some(reactor) => reactor,
+        };
+
+        if !can_react(&mgr.borrow(), &reactor.borrow(), &caster.borrow()) {
+            continue;
+        }
+
+        if use_attack(&reactor, caster, ReactionKind::Interrupt) {
+            let result = weapon_attack(&reactor, caster);
+            interrupted = caster.borrow().actor.is_dead()
+                || result.iter().any(|(kind, _, _)| *kind != HitKind::Miss);
+        }
+
+        fire_callbacks(&mgr, &reactor, caster, ReactionKind::Interrupt);
+
+        if interrupted {
+            break;
+        }
+    }
+
+    if !interrupted {
+        return false;
+    }
+
+    info!(
+        "'{}' is interrupted activating '{}'",
+        caster.borrow().unique_id(),
+        ability.id
+    );
+
+    let bonus = caster.borrow().actor.stats.bonus_ability_action_point_cost;
+    let ap = 0.max(active.ap as i32 - bonus);
+    caster.borrow_mut().actor.remove_ap(ap as u32);
+
+    let area_state = GameState::area_state();
+    let mut text = AreaFeedbackText::with_target(&caster.borrow(), &area_state.borrow());
+    text.add_entry("Interrupted".to_string(), ColorKind::Miss);
+    area_state.borrow_mut().add_feedback_text(text);
+
+    true
+}
+
+fn can_react(mgr: &TurnManager, reactor: &EntityState, target: &EntityState) -> bool {
+    if !mgr.is_combat_active() {
+        return false;
+    }
+
+    // entities only react outside of their own turn
+    if let Some(current) = mgr.current() {
+        if current.borrow().index() == reactor.index() {
+            return false;
+        }
+    }
+
+    if reactor.actor.is_dead() || target.actor.is_dead() || reactor.actor.has_surrendered() {
+        return false;
+    }
+
+    if !target.location.is_in_area_id(&reactor.location.area_id) {
+        return false;
+    }
+
+    reactor.is_hostile(target) && reactor.actor.reactions_remaining() > 0
+}
+
+fn fire(
+    mgr: &Rc<RefCell<TurnManager>>,
+    reactor: &Rc<RefCell<EntityState>>,
+    target: &Rc<RefCell<EntityState>>,
+    kind: ReactionKind,
+) {
+    if use_attack(reactor, target, kind) {
+        EntityState::attack(reactor, target, None, false);
+    }
+
+    fire_callbacks(mgr, reactor, target, kind);
+}
+
+/// Uses up one of the reactor's reactions for a weapon attack against the
+/// target, if it is able to make one.  Returns true if the attack should
+/// be made.
+fn use_attack(
+    reactor: &Rc<RefCell<EntityState>>,
+    target: &Rc<RefCell<EntityState>>,
+    kind: ReactionKind,
+) -> bool {
+    let attacks = {
+        let reactor = reactor.borrow();
+        let stats = &reactor.actor.stats;
+
+        // the target of an opportunity attack has just moved out of reach
+        stats.has_reaction(kind)
+            && !stats.attack_disabled
+            && (kind == ReactionKind::OpportunityAttack
+                || is_within_attack_dist(&reactor, &*target.borrow()))
+    };
+
+    if !attacks || !reactor.borrow_mut().actor.use_reaction() {
+        return false;
+    }
+
+    info!(
+        "'{}' reacts to '{}' with {}",
+        reactor.borrow().unique_id(),
+        target.borrow().unique_id(),
+        kind
+    );
+
+    let area_state = GameState::area_state();
+    let mut text = AreaFeedbackText::with_target(&reactor.borrow(), &area_state.borrow());
+    text.add_entry(feedback_text(kind).to_string(), ColorKind::Info);
+    area_state.borrow_mut().add_feedback_text(text);
+
+    true
+}
+
+fn fire_callbacks(
+    mgr: &Rc<RefCell<TurnManager>>,
+    reactor: &Rc<RefCell<EntityState>>,
+    target: &Rc<RefCell<EntityState>>,
+    kind: ReactionKind,
+) {
+    if reactor.borrow().actor.reactions_remaining() == 0 {
+        return;
+    }
+
+    let cbs = reactor.borrow().callbacks(&mgr.borrow());
+    if cbs.is_empty() {
+        return;
+    }
+
+    let targets = ScriptEntitySet::from_pair(reactor, target);
+    for cb in cbs {
+        cb.on_reaction(&targets, kind);
+    }
+}
+
+fn feedback_text(kind: ReactionKind) -> &'static str {
+    match kind {
+        ReactionKind::OpportunityAttack => "Opportunity Attack",
+        ReactionKind::Counterattack => "Counterattack",
+        ReactionKind::Interrupt => "Interrupt",
+    }
+}
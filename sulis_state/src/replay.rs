@@ -0,0 +1,173 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{BufReader, Error};
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use sulis_core::config;
+use sulis_core::io::{InputRecorder, RecordedFrame};
+use sulis_core::resource::write_json_to_file;
+use sulis_core::serde_json::{self, Value};
+use sulis_core::util::invalid_data_error;
+use sulis_module::Module;
+
+use crate::SaveState;
+
+thread_local! {
+    static START_STATE: RefCell<Option<Value>> = const { RefCell::new(None) };
+}
+
+const MAX_REPORTED_DIFFERENCES: usize = 20;
+
+/// A recording of all input delivered during a play session, together with
+/// the save state the session started from and the state it ended with.
+/// Replaying the input against the starting state should reproduce the
+/// final state.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Replay {
+    pub campaign: String,
+    pub start: Value,
+    pub frames: Vec<RecordedFrame>,
+    pub end: Value,
+}
+
+/// Begins recording input, starting from the specified state.  This should
+/// be called just prior to loading the state.
+pub fn start_recording(save_state: &SaveState) -> Result<(), Error> {
+    let start = serde_json::to_value(save_state).map_err(|e| invalid_data_error(&e.to_string()))?;
+    START_STATE.with(|s| *s.borrow_mut() = Some(start));
+    InputRecorder::start();
+    Ok(())
+}
+
+/// Stops the current recording, capturing the current game state as the
+/// final state.  Returns `None` if no recording was in progress.
+pub fn finish_recording() -> Option<Replay> {
+    let frames = InputRecorder::finish();
+    let start = START_STATE.with(|s| s.borrow_mut().take());
+
+    let (frames, start) = match (frames, start) {
+        (Some(frames), Some(start)) => (frames, start),
+        _ => return None,
+    };
+
+    let end = match serde_json::to_value(SaveState::create()) {
+        Ok(end) => end,
+        Err(e) => {
+            warn!("Unable to serialize final replay state: {}", e);
+            return None;
+        }
+    };
+
+    Some(Replay {
+        campaign: Module::campaign().id.to_string(),
+        start,
+        frames,
+        end,
+    })
+}
+
+impl Replay {
+    pub fn read(path: &Path) -> Result<Replay, Error> {
+        let reader = BufReader::new(File::open(path)?);
+        serde_json::from_reader(reader).map_err(|e| invalid_data_error(&format!("{e}")))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        write_json_to_file(path, self)
+    }
+
+    /// Writes this replay to a new, timestamped file in the user's replay
+    /// directory, returning the path written to
+    pub fn write_to_replay_dir(&self) -> Result<PathBuf, Error> {
+        let mut path = config::USER_DIR.clone();
+        path.push("replay");
+        path.push(&self.campaign);
+        fs::create_dir_all(&path)?;
+
+        let utc = Utc::now();
+        path.push(format!("replay_{}.json", utc.format("%Y%m%d-%H%M%S%.3f")));
+        self.write(&path)?;
+        Ok(path)
+    }
+
+    /// Creates the save state this replay starts from
+    pub fn start_state(&self) -> Result<SaveState, Error> {
+        serde_json::from_value(self.start.clone()).map_err(|e| invalid_data_error(&format!("{e}")))
+    }
+
+    /// Compares the current game state against the final state of this
+    /// replay.  Returns a description of each difference found, which will
+    /// be empty if the states match.
+    pub fn verify(&self) -> Vec<String> {
+        let actual = match serde_json::to_value(SaveState::create()) {
+            Ok(actual) => actual,
+            Err(e) => return vec![format!("Unable to serialize game state: {e}")],
+        };
+
+        let mut diffs = Vec::new();
+        diff_values("", &self.end, &actual, &mut diffs);
+        if diffs.len() > MAX_REPORTED_DIFFERENCES {
+            let remaining = diffs.len() - MAX_REPORTED_DIFFERENCES;
+            diffs.truncate(MAX_REPORTED_DIFFERENCES);
+            diffs.push(format!("...and {remaining} more differences"));
+        }
+        diffs
+    }
+}
+
+fn diff_values(path: &str, expected: &Value, actual: &Value, diffs: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected_value) in expected.iter() {
+                let child = format!("{path}/{key}");
+                match actual.get(key) {
+                    None => diffs.push(format!("{child}: missing")),
+                    Some(actual_value) => diff_values(&child, expected_value, actual_value, diffs),
+                }
+            }
+
+            for key in actual.keys().filter(|key| !expected.contains_key(*key)) {
+                diffs.push(format!("{path}/{key}: unexpected"));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if expected.len() != actual.len() {
+                diffs.push(format!(
+                    "{path}: expected {} elements, found {}",
+                    expected.len(),
+                    actual.len()
+                ));
+                return;
+            }
+
+            for (index, (expected, actual)) in expected.iter().zip(actual.iter()).enumerate() {
+                diff_values(&format!("{path}/{index}"), expected, actual, diffs);
+            }
+        }
+        (expected, actual) => {
+            if expected != actual {
+                diffs.push(format!("{path}: expected {expected}, found {actual}"));
+            }
+        }
+    }
+}
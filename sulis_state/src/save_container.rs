@@ -0,0 +1,213 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! A compressed container for save files.  The container consists of a
+//! fixed header followed by the deflate compressed JSON of the save:
+//!
+//! | Bytes | Contents                                       |
+//! |-------|------------------------------------------------|
+//! | 8     | The magic bytes `SULISSAV`                     |
+//! | 8     | Length of the compressed data, little endian   |
+//! | 8     | Length of the uncompressed JSON, little endian |
+//! | 4     | CRC32 of the uncompressed JSON, little endian  |
+//!
+//! Files not starting with the magic bytes are read as plain JSON, so saves
+//! in either format may be loaded.
+
+use std::fs::{self, File};
+use std::io::{Error, Read, Write};
+use std::path::Path;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use sulis_core::util::invalid_data_error;
+
+const MAGIC: &[u8; 8] = b"SULISSAV";
+const HEADER_LEN: usize = 28;
+
+/// The file extension used for compressed saves
+pub const COMPRESSED_EXTENSION: &str = "sav";
+
+/// The file extension used for plain JSON saves
+pub const JSON_EXTENSION: &str = "json";
+
+/// Returns true if the specified file has an extension used by save files
+pub fn is_save_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext == COMPRESSED_EXTENSION || ext == JSON_EXTENSION)
+}
+
+/// Writes the specified JSON data to the file at `path` inside a compressed
+/// and checksummed container.
+pub fn write_compressed(path: &Path, json: &[u8]) -> Result<(), Error> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(json)?;
+    let compressed = encoder.finish()?;
+
+    let mut file = File::create(path)?;
+    file.write_all(MAGIC)?;
+    file.write_all(&(compressed.len() as u64).to_le_bytes())?;
+    file.write_all(&(json.len() as u64).to_le_bytes())?;
+    file.write_all(&crc32fast::hash(json).to_le_bytes())?;
+    file.write_all(&compressed)?;
+    file.sync_all()
+}
+
+/// Reads the JSON data of the save file at `path`, which may be either plain
+/// JSON or a compressed container.  Compressed data is checked against its
+/// stored length and checksum, and an error describing the save as truncated
+/// or corrupted is returned if either does not match.
+pub fn read(path: &Path) -> Result<String, Error> {
+    let data = fs::read(path)?;
+
+    if !data.starts_with(MAGIC) {
+        return String::from_utf8(data).map_err(|e| invalid_data_error(&format!("{e}")));
+    }
+
+    if data.len() < HEADER_LEN {
+        return Err(truncated_error());
+    }
+
+    let compressed_len = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let len = u64::from_le_bytes(data[16..24].try_into().unwrap());
+    let checksum = u32::from_le_bytes(data[24..28].try_into().unwrap());
+
+    let compressed = &data[HEADER_LEN..];
+    if (compressed.len() as u64) < compressed_len {
+        return Err(truncated_error());
+    }
+
+    let mut json = Vec::new();
+    let mut decoder = DeflateDecoder::new(compressed).take(len.saturating_add(1));
+    if let Err(e) = decoder.read_to_end(&mut json) {
+        return Err(invalid_data_error(&format!("Save file is corrupted: {e}")));
+    }
+
+    if json.len() as u64 != len {
+        return Err(invalid_data_error(
+            "Save file is corrupted: data length does not match",
+        ));
+    }
+
+    if crc32fast::hash(&json) != checksum {
+        return Err(invalid_data_error(
+            "Save file is corrupted: checksum does not match",
+        ));
+    }
+
+    String::from_utf8(json).map_err(|e| invalid_data_error(&format!("{e}")))
+}
+
+fn truncated_error() -> Error {
+    invalid_data_error("Save file is truncated")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const JSON: &str = r#"{"meta":{"player_name":"hero"},"state":{"party":[1,2,3]}}"#;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "sulis_save_container_{}_{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    fn write_and_modify(name: &str, modify: impl FnOnce(&mut Vec<u8>)) -> Result<String, Error> {
+        let path = temp_path(name);
+        write_compressed(&path, JSON.as_bytes()).unwrap();
+
+        let mut data = fs::read(&path).unwrap();
+        modify(&mut data);
+        fs::write(&path, data).unwrap();
+
+        let result = read(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    fn assert_err_contains(result: Result<String, Error>, text: &str) {
+        match result {
+            Ok(_) => panic!("expected an error containing '{text}'"),
+            Err(e) => assert!(e.to_string().contains(text), "unexpected error '{e}'"),
+        }
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let path = temp_path("round_trip");
+        write_compressed(&path, JSON.as_bytes()).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert!(data.starts_with(MAGIC));
+
+        let result = read(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap(), JSON);
+    }
+
+    #[test]
+    fn reads_uncompressed_json() {
+        let path = temp_path("legacy");
+        fs::write(&path, JSON).unwrap();
+
+        let result = read(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap(), JSON);
+    }
+
+    #[test]
+    fn truncated_header() {
+        let result = write_and_modify("truncated_header", |data| data.truncate(HEADER_LEN - 4));
+        assert_err_contains(result, "truncated");
+    }
+
+    #[test]
+    fn truncated_body() {
+        let result = write_and_modify("truncated_body", |data| {
+            let len = data.len();
+            data.truncate(len - 4);
+        });
+        assert_err_contains(result, "truncated");
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        let result = write_and_modify("checksum", |data| data[24] ^= 0xff);
+        assert_err_contains(result, "checksum does not match");
+    }
+
+    #[test]
+    fn length_mismatch() {
+        let result = write_and_modify("length", |data| data[16] ^= 0x01);
+        assert_err_contains(result, "length does not match");
+    }
+
+    #[test]
+    fn corrupted_body() {
+        let result = write_and_modify("corrupted_body", |data| data[HEADER_LEN + 2] ^= 0xff);
+        assert_err_contains(result, "corrupted");
+    }
+}
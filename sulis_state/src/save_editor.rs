@@ -0,0 +1,418 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Inspection, validation, and editing of save files outside of a running
+//! game.  The campaign the save belongs to must be loaded into the `Module`
+//! prior to using any of these methods, as references are resolved against
+//! it.
+
+use std::collections::HashMap;
+use std::io::Error;
+
+use serde::Serialize;
+
+use sulis_core::util::invalid_data_error;
+use sulis_module::{
+    on_trigger::QuestEntryState, ItemListEntrySaveState, ItemSaveState, Module, Time,
+};
+
+use crate::save_state::{AreaSaveState, EntitySaveState, PropInteractiveSaveState, QuestSaveState};
+use crate::{QuestStateSet, SaveFile, SaveManifest};
+
+/// A summary of the contents of a save file
+#[derive(Serialize, Debug)]
+pub struct SaveSummary {
+    pub player_name: String,
+    pub datetime: String,
+    pub format_version: u32,
+    pub autosave: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<SaveManifest>,
+    pub current_area: String,
+    pub coins: i32,
+    pub total_elapsed_millis: usize,
+    pub time: Time,
+    pub party: Vec<PartyMemberSummary>,
+    pub quests: Vec<QuestSummary>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PartyMemberSummary {
+    pub unique_id: String,
+    pub actor_id: String,
+    pub name: String,
+    pub level: u32,
+    pub hp: i32,
+    pub xp: u32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct QuestSummary {
+    pub id: String,
+    pub state: QuestEntryState,
+    pub entries: Vec<(String, QuestEntryState)>,
+}
+
+impl SaveFile {
+    pub fn summary(&self) -> SaveSummary {
+        let meta = self.meta();
+        let state = self.state();
+
+        let party = state
+            .party
+            .iter()
+            .filter_map(|index| find_entity(&state.manager.entities, *index))
+            .map(|entity| {
+                let (name, level) = match (&entity.actor_base, Module::actor(&entity.actor.id)) {
+                    (Some(base), _) => (base.name.to_string(), base.levels.values().sum()),
+                    (None, Some(actor)) => (actor.name.to_string(), actor.total_level),
+                    (None, None) => (entity.actor.id.to_string(), 0),
+                };
+
+                PartyMemberSummary {
+                    unique_id: entity.unique_id.to_string(),
+                    actor_id: entity.actor.id.to_string(),
+                    name,
+                    level,
+                    hp: entity.actor.p_stats.hp(),
+                    xp: entity.actor.p_stats.xp(),
+                }
+            })
+            .collect();
+
+        let mut quests: Vec<_> = state
+            .quests
+            .quests
+            .iter()
+            .map(|quest| QuestSummary {
+                id: quest.id().to_string(),
+                state: quest.state(),
+                entries: quest.iter().cloned().collect(),
+            })
+            .collect();
+        quests.sort_by(|a, b| a.id.cmp(&b.id));
+
+        SaveSummary {
+            player_name: meta.player_name.to_string(),
+            datetime: meta.datetime.to_string(),
+            format_version: meta.format_version,
+            autosave: meta.autosave,
+            manifest: meta.manifest.clone(),
+            current_area: state.current_area.to_string(),
+            coins: state.coins,
+            total_elapsed_millis: state.total_elapsed_millis,
+            time: Module::rules().compute_time(state.total_elapsed_millis),
+            party,
+            quests,
+        }
+    }
+
+    /// Checks that every actor, item, area, and other resource referenced by
+    /// this save exists in the currently loaded module.  Returns a
+    /// description of each problem found, which will be empty if the save
+    /// is valid.
+    pub fn validate(&self) -> Vec<String> {
+        let state = self.state();
+        let mut errors = Vec::new();
+
+        if let Some(error) = self.meta().check_manifest().error {
+            errors.push(error);
+        }
+
+        if !state.areas.contains_key(&state.current_area) {
+            errors.push(format!(
+                "Current area '{}' is not present in the save",
+                state.current_area
+            ));
+        }
+
+        for (id, area) in state.areas.iter() {
+            if Module::area(id).is_none() {
+                errors.push(format!("Area '{id}' does not exist"));
+            }
+
+            for prop in area.props.iter() {
+                let context = format!("Area '{id}' prop '{}'", prop.id);
+                if Module::prop(&prop.id).is_none() {
+                    errors.push(format!("{context}: prop does not exist"));
+                }
+
+                if let PropInteractiveSaveState::Container {
+                    items,
+                    loot_to_generate,
+                    ..
+                } = &prop.interactive
+                {
+                    validate_item_list(&context, items, &mut errors);
+                    if let Some(loot) = loot_to_generate {
+                        if Module::loot_list(loot).is_none() {
+                            errors.push(format!("{context}: loot list '{loot}' does not exist"));
+                        }
+                    }
+                }
+            }
+
+            for merchant in area.merchants.iter() {
+                let context = format!("Area '{id}' merchant '{}'", merchant.id);
+                validate_item_list(&context, &merchant.items, &mut errors);
+                if let Some(loot) = &merchant.loot_list_id {
+                    if Module::loot_list(loot).is_none() {
+                        errors.push(format!("{context}: loot list '{loot}' does not exist"));
+                    }
+                }
+            }
+        }
+
+        let entities = &state.manager.entities;
+        for index in state.party.iter().chain(state.selected.iter()) {
+            if find_entity(entities, *index).is_none() {
+                errors.push(format!("Party member {index} is not a valid entity"));
+            }
+        }
+
+        for entity in entities.iter() {
+            validate_entity(entity, &state.areas, &mut errors);
+        }
+
+        for effect in state.manager.effects.iter() {
+            if let Some(index) = effect.entity {
+                if find_entity(entities, index).is_none() {
+                    errors.push(format!(
+                        "Effect '{}' is applied to invalid entity {index}",
+                        effect.name
+                    ));
+                }
+            }
+        }
+
+        validate_item_list("Stash", &state.stash, &mut errors);
+
+        for quest in state.quests.quests.iter() {
+            let module_quest = match Module::quest(quest.id()) {
+                None => {
+                    errors.push(format!("Quest '{}' does not exist", quest.id()));
+                    continue;
+                }
+                Some(quest) => quest,
+            };
+
+            for (entry, _) in quest.iter() {
+                if !module_quest.entries.contains_key(entry) {
+                    errors.push(format!(
+                        "Quest '{}' entry '{entry}' does not exist",
+                        quest.id()
+                    ));
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Sets a custom flag on the entity with the specified unique ID, or on
+    /// the player character if no ID is specified
+    pub fn set_flag(&mut self, entity: Option<&str>, flag: &str, value: &str) -> Result<(), Error> {
+        let state = self.state_mut();
+
+        let entity = match entity {
+            Some(id) => state
+                .manager
+                .entities
+                .iter_mut()
+                .find(|entity| entity.unique_id == id)
+                .ok_or_else(|| invalid_data_error(&format!("Entity '{id}' not found")))?,
+            None => {
+                let index = *state
+                    .party
+                    .first()
+                    .ok_or_else(|| invalid_data_error("Save has no player character"))?;
+                state
+                    .manager
+                    .entities
+                    .iter_mut()
+                    .find(|entity| entity.index == index)
+                    .ok_or_else(|| invalid_data_error("Player character entity not found"))?
+            }
+        };
+
+        entity
+            .custom_flags
+            .insert(flag.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// Adds the specified quantity of the item to the party stash
+    pub fn give_item(&mut self, item_id: &str, quantity: u32) -> Result<(), Error> {
+        if Module::item(item_id).is_none() {
+            return Err(invalid_data_error(&format!(
+                "Item '{item_id}' does not exist"
+            )));
+        }
+
+        let stash = &mut self.state_mut().stash;
+        let existing = stash.iter_mut().find(|entry| {
+            entry.item.id == item_id
+                && entry.item.adjectives.is_empty()
+                && entry.item.variant.is_none()
+        });
+
+        match existing {
+            Some(entry) => entry.quantity += quantity,
+            None => stash.push(ItemListEntrySaveState {
+                quantity,
+                item: ItemSaveState {
+                    id: item_id.to_string(),
+                    adjectives: Vec::new(),
+                    variant: None,
+                },
+            }),
+        }
+
+        Ok(())
+    }
+
+    /// Sets the state of the specified quest, or of one of its entries
+    pub fn set_quest_state(
+        &mut self,
+        quest_id: &str,
+        entry: Option<&str>,
+        quest_state: QuestEntryState,
+    ) -> Result<(), Error> {
+        let quest = Module::quest(quest_id)
+            .ok_or_else(|| invalid_data_error(&format!("Quest '{quest_id}' does not exist")))?;
+
+        if let Some(entry) = entry {
+            if !quest.entries.contains_key(entry) {
+                return Err(invalid_data_error(&format!(
+                    "Quest '{quest_id}' entry '{entry}' does not exist"
+                )));
+            }
+        }
+
+        let state = self.state_mut();
+        let data = std::mem::replace(
+            &mut state.quests,
+            QuestSaveState {
+                quests: Vec::new(),
+                current_quest: Vec::new(),
+            },
+        );
+
+        let mut quests = QuestStateSet::load(data);
+        match entry {
+            None => quests.set_state(quest_id, quest_state),
+            Some(entry) => quests.set_entry_state(quest_id, entry, quest_state),
+        }
+
+        let current_quest = quests.current_quest_stack();
+        state.quests = QuestSaveState {
+            quests: quests.quests_iter().map(|(_, quest)| quest).collect(),
+            current_quest,
+        };
+
+        Ok(())
+    }
+}
+
+fn find_entity(entities: &[EntitySaveState], index: usize) -> Option<&EntitySaveState> {
+    entities.iter().find(|entity| entity.index == index)
+}
+
+fn validate_entity(
+    entity: &EntitySaveState,
+    areas: &HashMap<String, AreaSaveState>,
+    errors: &mut Vec<String>,
+) {
+    let context = format!("Entity '{}'", entity.unique_id);
+
+    match &entity.actor_base {
+        None => {
+            if Module::actor(&entity.actor.id).is_none() {
+                errors.push(format!(
+                    "{context}: actor '{}' does not exist",
+                    entity.actor.id
+                ));
+            }
+        }
+        Some(base) => {
+            if let Some(race) = &base.race {
+                if Module::race(race).is_none() {
+                    errors.push(format!("{context}: race '{race}' does not exist"));
+                }
+            }
+
+            for class in base.levels.keys() {
+                if Module::class(class).is_none() {
+                    errors.push(format!("{context}: class '{class}' does not exist"));
+                }
+            }
+
+            for ability in base.abilities.iter() {
+                if Module::ability(ability).is_none() {
+                    errors.push(format!("{context}: ability '{ability}' does not exist"));
+                }
+            }
+        }
+    }
+
+    if !areas.contains_key(&entity.location.area) {
+        errors.push(format!(
+            "{context}: located in area '{}', which is not present in the save",
+            entity.location.area
+        ));
+    }
+
+    if Module::size(&entity.size).is_none() {
+        errors.push(format!("{context}: size '{}' does not exist", entity.size));
+    }
+
+    let items = entity
+        .actor
+        .equipped
+        .iter()
+        .chain(entity.actor.quick.iter())
+        .flatten();
+    for item in items {
+        validate_item(&context, item, errors);
+    }
+
+    for ability in entity.actor.ability_states.keys() {
+        if Module::ability(ability).is_none() {
+            errors.push(format!("{context}: ability '{ability}' does not exist"));
+        }
+    }
+}
+
+fn validate_item_list(context: &str, items: &[ItemListEntrySaveState], errors: &mut Vec<String>) {
+    for entry in items {
+        validate_item(context, &entry.item, errors);
+    }
+}
+
+fn validate_item(context: &str, item: &ItemSaveState, errors: &mut Vec<String>) {
+    if Module::item(&item.id).is_none() {
+        errors.push(format!("{context}: item '{}' does not exist", item.id));
+    }
+
+    for adjective in item.adjectives.iter() {
+        if Module::item_adjective(adjective).is_none() {
+            errors.push(format!(
+                "{context}: item adjective '{adjective}' does not exist"
+            ));
+        }
+    }
+}
@@ -14,8 +14,9 @@
 //  You should have received a copy of the GNU General Public License
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
-use std::fs::{self, File};
-use std::io::{Error, Read};
+use std::cell::RefCell;
+use std::fs;
+use std::io::Error;
 use std::path::{Path, PathBuf};
 use std::time;
 
@@ -23,12 +24,35 @@ use serde::{Deserialize, Serialize};
 
 use chrono::prelude::*;
 
+use crate::save_container;
+use crate::save_migration::{self, SAVE_FORMAT_VERSION};
 use crate::{GameState, SaveState};
-use sulis_core::resource::{read_single_resource_path, write_json_to_file};
+use sulis_core::config::{self, Config};
+use sulis_core::resource::write_json_to_file;
 use sulis_core::util::invalid_data_error;
-use sulis_core::{config, serde_json, util};
+use sulis_core::{serde_json, util};
 use sulis_module::Module;
 
+const AUTOSAVE_PREFIX: &str = "autosave_";
+
+thread_local! {
+    static AUTOSAVE: RefCell<AutosaveState> = RefCell::new(AutosaveState::default());
+}
+
+/// The game events that may cause an autosave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutosaveTrigger {
+    AreaTransition,
+    CombatEnd,
+    Interval,
+}
+
+#[derive(Default)]
+struct AutosaveState {
+    pending: Option<AutosaveTrigger>,
+    elapsed_millis: u32,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SaveFile {
@@ -38,13 +62,57 @@ pub struct SaveFile {
 
 impl SaveFile {
     fn from_json(data: &str) -> Result<Self, Error> {
-        serde_json::from_str(data).map_err(|e| invalid_data_error(&format!("{e}")))
+        let mut value: serde_json::Value =
+            serde_json::from_str(data).map_err(|e| invalid_data_error(&format!("{e}")))?;
+        save_migration::migrate(&mut value)?;
+        serde_json::from_value(value).map_err(|e| invalid_data_error(&format!("{e}")))
+    }
+
+    /// Reads the save file at the specified path, in either the compressed
+    /// or the plain JSON format, migrating it to the current format version
+    pub fn read(path: &Path) -> Result<SaveFile, Error> {
+        let file_data = save_container::read(path)?;
+
+        let mut save_file = SaveFile::from_json(&file_data)?;
+        save_file.meta.path = path.to_path_buf();
+        Ok(save_file)
+    }
+
+    /// Writes this save file to the specified path.  The file is compressed
+    /// if the path has the compressed save extension, and is otherwise
+    /// written as plain JSON.
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        let compress = path
+            .extension()
+            .is_some_and(|ext| ext == save_container::COMPRESSED_EXTENSION);
+
+        if compress {
+            serde_json::to_vec(self)
+                .map_err(|e| invalid_data_error(&format!("{e}")))
+                .and_then(|json| save_container::write_compressed(path, &json))
+        } else {
+            write_json_to_file(path, self)
+        }
+    }
+
+    pub fn meta(&self) -> &SaveFileMetaData {
+        &self.meta
+    }
+
+    pub fn state(&self) -> &SaveState {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut SaveState {
+        &mut self.state
     }
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SaveFileMetaData {
+    pub format_version: u32,
+
     pub player_name: String,
 
     pub level: Option<u32>,
@@ -53,6 +121,12 @@ pub struct SaveFileMetaData {
     pub datetime: String,
     pub current_area_name: String,
 
+    pub autosave: bool,
+
+    /// The campaign and mods the save was made with.  This is not known for
+    /// saves from before format version 3.
+    pub manifest: Option<SaveManifest>,
+
     #[serde(skip)]
     path: PathBuf,
 
@@ -60,9 +134,118 @@ pub struct SaveFileMetaData {
     pub error: Option<String>,
 }
 
+impl SaveFileMetaData {
+    /// The path this save was read from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Compares the manifest of this save against the currently loaded
+    /// campaign and mods
+    pub fn check_manifest(&self) -> ManifestCheck {
+        match &self.manifest {
+            None => ManifestCheck::default(),
+            Some(manifest) => manifest.check(),
+        }
+    }
+}
+
+/// The campaign and mods that were active when a save was made
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SaveManifest {
+    pub campaign: String,
+    pub mods: Vec<ModManifest>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ModManifest {
+    pub id: String,
+    pub version: String,
+}
+
+impl SaveManifest {
+    /// Creates a manifest of the currently loaded campaign and mods
+    pub fn current() -> SaveManifest {
+        let mods = Module::active_mods()
+            .into_iter()
+            .map(|modif| ModManifest {
+                id: modif.id,
+                version: modif.version,
+            })
+            .collect();
+
+        SaveManifest {
+            campaign: Module::campaign().id.to_string(),
+            mods,
+        }
+    }
+
+    /// Compares this manifest against the currently loaded campaign and mods.
+    /// A save from a different campaign may not be loaded, while differences
+    /// in the active mods produce warnings.
+    pub fn check(&self) -> ManifestCheck {
+        let current = SaveManifest::current();
+        let mut check = ManifestCheck::default();
+
+        if self.campaign != current.campaign {
+            check.error = Some(format!(
+                "Save is from campaign '{}', not '{}'",
+                self.campaign, current.campaign
+            ));
+            return check;
+        }
+
+        for modif in self.mods.iter() {
+            match current.mods.iter().find(|m| m.id == modif.id) {
+                None => check
+                    .warnings
+                    .push(format!("Mod '{}' is not active", modif.id)),
+                Some(active) if active.version != modif.version => check.warnings.push(format!(
+                    "Mod '{}' is version '{}', but the save used '{}'",
+                    modif.id, active.version, modif.version
+                )),
+                Some(_) => (),
+            }
+        }
+
+        for modif in current.mods.iter() {
+            if !self.mods.iter().any(|m| m.id == modif.id) {
+                check
+                    .warnings
+                    .push(format!("Mod '{}' was not active in the save", modif.id));
+            }
+        }
+
+        // compare the order of the mods present in both
+        let shared_order = |mods: &[ModManifest], other: &[ModManifest]| -> Vec<String> {
+            mods.iter()
+                .filter(|m| other.contains(m))
+                .map(|m| m.id.to_string())
+                .collect()
+        };
+        if shared_order(&self.mods, &current.mods) != shared_order(&current.mods, &self.mods) {
+            check
+                .warnings
+                .push("Mods are loaded in a different order than in the save".to_string());
+        }
+
+        check
+    }
+}
+
+/// The result of comparing a save's manifest against the currently loaded
+/// campaign and mods
+#[derive(Debug, Clone, Default)]
+pub struct ManifestCheck {
+    /// Set if the save may not be loaded
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
 fn get_save_dir() -> PathBuf {
-    let mut path = config::USER_DIR.clone();
-    path.push("save");
+    let mut path = save_root_dir();
     path.push(&Module::campaign().id);
     path
 }
@@ -74,17 +257,80 @@ pub fn delete_save(save_file: &SaveFileMetaData) -> Result<(), Error> {
 
 pub fn load_state(save_file: &SaveFileMetaData) -> Result<SaveState, Error> {
     let path = save_file.path.as_path();
-    let save_file: SaveFile = read_single_resource_path(path)?;
+    let save_file = SaveFile::read(path)?;
+
+    Ok(save_file.state)
+}
+
+/// Loads the save state stored in the save file at the specified path, which
+/// need not be in the current campaign's save directory
+pub fn load_state_from_path(path: &Path) -> Result<SaveState, Error> {
+    let save_file = SaveFile::read(path)?;
 
     Ok(save_file.state)
 }
 
 pub fn create_save() -> Result<(), Error> {
+    let utc = Utc::now();
+    let name = format!("save_{}", utc.format("%Y%m%d-%H%M%S%.3f"));
+    write_save(&name, utc, false)
+}
+
+/// Writes an autosave into the next autosave slot.  Slots are used in
+/// rotation, with the oldest slot being overwritten once all are in use.
+pub fn create_autosave() -> Result<(), Error> {
+    let slot = next_autosave_slot();
+    info!("Autosaving to slot {}", slot);
+    write_save(&format!("{AUTOSAVE_PREFIX}{slot}"), Utc::now(), true)
+}
+
+fn next_autosave_slot() -> u32 {
+    let dir = get_save_dir();
+    let slots = Config::saves_config().autosave_slots.max(1);
+
+    let mut oldest: Option<(u32, time::SystemTime)> = None;
+    for slot in 0..slots {
+        let modified = [
+            save_container::COMPRESSED_EXTENSION,
+            save_container::JSON_EXTENSION,
+        ]
+        .iter()
+        .filter_map(|ext| {
+            let path = dir.join(format!("{AUTOSAVE_PREFIX}{slot}.{ext}"));
+            fs::metadata(path).and_then(|m| m.modified()).ok()
+        })
+        .max();
+
+        let modified = match modified {
+            None => return slot,
+            Some(modified) => modified,
+        };
+
+        match oldest {
+            Some((_, oldest_time)) if oldest_time <= modified => (),
+            _ => oldest = Some((slot, modified)),
+        }
+    }
+
+    oldest.map_or(0, |(slot, _)| slot)
+}
+
+fn write_save(name: &str, utc: DateTime<Utc>, autosave: bool) -> Result<(), Error> {
     let start_time = time::Instant::now();
     info!("Start save");
 
-    let utc = Utc::now();
-    let filename = format!("save_{}.json", utc.format("%Y%m%d-%H%M%S%.3f"));
+    let compress = Config::saves_config().compress;
+    let (extension, other_extension) = if compress {
+        (
+            save_container::COMPRESSED_EXTENSION,
+            save_container::JSON_EXTENSION,
+        )
+    } else {
+        (
+            save_container::JSON_EXTENSION,
+            save_container::COMPRESSED_EXTENSION,
+        )
+    };
 
     let mut path = get_save_dir();
     if !path.is_dir() {
@@ -92,9 +338,10 @@ pub fn create_save() -> Result<(), Error> {
         fs::create_dir_all(path.clone())?;
     }
 
-    path.push(filename);
+    path.push(format!("{name}.{extension}"));
 
-    let meta = create_meta_data(utc.format("%c").to_string());
+    let mut meta = create_meta_data(utc.format("%c").to_string());
+    meta.autosave = autosave;
 
     info!(
         "  Filename and meta data creation complete in {} secs",
@@ -110,7 +357,13 @@ pub fn create_save() -> Result<(), Error> {
         util::format_elapsed_secs(start_time.elapsed())
     );
 
-    let result = write_json_to_file(path.as_path(), &save);
+    let result = save.write(&path);
+
+    // an autosave slot previously written in the other format is now stale
+    let stale = path.with_extension(other_extension);
+    if result.is_ok() && autosave && stale.is_file() {
+        fs::remove_file(stale)?;
+    }
 
     info!(
         "  Save to disk complete in {} secs",
@@ -120,6 +373,71 @@ pub fn create_save() -> Result<(), Error> {
     result
 }
 
+/// Requests that an autosave be made, if autosaves are enabled for the
+/// specified `trigger`.  The save is not written immediately, but rather
+/// at the next point where it is safe to do so, outside of combat and with
+/// no blocking animations running.
+pub fn request_autosave(trigger: AutosaveTrigger) {
+    let config = Config::saves_config();
+    let enabled = config.autosave_slots > 0
+        && match trigger {
+            AutosaveTrigger::AreaTransition => config.autosave_on_transition,
+            AutosaveTrigger::CombatEnd => config.autosave_on_combat_end,
+            AutosaveTrigger::Interval => config.autosave_interval_minutes > 0,
+        };
+
+    if !enabled {
+        return;
+    }
+
+    debug!("Requesting autosave on {:?}", trigger);
+    AUTOSAVE.with(|a| {
+        let mut autosave = a.borrow_mut();
+        if autosave.pending.is_none() {
+            autosave.pending = Some(trigger);
+        }
+    });
+}
+
+/// Clears any pending autosave and restarts the autosave interval
+pub fn reset_autosave() {
+    AUTOSAVE.with(|a| *a.borrow_mut() = AutosaveState::default());
+}
+
+pub(crate) fn update_autosave(millis: u32) {
+    let interval_millis = Config::saves_config()
+        .autosave_interval_minutes
+        .saturating_mul(60_000);
+
+    let elapsed_millis = AUTOSAVE.with(|a| {
+        let mut autosave = a.borrow_mut();
+        autosave.elapsed_millis = autosave.elapsed_millis.saturating_add(millis);
+        autosave.elapsed_millis
+    });
+
+    if interval_millis > 0 && elapsed_millis >= interval_millis {
+        request_autosave(AutosaveTrigger::Interval);
+    }
+
+    if !AUTOSAVE.with(|a| a.borrow().pending.is_some()) {
+        return;
+    }
+
+    if GameState::is_combat_active()
+        || GameState::is_modal_locked()
+        || GameState::has_any_blocking_animations()
+        || GameState::player().borrow().actor.is_dead()
+    {
+        return;
+    }
+
+    reset_autosave();
+    if let Err(e) = create_autosave() {
+        error!("Error autosaving game");
+        error!("{}", e);
+    }
+}
+
 fn create_meta_data(datetime: String) -> SaveFileMetaData {
     let cur_area = GameState::area_state();
     let cur_area = cur_area.borrow();
@@ -127,11 +445,14 @@ fn create_meta_data(datetime: String) -> SaveFileMetaData {
     let player = player.borrow();
 
     SaveFileMetaData {
+        format_version: SAVE_FORMAT_VERSION,
         player_name: player.actor.actor.name.to_string(),
         level: Some(player.actor.actor.total_level),
         class: Some(player.actor.actor.base_class().name.to_string()),
         datetime,
         current_area_name: cur_area.area.area.name.to_string(),
+        autosave: false,
+        manifest: Some(SaveManifest::current()),
         path: Default::default(),
         error: None,
     }
@@ -146,18 +467,9 @@ pub fn has_available_save_files() -> bool {
     fs::read_dir(dir).map_or(false, |entries| {
         entries
             .filter_map(Result::ok)
-            .filter(|entry| entry.path().is_file())
-            .any(|entry| entry.path().extension().map_or(false, |ext| ext == "json"))
+            .any(|entry| save_container::is_save_file(&entry.path()))
     })
 }
-fn read_save_file(path: &Path) -> Result<SaveFile, Error> {
-    let mut file = File::open(path)?;
-
-    let mut file_data = String::new();
-    file.read_to_string(&mut file_data)?;
-
-    SaveFile::from_json(&file_data)
-}
 
 fn create_error_meta(path: PathBuf, error: Error) -> SaveFileMetaData {
     let time = match fs::metadata(&path) {
@@ -182,11 +494,14 @@ fn create_error_meta(path: PathBuf, error: Error) -> SaveFileMetaData {
     let datetime = time.format("%c").to_string();
 
     SaveFileMetaData {
+        format_version: 0,
         player_name: "Unknown Player".to_string(),
         level: None,
         class: None,
         datetime,
         current_area_name: "Unknown Area".to_string(),
+        autosave: false,
+        manifest: None,
         path,
         error: Some(error.to_string()),
     }
@@ -194,22 +509,32 @@ fn create_error_meta(path: PathBuf, error: Error) -> SaveFileMetaData {
 
 pub fn get_available_save_files() -> Result<Vec<SaveFileMetaData>, Error> {
     let dir = get_save_dir();
-    debug!("Reading save games from {}", dir.to_string_lossy());
 
     fs::create_dir_all(&dir)?;
 
+    get_save_files_in(&dir)
+}
+
+/// Returns the root directory containing the save directory of each campaign
+pub fn save_root_dir() -> PathBuf {
+    let mut path = config::USER_DIR.clone();
+    path.push("save");
+    path
+}
+
+/// Reads the meta data of every save file in the specified directory, most
+/// recently modified first.  Save files that cannot be read are included,
+/// with their `error` set.
+pub fn get_save_files_in(dir: &Path) -> Result<Vec<SaveFileMetaData>, Error> {
+    debug!("Reading save games from {}", dir.to_string_lossy());
+
     let results: Vec<SaveFileMetaData> = fs::read_dir(dir)?
         .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.path().is_file())
-        .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "json"))
+        .filter(|entry| save_container::is_save_file(&entry.path()))
         .map(|entry| {
             let path_buf = entry.path();
-            match read_save_file(&path_buf) {
-                Ok(save_file) => {
-                    let mut meta = save_file.meta;
-                    meta.path = path_buf;
-                    Ok::<SaveFileMetaData, Error>(meta)
-                }
+            match SaveFile::read(&path_buf) {
+                Ok(save_file) => Ok::<SaveFileMetaData, Error>(save_file.meta),
                 Err(e) => {
                     warn!("Unable to read save file: {}", path_buf.to_string_lossy());
                     warn!("{}", e);
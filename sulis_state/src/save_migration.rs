@@ -0,0 +1,233 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Migration of save files from older format versions.  Whenever a change is
+//! made to the layout of `SaveState` or `SaveFileMetaData` that older saves
+//! will not deserialize into, `SAVE_FORMAT_VERSION` should be incremented and
+//! a migration function added to `MIGRATIONS`, transforming the raw JSON of
+//! a save from the previous version to the new one.
+
+use std::io::Error;
+
+use sulis_core::serde_json::{self, Map, Value};
+use sulis_core::util::{invalid_data_error, ReproducibleRandom};
+
+/// The format version of save files written by this build.  Save files
+/// written before versioning was introduced have no version, and are
+/// treated as version 0.
+pub const SAVE_FORMAT_VERSION: u32 = 3;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), Error>;
+
+/// The migration at each index transforms a save from that format version
+/// to the next version.  Each migration receives the top level save file
+/// object, containing both the `meta` and the `state`.
+const MIGRATIONS: [Migration; SAVE_FORMAT_VERSION as usize] =
+    [add_random_state, add_autosave_flag, add_manifest];
+
+/// Reads the format version of the specified save file JSON
+pub fn format_version(save: &Value) -> Result<u32, Error> {
+    match save.get("meta").and_then(|meta| meta.get("format_version")) {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| invalid_data_error(&format!("Invalid save format version {version}"))),
+    }
+}
+
+/// Migrates the specified save file JSON from its format version to the
+/// current `SAVE_FORMAT_VERSION`, one version at a time.  Returns an error
+/// if the save is from a newer build than this one, or if any migration
+/// fails.
+pub fn migrate(save: &mut Value) -> Result<(), Error> {
+    let version = format_version(save)?;
+
+    if version > SAVE_FORMAT_VERSION {
+        return Err(invalid_data_error(&format!(
+            "Save format version {version} is newer than the latest version supported by \
+            this build, {SAVE_FORMAT_VERSION}.  Please update Sulis to load this save."
+        )));
+    }
+
+    if version == SAVE_FORMAT_VERSION {
+        return Ok(());
+    }
+
+    let save = save
+        .as_object_mut()
+        .ok_or_else(|| invalid_data_error("Save file must be a JSON object"))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        debug!(
+            "Migrating save from format version {} to {}",
+            index,
+            index + 1
+        );
+        migration(save).map_err(|e| {
+            invalid_data_error(&format!(
+                "Unable to migrate save from format version {} to {}: {}",
+                index,
+                index + 1,
+                e
+            ))
+        })?;
+    }
+
+    let meta = get_object(save, "meta")?;
+    meta.insert("format_version".to_string(), SAVE_FORMAT_VERSION.into());
+
+    info!(
+        "Migrated save from format version {} to {}",
+        version, SAVE_FORMAT_VERSION
+    );
+
+    Ok(())
+}
+
+fn get_object<'a>(
+    parent: &'a mut Map<String, Value>,
+    key: &str,
+) -> Result<&'a mut Map<String, Value>, Error> {
+    parent
+        .get_mut(key)
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| invalid_data_error(&format!("Save file has no '{key}' object")))
+}
+
+/// Version 1 added the state of the gameplay random number generator.
+/// Older saves are given a newly seeded generator.
+fn add_random_state(save: &mut Map<String, Value>) -> Result<(), Error> {
+    let state = get_object(save, "state")?;
+    if state.contains_key("random") {
+        return Ok(());
+    }
+
+    let random = serde_json::to_value(ReproducibleRandom::new(None))
+        .map_err(|e| invalid_data_error(&e.to_string()))?;
+    state.insert("random".to_string(), random);
+    Ok(())
+}
+
+/// Version 2 added the flag marking autosaves.  All older saves were made
+/// manually.
+fn add_autosave_flag(save: &mut Map<String, Value>) -> Result<(), Error> {
+    let meta = get_object(save, "meta")?;
+    meta.entry("autosave").or_insert(Value::Bool(false));
+    Ok(())
+}
+
+/// Version 3 added the manifest of the campaign and mods the save was made
+/// with.  This is unknown for older saves.
+fn add_manifest(save: &mut Map<String, Value>) -> Result<(), Error> {
+    let meta = get_object(save, "meta")?;
+    meta.entry("manifest").or_insert(Value::Null);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sulis_core::serde_json::json;
+
+    use super::*;
+
+    fn v0_save() -> Value {
+        json!({
+            "meta": { "player_name": "hero" },
+            "state": { "party": [] },
+        })
+    }
+
+    #[test]
+    fn unversioned_save_is_version_0() {
+        assert_eq!(format_version(&v0_save()).unwrap(), 0);
+    }
+
+    #[test]
+    fn invalid_version_is_rejected() {
+        let save = json!({ "meta": { "format_version": "one" }, "state": {} });
+        assert!(format_version(&save).is_err());
+
+        let save = json!({ "meta": { "format_version": -1 }, "state": {} });
+        assert!(format_version(&save).is_err());
+
+        // would truncate to a valid version if cast to u32
+        let version = u64::from(u32::MAX) + 2;
+        let save = json!({ "meta": { "format_version": version }, "state": {} });
+        assert!(format_version(&save).is_err());
+    }
+
+    #[test]
+    fn migrates_v0_to_current() {
+        let mut save = v0_save();
+        migrate(&mut save).unwrap();
+
+        assert_eq!(format_version(&save).unwrap(), SAVE_FORMAT_VERSION);
+        assert_eq!(SAVE_FORMAT_VERSION, 3);
+
+        // add_random_state
+        assert!(save["state"]["random"].is_object());
+        // add_autosave_flag
+        assert_eq!(save["meta"]["autosave"], Value::Bool(false));
+        // add_manifest
+        assert_eq!(save["meta"]["manifest"], Value::Null);
+
+        // existing data is untouched
+        assert_eq!(save["meta"]["player_name"], "hero");
+        assert_eq!(save["state"]["party"], json!([]));
+    }
+
+    #[test]
+    fn migration_starts_from_save_version() {
+        let mut save = json!({
+            "meta": { "format_version": 1, "manifest": { "campaign": "c" } },
+            "state": {},
+        });
+        migrate(&mut save).unwrap();
+
+        assert_eq!(format_version(&save).unwrap(), SAVE_FORMAT_VERSION);
+        // version 1 saves already have a random state, so none is added
+        assert!(save["state"].get("random").is_none());
+        assert_eq!(save["meta"]["autosave"], Value::Bool(false));
+        assert_eq!(save["meta"]["manifest"], json!({ "campaign": "c" }));
+    }
+
+    #[test]
+    fn current_version_is_unchanged() {
+        let mut save = json!({
+            "meta": { "format_version": SAVE_FORMAT_VERSION },
+            "state": {},
+        });
+        let expected = save.clone();
+        migrate(&mut save).unwrap();
+        assert_eq!(save, expected);
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let mut save = json!({
+            "meta": { "format_version": SAVE_FORMAT_VERSION + 1 },
+            "state": {},
+        });
+        assert!(migrate(&mut save).is_err());
+    }
+
+    #[test]
+    fn missing_state_is_rejected() {
+        let mut save = json!({ "meta": {} });
+        assert!(migrate(&mut save).is_err());
+    }
+}
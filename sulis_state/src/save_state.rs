@@ -21,7 +21,7 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
-use sulis_core::util::{ExtInt, Point};
+use sulis_core::util::{self, ExtInt, Point, ReproducibleRandom};
 use sulis_module::{
     actor::{ActorBuilder, RewardBuilder},
     BonusList, ItemListEntrySaveState, ItemSaveState, QuickSlot, Slot,
@@ -31,8 +31,9 @@ use crate::animation::AnimSaveState;
 use crate::area_state::TriggerState;
 use crate::script::CallbackData;
 use crate::{
-    effect, prop_state::Interactive, turn_manager::EncounterRef, ActorState, Effect, EntityState,
-    Formation, GameState, Location, MerchantState, PStats, PropState, QuestState, WorldMapState,
+    condition, effect, prop_state::Interactive, turn_manager::EncounterRef, ActorState, Effect,
+    EntityState, Formation, GameState, Location, MerchantState, PStats, PropState, QuestState,
+    WorldMapState,
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -55,6 +56,8 @@ pub struct SaveState {
 
     #[serde(default)]
     pub(crate) total_elapsed_millis: usize,
+
+    pub(crate) random: ReproducibleRandom,
 }
 
 fn default_zoom() -> f32 {
@@ -117,12 +120,19 @@ impl SaveState {
             world_map: GameState::world_map(),
             quests: quest_state,
             total_elapsed_millis,
+            random: util::get_random(),
         }
     }
 
     pub fn load(self) -> Result<(), Error> {
         GameState::load(self)
     }
+
+    /// Replaces the saved random number generator state with a newly seeded
+    /// generator, so the game plays out differently from this save.
+    pub fn reseed(&mut self, seed: u64) {
+        self.random = ReproducibleRandom::new(Some(seed as u128));
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -194,6 +204,21 @@ pub struct EffectSaveState {
 
     #[serde(default = "default_true")]
     pub(crate) ui_visible: bool,
+
+    #[serde(default)]
+    pub(crate) condition: Option<condition::ConditionState>,
+
+    #[serde(default)]
+    pub(crate) stacking: effect::EffectStacking,
+
+    #[serde(default)]
+    pub(crate) strength: f32,
+
+    #[serde(default)]
+    pub(crate) source: Option<usize>,
+
+    #[serde(default)]
+    pub(crate) categories: Vec<String>,
 }
 
 fn default_true() -> bool {
@@ -221,6 +246,11 @@ impl EffectSaveState {
             callbacks,
             icon: effect.icon.clone(),
             ui_visible: effect.ui_visible,
+            condition: effect.condition.clone(),
+            stacking: effect.stacking,
+            strength: effect.strength,
+            source: effect.source,
+            categories: effect.categories.clone(),
         }
     }
 }
@@ -490,6 +520,7 @@ impl EntitySaveState {
                 reward,
                 abilities,
                 ai,
+                immunities: actor.immunities.clone(),
             })
         } else {
             None
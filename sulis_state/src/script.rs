@@ -38,7 +38,9 @@
 //! --INCLUDE <other_script_id>
 //! The entire contents of the referenced script will be placed into the script file in place
 //! of the --INCLUDE directive at evaluation time.
-//! --INCLUDE directives are only evaluated one level deep at this time.
+//! Included scripts may themselves contain --INCLUDE directives.  Each script is only included
+//! once into a given script, and a script that would end up including itself is an error.
+//! Errors in included scripts are reported against the original script and line.
 
 mod area_targeter;
 pub use self::area_targeter::AreaTargeter;
@@ -51,18 +53,22 @@ pub use self::script_ability::{ScriptAbility, ScriptAbilitySet};
 
 pub mod script_cache;
 
+pub mod script_validator;
+
 pub mod script_callback;
 pub use self::script_callback::{
-    CallbackData, FuncKind, ScriptCallback, ScriptHitKind, TriggeredCallback,
+    CallbackData, FuncKind, ScriptCallback, ScriptHitKind, ScriptReaction, TriggeredCallback,
 };
 
 mod script_effect;
 pub use self::script_effect::{
     ScriptActiveSurface, ScriptAppliedEffect, ScriptEffect, ScriptMenuSelection,
+    ScriptResistedEffect,
 };
 
 mod script_entity;
 pub use self::script_entity::ScriptEntity;
+pub(crate) use self::script_entity::{get_on_activate_fn, move_towards_dest};
 
 mod script_entity_set;
 pub use self::script_entity_set::ScriptEntitySet;
@@ -104,8 +110,11 @@ use std::time;
 
 use rlua::{self, FromLuaMulti, Function, Lua, ToLuaMulti};
 
-use crate::{ai, EntityState, GameState};
-use sulis_core::{config::Config, util::Point};
+use crate::{ai, reaction, EntityState, GameState};
+use sulis_core::{
+    config::Config,
+    util::{gen_rand, Point},
+};
 use sulis_module::{Ability, DamageKind, HitKind, Module, QuickSlot};
 
 pub type Result<T> = std::result::Result<T, rlua::Error>;
@@ -232,6 +241,13 @@ impl Script {
     }
 
     pub fn ability_on_activate(parent: usize, func: String, ability: &Rc<Ability>) {
+        let entity = GameState::turn_manager().borrow().entity_checked(parent);
+        if let Some(entity) = entity {
+            if reaction::interrupt(&entity, ability) {
+                return;
+            }
+        }
+
         if let Err(e) = script_cache::ability_on_activate(parent, func, ability) {
             warn!("Error in ability on_activate: {}", e);
         }
@@ -341,6 +357,11 @@ impl Default for ScriptState {
                     warn!("{}", e);
                 }
             }
+
+            if let Err(e) = replace_math_random(lua) {
+                warn!("Error replacing Lua math.random");
+                warn!("{}", e);
+            }
         });
 
         let instructions = Arc::new(Mutex::new(InstructionState {
@@ -486,6 +507,35 @@ fn get_targeter() -> Result<Rc<RefCell<AreaTargeter>>> {
         })
 }
 
+/// Replaces the standard Lua `math.random` with one backed by the gameplay
+/// random number generator, so that script randomness is reproducible from
+/// a save.  `math.randomseed` is disabled, as the seed is owned by the game.
+fn replace_math_random(lua: rlua::Context) -> Result<()> {
+    let math: rlua::Table = lua.globals().get("math")?;
+
+    let random = lua.create_function(|_, (m, n): (Option<i64>, Option<i64>)| {
+        let (min, max) = match (m, n) {
+            (None, _) => return Ok(rlua::Value::Number(gen_rand(0.0, 1.0))),
+            (Some(m), None) => (1, m),
+            (Some(m), Some(n)) => (m, n),
+        };
+
+        if min > max {
+            return Err(rlua::Error::RuntimeError(
+                "bad argument to 'random' (interval is empty)".to_string(),
+            ));
+        }
+
+        Ok(rlua::Value::Integer(gen_rand(min, max.saturating_add(1))))
+    })?;
+    math.set("random", random)?;
+
+    let randomseed = lua.create_function(|_, _: rlua::MultiValue| Ok(()))?;
+    math.set("randomseed", randomseed)?;
+
+    Ok(())
+}
+
 fn get_rlua_std_lib() -> rlua::StdLib {
     use rlua::StdLib;
 
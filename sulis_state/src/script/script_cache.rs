@@ -16,6 +16,7 @@
 
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::fmt::Display;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
@@ -26,7 +27,7 @@ use crate::script::{
 };
 use crate::{ai, EntityState};
 use sulis_core::util::Point;
-use sulis_module::{ai::AITemplate, Ability, Item, Module};
+use sulis_module::{ai::AITemplate, Ability, ExpandedScript, Item, Module};
 
 thread_local! {
     static SCRIPT_CACHE: RefCell<HashMap<String, Rc<ScriptState>>> = RefCell::new(HashMap::new());
@@ -55,6 +56,47 @@ pub fn setup() -> Result<()> {
     Ok(())
 }
 
+/// Re-reads changed scripts from the module directories, and rebuilds the
+/// state of every script affected by the change.  A script that fails to
+/// compile keeps its previous state.  Returns a description of the result
+/// for each affected script.
+pub fn reload() -> Vec<String> {
+    let mut results = Vec::new();
+    let mut failed = Vec::new();
+
+    let reload = Module::reload_scripts();
+    for (id, script) in reload.affected.iter() {
+        let script = match script {
+            None => {
+                SCRIPT_CACHE.with(|cache| cache.borrow_mut().remove(id));
+                results.push(format!("Removed '{id}'"));
+                continue;
+            }
+            Some(script) => script,
+        };
+
+        results.extend(script.errors.iter().cloned());
+
+        let mut state = ScriptState::default();
+        match state.load(id, &script.source) {
+            Ok(()) => {
+                SCRIPT_CACHE
+                    .with(|cache| cache.borrow_mut().insert(id.to_string(), Rc::new(state)));
+                results.push(format!("Reloaded '{id}'"));
+            }
+            Err(e) => {
+                let error = map_error_location(script, &e.to_string());
+                warn!("Error reloading script '{}': {}", id, error);
+                results.push(format!("Error in '{id}': {error}"));
+                failed.push(id.to_string());
+            }
+        }
+    }
+
+    Module::apply_script_reload(reload, &failed);
+    results
+}
+
 pub fn set_report_enabled(enabled: bool) {
     REPORTING.with(|r| r.set(enabled));
 }
@@ -68,20 +110,36 @@ fn parse_traceback_line_num(traceback: &str) -> Option<i32> {
         .find_map(|num_str| num_str.parse().ok())
 }
 
-fn print_nearby_lines(state: &ScriptState, traceback: &str) -> (String, i32) {
+/// Finds the script and line that the specified line of the expanded script
+/// with `id` originally came from, along with the source of that script
+fn source_location(id: &str, num: i32) -> Option<(String, i32, String)> {
+    let expanded = Module::expanded_script(id)?;
+    let (file, line) = expanded.source_location(usize::try_from(num).ok()?)?;
+    let script = Module::raw_script(file)?;
+    Some((file.to_string(), line as i32, script))
+}
+
+fn print_nearby_lines(state: &ScriptState, traceback: &str) -> (String, String, i32) {
     let num = match parse_traceback_line_num(traceback) {
-        None => return (String::from("No traceback available.\n"), 0),
+        None => {
+            return (
+                String::from("No traceback available.\n"),
+                state.id.to_string(),
+                0,
+            )
+        }
         Some(num) => num,
     };
 
-    let script = match get_script_from_id(&state.id) {
-        Err(_) => {
+    let (file, num, script) = match source_location(&state.id, num) {
+        None => {
             return (
                 format!("Unable to find script: {} for traceback.\n", state.id),
-                0,
+                state.id.to_string(),
+                num,
             )
         }
-        Ok(script) => script,
+        Some(location) => location,
     };
 
     let start_num = 0.max(num - 5) as usize;
@@ -91,8 +149,8 @@ fn print_nearby_lines(state: &ScriptState, traceback: &str) -> (String, i32) {
         .take(9)
         .enumerate()
         .map(|(i, line)| {
-            let line_num = if i == 4 {
-                format!("{:4}", start_num + i + 1)
+            let line_num = if (start_num + i + 1) as i32 == num {
+                format!("{:4}", num)
             } else {
                 "    ".to_string()
             };
@@ -100,7 +158,15 @@ fn print_nearby_lines(state: &ScriptState, traceback: &str) -> (String, i32) {
         })
         .collect();
 
-    (out, num)
+    (out, file, num)
+}
+
+fn report_error(state: &ScriptState, func: &str, cause: &dyn Display, traceback: &str) {
+    let (output, file, line_num) = print_nearby_lines(state, traceback);
+    warn!(
+        "Script Error:\n{}\n{}.lua:{} Called '{}:{}'\n{}",
+        cause, file, line_num, state.id, func, output
+    );
 }
 
 pub fn exec_func<Args, Ret>(id: &str, func: &str, args: Args) -> Result<Ret>
@@ -129,13 +195,14 @@ where
     match state.exec_func(func, args, reporting) {
         Ok(ret) => Ok(ret),
         Err(CallbackError { traceback, cause }) => {
-            let (output, line_num) = print_nearby_lines(&state, &traceback);
-            warn!(
-                "Script Error:\n{}\n{}.lua:{} Called '{}'\n{}",
-                cause, state.id, line_num, func, output
-            );
+            report_error(&state, func, &cause, &traceback);
             Err(CallbackError { traceback, cause })
         }
+        Err(RuntimeError(message)) => {
+            // errors raised in Lua include the traceback in the message
+            report_error(&state, func, &message, &message);
+            Err(RuntimeError(message))
+        }
         Err(e) => Err(e),
     }
 }
@@ -328,6 +395,37 @@ fn get_script_from_id(id: &str) -> Result<String> {
     })
 }
 
+/// Describes the original script and line of the specified one based line
+/// of an expanded script
+pub(in crate::script) fn location(script: &ExpandedScript, line: usize) -> String {
+    match script.source_location(line) {
+        None => format!("{}.lua:{}", script.id, line),
+        Some((file, line)) => format!("{file}.lua:{line}"),
+    }
+}
+
+/// Lua reports errors against the chunk name and the line of the expanded
+/// script, which is replaced with the original location of that line
+pub(in crate::script) fn map_error_location(script: &ExpandedScript, error: &str) -> String {
+    let chunk = format!("[string \"{}\"]:", script.id);
+    let start = match error.find(&chunk) {
+        None => return error.to_string(),
+        Some(start) => start,
+    };
+
+    let rest = &error[(start + chunk.len())..];
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    match rest[..digits].parse() {
+        Err(_) => error.to_string(),
+        Ok(line) => format!(
+            "{}{}{}",
+            &error[..start],
+            location(script, line),
+            &rest[digits..]
+        ),
+    }
+}
+
 fn get_elapsed_millis(elapsed: Duration) -> f64 {
     (elapsed.as_secs() as f64) * 1000.0 + (elapsed.subsec_nanos() as f64) / 1_000_000.0
 }
@@ -24,11 +24,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::script::{
     script_entity, ScriptActiveSurface, ScriptAppliedEffect, ScriptEntity, ScriptEntitySet,
-    ScriptItemKind, ScriptMenuSelection,
+    ScriptItemKind, ScriptMenuSelection, ScriptResistedEffect,
 };
 use crate::{EntityState, GameState, Script};
 use sulis_core::util::invalid_data_error;
-use sulis_module::{on_trigger::Kind, Ability, DamageKind, HitKind, Module};
+use sulis_module::{on_trigger::Kind, Ability, DamageKind, HitKind, Module, ReactionKind};
 
 pub fn fire_round_elapsed(cbs: Vec<Rc<CallbackData>>) {
     for cb in cbs {
@@ -196,6 +196,14 @@ pub enum FuncKind {
 
     /// Called whena an ability mode is deactivated
     OnDeactivated,
+
+    /// Called on an entity when it is able to react to an action by a hostile,
+    /// including the hostile as a target.  Only called in combat, when the
+    /// entity has reactions remaining.
+    OnReaction,
+
+    /// Called when a parent resists an effect due to one of its immunities
+    OnEffectResisted,
 }
 
 /// A trait representing a callback that will fire a script when called.  In lua scripts,
@@ -205,6 +213,8 @@ pub trait ScriptCallback {
 
     fn on_effect_applied(&self, _effect: ScriptAppliedEffect) {}
 
+    fn on_effect_resisted(&self, _effect: ScriptResistedEffect) {}
+
     fn on_menu_select(&self, _value: ScriptMenuSelection) {}
 
     fn on_removed(&self) {}
@@ -252,6 +262,8 @@ pub trait ScriptCallback {
     fn on_entered_surface(&self, _target: usize) {}
 
     fn on_exited_surface(&self, _target: usize) {}
+
+    fn on_reaction(&self, _targets: &ScriptEntitySet, _kind: ReactionKind) {}
 }
 
 /// A callback that can be passed to various functions to be executed later.
@@ -275,6 +287,7 @@ pub trait ScriptCallback {
 ///
 /// # `set_on_held_changed(func: String)`
 /// # `set_on_effect_applied_fn(func: String)`
+/// # `set_on_effect_resisted_fn(func: String)`
 /// # `set_on_menu_select_fn(func: String)`
 /// # `set_on_removed_fn(func: String)`
 /// # `set_on_damaged_fn(func: String)`
@@ -290,6 +303,7 @@ pub trait ScriptCallback {
 /// # `set_on_moved_in_surface_fn(func: String)`
 /// # `set_on_entered_surface_fn(func: String)`
 /// # `set_on_exited_surface_fn(func: String)`
+/// # `set_on_reaction_fn(func: String)`
 /// Each of these methods causes a specified lua `func` to be called when the condition is met,
 /// as described in `FuncKind`.  Multiple of these methods may be added to one
 /// Callback.
@@ -557,6 +571,14 @@ impl ScriptCallback for CallbackData {
         );
     }
 
+    fn on_effect_resisted(&self, effect: ScriptResistedEffect) {
+        self.exec_script_with_arg(
+            self.get_or_create_targets(),
+            effect,
+            FuncKind::OnEffectResisted,
+        );
+    }
+
     fn on_menu_select(&self, value: ScriptMenuSelection) {
         self.exec_script_with_arg(self.get_or_create_targets(), value, FuncKind::OnMenuSelect);
     }
@@ -616,6 +638,14 @@ impl ScriptCallback for CallbackData {
         self.exec_standard_script(targets, FuncKind::OnExitedSurface);
     }
 
+    fn on_reaction(&self, targets: &ScriptEntitySet, kind: ReactionKind) {
+        self.exec_script_with_arg(
+            self.get_targets(targets),
+            ScriptReaction { kind },
+            FuncKind::OnReaction,
+        );
+    }
+
     fn after_defense(
         &self,
         targets: &ScriptEntitySet,
@@ -758,6 +788,10 @@ impl UserData for CallbackData {
             cb.add_func(FuncKind::OnEffectApplied, func);
             Ok(())
         });
+        methods.add_method_mut("set_on_effect_resisted_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnEffectResisted, func);
+            Ok(())
+        });
         methods.add_method_mut("set_on_menu_select_fn", |_, cb, func: String| {
             cb.add_func(FuncKind::OnMenuSelect, func);
             Ok(())
@@ -818,6 +852,10 @@ impl UserData for CallbackData {
             cb.add_func(FuncKind::OnExitedSurface, func);
             Ok(())
         });
+        methods.add_method_mut("set_on_reaction_fn", |_, cb, func: String| {
+            cb.add_func(FuncKind::OnReaction, func);
+            Ok(())
+        });
     }
 }
 
@@ -919,3 +957,46 @@ impl UserData for ScriptHitKind {
         methods.add_method("kind", |_, hit, ()| Ok(format!("{:?}", hit.kind)));
     }
 }
+
+/// ScriptReaction is passed to `OnReaction` callbacks, describing the action
+/// being reacted to.  The callback should call `use_reaction` on the parent
+/// before doing anything that uses up a reaction.
+///
+/// # `is_opportunity_attack() -> Bool`
+/// Whether the target moved out of the parent's reach
+///
+/// # `is_counterattack() -> Bool`
+/// Whether the target's weapon attack against the parent missed
+///
+/// # `is_interrupt() -> Bool`
+/// Whether the target activated an ability while threatened by the parent
+///
+/// # `kind() -> String`
+/// The kind of reaction.  One of `opportunity_attack`, `counterattack`, or
+/// `interrupt`.
+#[derive(Clone)]
+pub struct ScriptReaction {
+    pub kind: ReactionKind,
+}
+
+impl UserData for ScriptReaction {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("is_opportunity_attack", |_, reaction, ()| {
+            Ok(reaction.kind == ReactionKind::OpportunityAttack)
+        });
+        methods.add_method("is_counterattack", |_, reaction, ()| {
+            Ok(reaction.kind == ReactionKind::Counterattack)
+        });
+        methods.add_method("is_interrupt", |_, reaction, ()| {
+            Ok(reaction.kind == ReactionKind::Interrupt)
+        });
+        methods.add_method("kind", |_, reaction, ()| {
+            let kind = match reaction.kind {
+                ReactionKind::OpportunityAttack => "opportunity_attack",
+                ReactionKind::Counterattack => "counterattack",
+                ReactionKind::Interrupt => "interrupt",
+            };
+            Ok(kind)
+        });
+    }
+}
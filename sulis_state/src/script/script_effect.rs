@@ -22,8 +22,8 @@ use rlua::{Context, UserData, UserDataMethods};
 use sulis_core::util::{ExtInt, Point};
 use sulis_module::{
     bonus::{self, Contingent},
-    ArmorKind, Attribute, Bonus, BonusKind, BonusList, Damage, DamageKind, Slot, WeaponKind,
-    WeaponStyle, ROUND_TIME_MILLIS,
+    ArmorKind, Attribute, Bonus, BonusKind, BonusList, Damage, DamageKind, ReactionKind, Slot,
+    WeaponKind, WeaponStyle, ROUND_TIME_MILLIS,
 };
 
 use crate::script::{
@@ -32,7 +32,7 @@ use crate::script::{
     ScriptCallback, ScriptColorAnimation, ScriptEntity, ScriptImageLayerAnimation,
     ScriptParticleGenerator, ScriptScaleAnimation, ScriptSubposAnimation,
 };
-use crate::{effect, Effect, GameState};
+use crate::{effect, Effect, EffectOutcome, EffectStacking, GameState};
 
 /// Represents a surface that already exists, and is being passed into
 /// a Lua script.  Not used during effect creation
@@ -88,6 +88,27 @@ impl UserData for ScriptMenuSelection {
     }
 }
 
+/// An effect that was not applied to an entity because the entity is
+/// immune to one of its categories
+///
+/// # `name() -> String`
+/// Returns the user defined name of the effect
+///
+/// # `category() -> String`
+/// Returns the category the entity is immune to
+#[derive(Clone)]
+pub struct ScriptResistedEffect {
+    pub name: String,
+    pub category: String,
+}
+
+impl UserData for ScriptResistedEffect {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("name", |_, effect, ()| Ok(effect.name.to_string()));
+        methods.add_method("category", |_, effect, ()| Ok(effect.category.to_string()));
+    }
+}
+
 /// An already applied effect, in contrast to an effect being created
 /// via `ScriptEntity:create_effect`
 ///
@@ -120,7 +141,8 @@ impl UserData for ScriptMenuSelection {
 /// `hit_threshold`, `graze_threshold`, `graze_multiplier`, `hit_multiplier`,
 /// `crit_multiplier`, `movement_rate`, `move_anim_rate`, `attack_cost`, `ability_ap_cost`,
 /// `hidden`, `free_ability_group_use`, abilities_disabled`, `move_disabled`,
-/// `attack_disabled`, `flanked_immunity`, `sneak_attack_immunity`, `crit_immunity`
+/// `attack_disabled`, `flanked_immunity`, `sneak_attack_immunity`, `crit_immunity`,
+/// `morale`, `morale_immunity`, `reactions`
 ///
 /// # `mark_for_removal()`
 /// Marks this effect to be removed on the next update.  This is done asynchronously,
@@ -186,6 +208,8 @@ fn check_for_bonus(effect: &ScriptAppliedEffect, kind: String) -> bool {
         "attack_cost" => AttackCost(0),
         "caster_level" => CasterLevel(0),
         "flanking_angle" => FlankingAngle(0),
+        "morale" => Morale(0),
+        "reactions" => Reactions(0),
         "hidden" => Hidden,
         "free_ability_group_use" => FreeAbilityGroupUse,
         "abilities_disabled" => AbilitiesDisabled,
@@ -194,6 +218,7 @@ fn check_for_bonus(effect: &ScriptAppliedEffect, kind: String) -> bool {
         "flanked_immunity" => FlankedImmunity,
         "sneak_attack_immunity" => SneakAttackImmunity,
         "crit_immunity" => CritImmunity,
+        "morale_immunity" => MoraleImmunity,
         _ => {
             warn!("Attempted to add num bonus with invalid type '{}'", kind);
             return false;
@@ -288,8 +313,10 @@ impl UserData for ScriptAppliedEffect {
 /// An effect, normally created via `ScriptEntity:create_effect`.
 /// The effect is then configured and then `apply()` is called.
 ///
-/// # `apply()`
-/// Sets this effect to active on the parent entity.
+/// # `apply() -> Bool`
+/// Sets this effect to active on the parent entity.  Returns false if the
+/// effect was not applied, because the parent is immune to one of its
+/// categories or a stronger effect with the same tag is already applied.
 ///
 /// # `set_icon(icon: String, text: String)`
 /// Sets the specified icon and text as the icon data for this effect.  This icon
@@ -336,6 +363,21 @@ impl UserData for ScriptAppliedEffect {
 /// Sets this effect to be removed whenever the specified `ability` is deactivated.
 /// The ability must be a mode.
 ///
+/// # `set_stacking(kind: String, strength: Float (Optional))`
+/// Sets how this effect interacts with existing effects on the parent with the
+/// same tag.  One of `stack` (the default), `refresh`, `replace_if_stronger`,
+/// or `unique_per_source`.  `strength` is compared for `replace_if_stronger`.
+/// A refreshed effect keeps its original callbacks; those added to this
+/// effect are not used.
+///
+/// # `set_source(source: ScriptEntity)`
+/// Sets the entity applying this effect, used by `unique_per_source` stacking.
+///
+/// # `add_category(category: String)`
+/// Adds a category, such as `fear` or `poison`, to this effect.  Entities with
+/// an immunity matching the effect's tag or any of its categories will resist
+/// the effect.
+///
 /// # `set_ui_visible(visible: Boolean)`
 /// Sets whether this effect will show up in the UI listing of effects.  The default
 /// is true.
@@ -358,7 +400,7 @@ impl UserData for ScriptAppliedEffect {
 /// `reflex`, `will`, `concealment`, `concealment_ignore`, `crit_chance`,
 /// `hit_threshold`, `graze_threshold`, `graze_multiplier`, `hit_multiplier`,
 /// `crit_multiplier`, `movement_rate`, `move_anim_rate`, `attack_cost`, `ability_ap_cost`,
-/// `caster_level`, `flanking_angle`
+/// `caster_level`, `flanking_angle`, `morale`, `reactions`
 ///
 /// # `add_damage(min: Float, max: Float, ap: Float (Optional), when: String (Optional))`
 /// Adds a damage bonus of the specified amount (from `min` to `max` randomly, with `ap`
@@ -388,6 +430,13 @@ impl UserData for ScriptAppliedEffect {
 /// # `add_crit_immunity(when: String (Optional))`
 /// Adds immunity to crits to this effect (all crits become hits).  See `add_num_bonus`
 ///
+/// # `add_morale_immunity(when: String (Optional))`
+/// Adds immunity to morale loss to this effect.  See `add_num_bonus`
+///
+/// # `add_reaction(kind: String, when: String (Optional))`
+/// Allows the parent to take the specified kind of reaction, one of
+/// `opportunity_attack`, `counterattack`, or `interrupt`.  See `add_num_bonus`
+///
 /// # `add_damage_of_kind(min: Float, max: Float, kind: String, ap: String (Optional),
 /// when: String (Optional))`
 /// Adds the specified amount (from `min` to `max` randomly, with `ap` armor piercing)
@@ -421,6 +470,10 @@ pub struct ScriptEffect {
     color_anims: Vec<ScriptColorAnimation>,
     scale_anims: Vec<ScriptScaleAnimation>,
     subpos_anims: Vec<ScriptSubposAnimation>,
+    stacking: EffectStacking,
+    strength: f32,
+    source: Option<usize>,
+    categories: Vec<String>,
 }
 
 impl ScriptEffect {
@@ -444,6 +497,10 @@ impl ScriptEffect {
             color_anims: Vec::new(),
             scale_anims: Vec::new(),
             subpos_anims: Vec::new(),
+            stacking: EffectStacking::default(),
+            strength: 0.0,
+            source: None,
+            categories: Vec::new(),
         }
     }
 
@@ -463,6 +520,10 @@ impl ScriptEffect {
             color_anims: Vec::new(),
             scale_anims: Vec::new(),
             subpos_anims: Vec::new(),
+            stacking: EffectStacking::default(),
+            strength: 0.0,
+            source: None,
+            categories: Vec::new(),
         }
     }
 }
@@ -535,6 +596,27 @@ impl UserData for ScriptEffect {
             effect.tag = tag;
             Ok(())
         });
+        methods.add_method_mut(
+            "set_stacking",
+            |_, effect, (kind, strength): (String, Option<f32>)| {
+                match EffectStacking::from_str(&kind) {
+                    Err(e) => warn!("{}", e),
+                    Ok(stacking) => {
+                        effect.stacking = stacking;
+                        effect.strength = strength.unwrap_or(0.0);
+                    }
+                }
+                Ok(())
+            },
+        );
+        methods.add_method_mut("set_source", |_, effect, source: ScriptEntity| {
+            effect.source = Some(source.try_unwrap_index()?);
+            Ok(())
+        });
+        methods.add_method_mut("add_category", |_, effect, category: String| {
+            effect.categories.push(category);
+            Ok(())
+        });
         methods.add_method_mut("set_ui_visible", |_, effect, vis: bool| {
             effect.ui_visible = vis;
             Ok(())
@@ -605,6 +687,26 @@ impl UserData for ScriptEffect {
             add_bonus_to_effect(effect, kind, when);
             Ok(())
         });
+
+        methods.add_method_mut("add_morale_immunity", |_, effect, when: Option<String>| {
+            let kind = BonusKind::MoraleImmunity;
+            add_bonus_to_effect(effect, kind, when);
+            Ok(())
+        });
+        methods.add_method_mut(
+            "add_reaction",
+            |_, effect, (kind, when): (String, Option<String>)| {
+                let kind = match ReactionKind::from_str(&kind) {
+                    Err(e) => {
+                        warn!("{}", e);
+                        return Ok(());
+                    }
+                    Ok(kind) => kind,
+                };
+                add_bonus_to_effect(effect, BonusKind::Reaction(kind), when);
+                Ok(())
+            },
+        );
         methods.add_method_mut("add_damage_of_kind", |_, effect, (min, max, kind, ap, when):
                                (f32, f32, String, Option<f32>, Option<String>)| {
             let min = min as u32;
@@ -809,6 +911,8 @@ fn add_num_bonus(
         "attack_cost" => AttackCost(amount_int),
         "caster_level" => CasterLevel(amount_int),
         "flanking_angle" => FlankingAngle(amount_int),
+        "morale" => Morale(amount_int),
+        "reactions" => Reactions(amount_int),
         _ => {
             warn!("Attempted to add num bonus with invalid type '{}'", name);
             return Ok(());
@@ -819,7 +923,7 @@ fn add_num_bonus(
     Ok(())
 }
 
-fn apply(effect_data: &ScriptEffect) -> Result<()> {
+fn apply(effect_data: &ScriptEffect) -> Result<bool> {
     let mgr = GameState::turn_manager();
     let duration = effect_data.duration * ROUND_TIME_MILLIS;
 
@@ -835,6 +939,13 @@ fn apply(effect_data: &ScriptEffect) -> Result<()> {
         effect_data.deactivate_with_ability.clone(),
     );
     effect.ui_visible = effect_data.ui_visible;
+    effect.set_stacking(effect_data.stacking, effect_data.strength);
+    if let Some(source) = effect_data.source {
+        effect.set_source(source);
+    }
+    for category in effect_data.categories.iter() {
+        effect.add_category(category.to_string());
+    }
     if let Some(icon) = &effect_data.icon {
         effect.set_icon(icon.icon.clone(), icon.text.clone());
     }
@@ -886,7 +997,16 @@ fn apply(effect_data: &ScriptEffect) -> Result<()> {
             let on_applied_cbs = entity.borrow().callbacks(&mgr.borrow());
 
             // apply the effect
-            let index = mgr.borrow_mut().add_effect(effect, &entity, cbs, marked);
+            let outcome = mgr.borrow_mut().add_effect(effect, &entity, cbs, marked);
+            let index = match outcome {
+                EffectOutcome::Added(index) => index,
+                EffectOutcome::Refreshed(_) => return Ok(true),
+                EffectOutcome::Ignored => return Ok(false),
+                EffectOutcome::Resisted(category) => {
+                    effect::fire_resisted(&entity, &effect_data.name, &category);
+                    return Ok(false);
+                }
+            };
 
             // fire the on_applied cbs
             let sae = ScriptAppliedEffect::new(mgr.borrow().effect(index), index);
@@ -925,5 +1045,5 @@ fn apply(effect_data: &ScriptEffect) -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(true)
 }
@@ -22,8 +22,8 @@ use std::str::FromStr;
 use rlua::{self, Context, UserData, UserDataMethods};
 
 use crate::{ability_state::DisabledReason, dist, is_within_attack_dist, is_within_touch_dist};
-use crate::{ai, animation, entity_attack_handler, script::*, AreaFeedbackText};
-use crate::{area_feedback_text::ColorKind, EntityState, GameState, Location};
+use crate::{ai, animation, condition, entity_attack_handler, morale, script::*};
+use crate::{area_feedback_text::ColorKind, AreaFeedbackText, EntityState, GameState, Location};
 use sulis_core::config::Config;
 use sulis_core::resource::ResourceSet;
 use sulis_core::util::ExtInt;
@@ -283,6 +283,14 @@ use sulis_module::{
 /// # `has_visibility(target: ScriptEntity) -> Bool`
 /// Returns true if this entity can see the `target`, false otherwise.
 ///
+/// # `cover(target: ScriptEntity) -> String`
+/// Returns the cover the `target` has against ranged and spell attacks from this
+/// entity, one of `none`, `partial`, or `heavy`.
+///
+/// # `elevation(target: ScriptEntity) -> String`
+/// Returns whether this entity is on higher or lower ground than the `target`
+/// when attacking it, one of `level`, `high`, or `low`.
+///
 /// # `can_move() -> Bool`
 /// Returns true if this entity can move at all (even 1 square), false otherwise.
 ///
@@ -333,10 +341,38 @@ use sulis_module::{
 /// based on this entity's armor.  The damage is rolled randomly between `min_damage` and
 /// `max_damage`, with the specified (`ap`) amount of armor piercing.
 ///
+/// # `apply_condition(applier: ScriptEntity, id: String) -> Bool`
+/// Applies the condition with the specified `id`, defined in the module's `conditions`,
+/// to this entity.  The `applier` is the source of the condition's damage and makes the
+/// condition's save, if any.  If this entity already has the condition, it is stacked
+/// instead.  Returns true if the condition was applied or stacked, or false if it was
+/// resisted or had no effect.
+///
+/// # `cleanse_conditions(tag: String) -> Int`
+/// Removes all conditions with the specified tag, such as `poison`, from this entity,
+/// returning the number of conditions removed.
+///
 /// # `heal_damage(amount: Float)`
 /// Adds the specified number of hit points to this entity.  The entity's maximum hit
 /// points cannot be exceeded in this way.
 ///
+/// # `change_morale(amount: Int)`
+/// Adds the specified amount, which may be negative, to this entity's current morale.
+/// This may cause the entity to flee, surrender, or rally.  Has no effect on entities
+/// that are not hostile or that have morale immunity.
+///
+/// # `morale_state() -> String`
+/// Returns `Steady`, `Fleeing`, or `Surrendered` based on this entity's current
+/// morale
+///
+/// # `reactions_remaining() -> Int`
+/// Returns the number of reactions this entity may still take this round
+///
+/// # `use_reaction() -> Bool`
+/// Uses up one of this entity's reactions for the round.  Returns false, using
+/// nothing, if the entity has no reactions remaining.  `OnReaction` callbacks
+/// should call this before reacting.
+///
 /// # `add_class_stat(stat: String, amount: Float)`
 /// Adds the specified amount of the specified stat for this entity.  The entity's maximum
 /// class stat cannot be exceeded.
@@ -453,6 +489,16 @@ use sulis_module::{
 /// # `is_threatened_by(target: ScriptEntity) -> Bool`
 /// Returns true if this entity is threatened by the speciied target with its
 /// melee weapon, false otherwise
+///
+/// # `tactical_weight(target: ScriptEntity) -> Float`
+/// Returns the amount the tactics of this entity's encounter add to the
+/// weight of the specified hostile target, for example because the group is
+/// focusing its attacks on the target.  Returns 0.0 if this entity's group
+/// has no tactics or the target is not hostile.
+///
+/// # `is_group_retreating() -> Bool`
+/// Returns true if the tactics of this entity's encounter call for its group
+/// to retreat, false otherwise
 #[derive(Clone, Debug)]
 pub struct ScriptEntity {
     pub index: Option<usize>,
@@ -1032,7 +1078,7 @@ impl UserData for ScriptEntity {
 
                 dest.max_path_len = max_len;
 
-                move_towards_dest(parent, dest)
+                Ok(move_towards_dest(&parent, dest))
             },
         );
 
@@ -1044,7 +1090,7 @@ impl UserData for ScriptEntity {
                 let mut dest = GameState::get_point_dest(&parent.borrow(), x, y);
                 dest.dist = dist.unwrap_or(MOVE_TO_THRESHOLD);
 
-                move_towards_dest(parent, dest)
+                Ok(move_towards_dest(&parent, dest))
             },
         );
 
@@ -1083,6 +1129,24 @@ impl UserData for ScriptEntity {
             Ok(result)
         });
 
+        methods.add_method("cover", |_, entity, target: ScriptEntity| {
+            let parent = entity.try_unwrap()?;
+            let target = target.try_unwrap()?;
+            let area_state = GameState::area_state();
+            let area_state = area_state.borrow();
+            let cover = area_state.cover(&parent.borrow(), &target.borrow());
+            Ok(cover.to_string())
+        });
+
+        methods.add_method("elevation", |_, entity, target: ScriptEntity| {
+            let parent = entity.try_unwrap()?;
+            let target = target.try_unwrap()?;
+            let area_state = GameState::area_state();
+            let area_state = area_state.borrow();
+            let kind = area_state.elevation_kind(&parent.borrow(), &*target.borrow());
+            Ok(kind.to_string())
+        });
+
         methods.add_method("can_move", |_, entity, ()| {
             let parent = entity.try_unwrap()?;
             let result = parent.borrow().can_move();
@@ -1311,6 +1375,29 @@ impl UserData for ScriptEntity {
             },
         );
 
+        methods.add_method(
+            "apply_condition",
+            |_, entity, (applier, id): (ScriptEntity, String)| {
+                let target = entity.try_unwrap()?;
+                let applier = applier.try_unwrap()?;
+                let condition = match Module::condition(&id) {
+                    None => {
+                        warn!("Attempted to apply invalid condition '{}'", id);
+                        return Ok(false);
+                    }
+                    Some(condition) => condition,
+                };
+
+                Ok(condition::apply(&condition, &target, &applier))
+            },
+        );
+
+        methods.add_method("cleanse_conditions", |_, entity, tag: String| {
+            let entity = entity.try_unwrap()?;
+            let count = condition::cleanse(&entity.borrow(), &tag);
+            Ok(count)
+        });
+
         methods.add_method("heal_damage", |_, entity, amount: f32| {
             let amount = amount as u32;
             let parent = entity.try_unwrap()?;
@@ -1331,6 +1418,30 @@ impl UserData for ScriptEntity {
             Ok(())
         });
 
+        methods.add_method("change_morale", |_, entity, amount: i32| {
+            let parent = entity.try_unwrap()?;
+            morale::change(&parent, amount);
+            Ok(())
+        });
+
+        methods.add_method("morale_state", |_, entity, ()| {
+            let parent = entity.try_unwrap()?;
+            let state = parent.borrow().actor.morale_state();
+            Ok(state.to_string())
+        });
+
+        methods.add_method("reactions_remaining", |_, entity, ()| {
+            let parent = entity.try_unwrap()?;
+            let remaining = parent.borrow().actor.reactions_remaining();
+            Ok(remaining)
+        });
+
+        methods.add_method("use_reaction", |_, entity, ()| {
+            let parent = entity.try_unwrap()?;
+            let used = parent.borrow_mut().actor.use_reaction();
+            Ok(used)
+        });
+
         methods.add_method(
             "add_class_stat",
             |_, entity, (stat, amount): (String, f32)| {
@@ -1625,11 +1736,32 @@ impl UserData for ScriptEntity {
             let target = target.index.unwrap_or(usize::MAX);
             Ok(entity.actor.p_stats().is_threatened_by(target))
         });
+
+        methods.add_method("tactical_weight", |_, entity, target: ScriptEntity| {
+            let parent = entity.try_unwrap()?;
+            let target = target.try_unwrap()?;
+            if !parent.borrow().is_hostile(&target.borrow()) {
+                return Ok(0.0);
+            }
+
+            let tactics = match ai::GroupTactics::current(&parent) {
+                None => return Ok(0.0),
+                Some(tactics) => tactics,
+            };
+            let weight = tactics.weight_modifier(&parent.borrow(), &target.borrow());
+            Ok(weight)
+        });
+
+        methods.add_method("is_group_retreating", |_, entity, ()| {
+            let parent = entity.try_unwrap()?;
+            Ok(ai::GroupTactics::current(&parent).is_some_and(|tactics| tactics.is_retreating()))
+        });
     }
 }
 
-#[allow(clippy::unnecessary_wraps)] // this must return a result to be added as a method in the LUA context
-fn move_towards_dest(parent: Rc<RefCell<EntityState>>, dest: Destination) -> Result<bool> {
+/// Moves the parent towards the destination, ignoring any friendly entities
+/// in the same AI group when pathing.  Returns true if a move was started.
+pub(crate) fn move_towards_dest(parent: &Rc<RefCell<EntityState>>, dest: Destination) -> bool {
     let mgr = GameState::turn_manager();
     let area = GameState::get_area_state(&parent.borrow().location.area_id).unwrap();
     let mut to_ignore = vec![parent.borrow().index()];
@@ -1645,9 +1777,7 @@ fn move_towards_dest(parent: Rc<RefCell<EntityState>>, dest: Destination) -> Res
         }
     }
 
-    Ok(GameState::move_towards_dest(
-        &parent, &to_ignore, dest, None,
-    ))
+    GameState::move_towards_dest(parent, &to_ignore, dest, None)
 }
 
 pub fn unwrap_point(point: HashMap<String, i32>) -> Result<(i32, i32)> {
@@ -1691,6 +1821,8 @@ fn create_stats_table<'a>(
     stats.set("current_hp", parent.actor.hp())?;
     stats.set("current_ap", parent.actor.ap())?;
     stats.set("current_xp", parent.actor.xp())?;
+    stats.set("current_morale", parent.actor.morale())?;
+    stats.set("current_reactions", parent.actor.reactions_remaining())?;
 
     stats.set("strength", src.attributes.strength)?;
     stats.set("dexterity", src.attributes.dexterity)?;
@@ -1742,6 +1874,9 @@ fn create_stats_table<'a>(
 
     stats.set("level", parent.actor.actor.total_level)?;
     stats.set("caster_level", src.caster_level)?;
+    stats.set("morale", src.morale)?;
+    stats.set("is_morale_immune", src.morale_immunity)?;
+    stats.set("reactions", src.reactions)?;
     stats.set("bonus_reach", src.bonus_reach)?;
     stats.set("bonus_range", src.bonus_range)?;
     stats.set("max_hp", src.max_hp)?;
@@ -1821,7 +1956,7 @@ fn targets(_lua: Context, parent: &ScriptEntity, _args: ()) -> Result<ScriptEnti
     })
 }
 
-fn get_on_activate_fn(is_party_member: bool, ai_data: &AIData) -> String {
+pub(crate) fn get_on_activate_fn(is_party_member: bool, ai_data: &AIData) -> String {
     if is_party_member {
         "on_activate".to_string()
     } else if let Some(func) = &ai_data.on_activate_fn {
@@ -18,6 +18,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io::Error;
 use std::rc::Rc;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
@@ -28,7 +29,7 @@ use crate::{
     is_threat, is_within, is_within_attack_dist, is_within_touch_dist, EntityState, GameState,
 };
 use sulis_core::util::{gen_rand, invalid_data_error};
-use sulis_module::Faction;
+use sulis_module::{CoverKind, Faction};
 
 /// Represents a set of ScriptEntities, which can be created from a variety of
 /// sources.  This is passed to many script functions as a `targets` variable.
@@ -129,6 +130,11 @@ use sulis_module::Faction;
 /// Creates a new ScriptEntitySet with all the data from this set, except only targets
 /// which can hit the parent with a melee weapon currently or in the future without moving
 /// are present.
+///
+/// # `max_cover(cover: String) -> ScriptEntitySet`
+/// Creates a new ScriptEntitySet with all the data from this set, except only targets
+/// with at most the specified cover, one of `none`, `partial`, or `heavy`, against
+/// ranged and spell attacks from the parent are present.
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct ScriptEntitySet {
@@ -319,6 +325,7 @@ impl UserData for ScriptEntitySet {
         methods.add_method("touchable", touchable);
         methods.add_method("attackable", attackable);
         methods.add_method("threatening", threatening);
+        methods.add_method("max_cover", max_cover);
     }
 }
 
@@ -358,6 +365,25 @@ fn threatening(_lua: Context, set: &ScriptEntitySet, _args: ()) -> Result<Script
     })
 }
 
+fn max_cover(_lua: Context, set: &ScriptEntitySet, cover: String) -> Result<ScriptEntitySet> {
+    let max = CoverKind::from_str(&cover).map_err(|e| {
+        warn!("Attempted to check max_cover with invalid cover {}", cover);
+        rlua::Error::FromLuaConversionError {
+            from: "String",
+            to: "CoverKind",
+            message: Some(e.to_string()),
+        }
+    })?;
+
+    let area_state = GameState::area_state();
+    filter_entities(set, (), &|parent, entity, _| {
+        let cover = area_state
+            .borrow()
+            .cover(&parent.borrow(), &entity.borrow());
+        cover <= max
+    })
+}
+
 fn touchable(_lua: Context, set: &ScriptEntitySet, _args: ()) -> Result<ScriptEntitySet> {
     filter_entities(set, (), &|parent, entity, _| {
         let entity = &*entity.borrow();
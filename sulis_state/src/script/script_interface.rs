@@ -19,6 +19,7 @@ use std::rc::Rc;
 
 use rlua::{self, UserData, UserDataMethods};
 
+use crate::ai_trace::{self, Outcome, TraceEvent};
 use crate::script::*;
 use crate::{animation::Anim, AreaState, EntityState, GameState, Location};
 use sulis_core::config::Config;
@@ -160,7 +161,7 @@ use sulis_module::{Faction, ItemState, Module, OnTrigger, Time};
 ///
 /// # `log(message: String)`
 /// Logs the specified string to the game's output at info level.  This is primarily useful
-/// for debugging purposes.
+/// for debugging purposes.  Messages logged during an AI turn are also added to the AI trace.
 ///
 /// # `debug(message: String)`
 /// Logs the specified string to game output at debug level.
@@ -168,6 +169,30 @@ use sulis_module::{Faction, ItemState, Module, OnTrigger, Time};
 /// # `trace(message: String)`
 /// Logs the specified string to game output at trace level.
 ///
+/// # `ai_trace_weight(target: ScriptEntity, weight: Float)`
+/// Records the weight an AI script computed for a potential target in the AI trace of the
+/// current turn.  Has no effect outside of an AI turn.
+///
+/// # `ai_trace_candidate(action: String, target: ScriptEntity (Optional), score: Float)`
+/// Records an action that an AI script considered, and the score it was given, in the AI
+/// trace of the current turn.  Has no effect outside of an AI turn.
+///
+/// # `ai_trace_attempt(action: String, target: ScriptEntity (Optional), outcome: String)`
+/// Records an action that an AI script attempted in the AI trace of the current turn.
+/// `outcome` must be one of `Performed`, `Moved`, or `Failed`.  Has no effect outside of an
+/// AI turn.
+///
+/// # `dump_ai_trace() -> String`
+/// Writes the AI trace of recent turns to a new JSON file in the `ai_trace` directory of the
+/// user directory.  Returns a description of the file written, or of the error.  This is
+/// intended to be used from the console.
+///
+/// # `reload_scripts() -> String`
+/// Re-reads any script files that have changed on disk, and reloads each script that is
+/// affected, including scripts that include a changed script.  Scripts with errors keep
+/// their previous version.  Returns a description of each script reloaded, and any errors.
+/// This is intended to be used from the console while developing scripts.
+///
 /// # `ap_display_factor() -> Int`
 /// Gets the ap display factor, which is the factor that the internal AP representation is
 /// divided by when displayed.  Any AP values that are displayed to the user must be
@@ -536,6 +561,9 @@ impl UserData for ScriptInterface {
 
         methods.add_method("log", |_, _, val: String| {
             info!("[LUA]: {}", val);
+            if ai_trace::is_recording() {
+                ai_trace::record(TraceEvent::Log { message: val });
+            }
             Ok(())
         });
 
@@ -549,6 +577,71 @@ impl UserData for ScriptInterface {
             Ok(())
         });
 
+        methods.add_method(
+            "ai_trace_weight",
+            |_, _, (target, weight): (ScriptEntity, f32)| {
+                let target = target.try_unwrap()?.borrow().unique_id().to_string();
+                ai_trace::record(TraceEvent::Weight { target, weight });
+                Ok(())
+            },
+        );
+
+        methods.add_method(
+            "ai_trace_candidate",
+            |_, _, (action, target, score): (String, Option<ScriptEntity>, f32)| {
+                let target = trace_target(target)?;
+                ai_trace::record(TraceEvent::Candidate {
+                    action,
+                    target,
+                    score,
+                    move_dist: None,
+                });
+                Ok(())
+            },
+        );
+
+        methods.add_method(
+            "ai_trace_attempt",
+            |_, _, (action, target, outcome): (String, Option<ScriptEntity>, String)| {
+                let outcome = match outcome.as_str() {
+                    "Performed" => Outcome::Performed,
+                    "Moved" => Outcome::Moved,
+                    "Failed" => Outcome::Failed,
+                    _ => {
+                        return Err(rlua::Error::FromLuaConversionError {
+                            from: "String",
+                            to: "Outcome",
+                            message: Some(format!("Invalid outcome '{outcome}'")),
+                        });
+                    }
+                };
+                let target = trace_target(target)?;
+                ai_trace::record(TraceEvent::Attempt {
+                    action,
+                    target,
+                    outcome,
+                });
+                Ok(())
+            },
+        );
+
+        methods.add_method(
+            "dump_ai_trace",
+            |_, _, ()| match ai_trace::write_to_trace_dir() {
+                Ok(path) => Ok(format!("Wrote AI trace to {}", path.to_string_lossy())),
+                Err(e) => Ok(format!("Unable to write AI trace: {e}")),
+            },
+        );
+
+        methods.add_method("reload_scripts", |_, _, ()| {
+            let results = script_cache::reload();
+            if results.is_empty() {
+                Ok("No scripts have changed".to_string())
+            } else {
+                Ok(results.join("\n"))
+            }
+        });
+
         methods.add_method("ap_display_factor", |_, _, ()| {
             let rules = Module::rules();
             Ok(rules.display_ap)
@@ -1081,6 +1174,13 @@ fn get_area(id: Option<String>) -> Result<Rc<RefCell<AreaState>>> {
     }
 }
 
+fn trace_target(target: Option<ScriptEntity>) -> Result<Option<String>> {
+    match target {
+        None => Ok(None),
+        Some(target) => Ok(Some(target.try_unwrap()?.borrow().unique_id().to_string())),
+    }
+}
+
 fn entities_with_ids(ids: Vec<String>) -> Vec<ScriptEntity> {
     let mut result = Vec::new();
 
@@ -0,0 +1,379 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+//! Static checks of the scripts in the currently loaded module, so that
+//! problems which would otherwise only be found when a script is run can be
+//! reported when the module is loaded.  Every script is compiled and its top
+//! level evaluated, to find the functions it defines.  Every script and
+//! function referenced by triggers, conversations, cutscenes, the campaign,
+//! AI templates, abilities, and items is then checked against those
+//! definitions.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use rlua::{self, Context, FromLuaMulti, MetaMethod, ToLuaMulti, UserData, UserDataMethods};
+
+use crate::script::script_cache::{location, map_error_location};
+use crate::script::{ScriptInterface, ScriptState};
+use sulis_core::util;
+use sulis_module::ai::{AIBackend, FuncKind};
+use sulis_module::area::OnRest;
+use sulis_module::on_trigger::{Kind, ScriptData};
+use sulis_module::prop::Interactive;
+use sulis_module::{ExpandedScript, Module, OnTrigger};
+
+struct Reference {
+    context: String,
+    script: String,
+    func: String,
+}
+
+/// Checks every script in the module, returning a description of each
+/// problem found.  The returned list will be empty if no problems were found.
+pub fn validate() -> Vec<String> {
+    let start = Instant::now();
+    let mut errors = Vec::new();
+
+    let methods = game_methods();
+    let mut functions = HashMap::new();
+
+    let mut ids = Module::all_scripts();
+    ids.sort();
+    for id in ids {
+        let script = match Module::expanded_script(&id) {
+            None => continue,
+            Some(script) => script,
+        };
+
+        for error in script.errors.iter() {
+            errors.push(format!("Script '{id}': {error}"));
+        }
+        check_game_methods(&script, &methods, &mut errors);
+
+        match defined_functions(&id, &script.source) {
+            Ok(funcs) => {
+                functions.insert(id, funcs);
+            }
+            Err(e) => errors.push(format!(
+                "Script '{id}': {}",
+                map_error_location(&script, &e.to_string())
+            )),
+        }
+    }
+
+    for reference in references() {
+        let context = &reference.context;
+        if Module::script(&reference.script).is_none() {
+            errors.push(format!(
+                "{context}: script '{}' does not exist",
+                reference.script
+            ));
+            continue;
+        }
+
+        // scripts that failed to compile have already been reported
+        let funcs = match functions.get(&reference.script) {
+            None => continue,
+            Some(funcs) => funcs,
+        };
+
+        if !funcs.contains(&reference.func) {
+            errors.push(format!(
+                "{context}: script '{}' does not define function '{}'",
+                reference.script, reference.func
+            ));
+        }
+    }
+
+    info!(
+        "Validated scripts in {} secs",
+        util::format_elapsed_secs(start.elapsed())
+    );
+
+    errors
+}
+
+fn check_game_methods(
+    script: &ExpandedScript,
+    methods: &HashSet<String>,
+    errors: &mut Vec<String>,
+) {
+    let id = &script.id;
+    let pattern = "game:";
+    for (index, line) in script.source.lines().enumerate() {
+        let code = match line.find("--") {
+            None => line,
+            Some(comment) => &line[..comment],
+        };
+
+        for (start, _) in code.match_indices(pattern) {
+            let preceding = code[..start].chars().next_back();
+            if preceding.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+                continue;
+            }
+
+            let method: String = code[(start + pattern.len())..]
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+
+            if !methods.contains(&method) {
+                errors.push(format!(
+                    "Script '{id}' at {}: 'game:{method}' is not a valid method",
+                    location(script, index + 1)
+                ));
+            }
+        }
+    }
+}
+
+/// Compiles and evaluates the top level of the script in a new state,
+/// returning the names of all global functions it defines.  The `game`
+/// object is not available during this evaluation, as there may not be a
+/// game in progress.
+fn defined_functions(id: &str, script: &str) -> rlua::Result<HashSet<String>> {
+    let state = ScriptState::default();
+    state
+        .lua
+        .context(|lua| lua.globals().set("game", rlua::Value::Nil))?;
+
+    state.lua.context(|lua| {
+        lua.load(script).set_name(id)?.exec()?;
+
+        let mut funcs = HashSet::new();
+        for pair in lua.globals().pairs::<rlua::Value, rlua::Value>() {
+            if let (rlua::Value::String(name), rlua::Value::Function(_)) = pair? {
+                funcs.insert(name.to_str()?.to_string());
+            }
+        }
+        Ok(funcs)
+    })
+}
+
+/// Collects the names of all methods callable on the `game` object
+#[derive(Default)]
+struct MethodNames(HashSet<String>);
+
+impl MethodNames {
+    fn add<S: ?Sized + AsRef<[u8]>>(&mut self, name: &S) {
+        self.0
+            .insert(String::from_utf8_lossy(name.as_ref()).to_string());
+    }
+}
+
+impl<'lua, T: UserData> UserDataMethods<'lua, T> for MethodNames {
+    fn add_method<S, A, R, M>(&mut self, name: &S, _method: M)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + Fn(Context<'lua>, &T, A) -> rlua::Result<R>,
+    {
+        self.add(name);
+    }
+
+    fn add_method_mut<S, A, R, M>(&mut self, name: &S, _method: M)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + FnMut(Context<'lua>, &mut T, A) -> rlua::Result<R>,
+    {
+        self.add(name);
+    }
+
+    fn add_function<S, A, R, F>(&mut self, name: &S, _function: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, A) -> rlua::Result<R>,
+    {
+        self.add(name);
+    }
+
+    fn add_function_mut<S, A, R, F>(&mut self, name: &S, _function: F)
+    where
+        S: ?Sized + AsRef<[u8]>,
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, A) -> rlua::Result<R>,
+    {
+        self.add(name);
+    }
+
+    fn add_meta_method<A, R, M>(&mut self, _meta: MetaMethod, _method: M)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + Fn(Context<'lua>, &T, A) -> rlua::Result<R>,
+    {
+    }
+
+    fn add_meta_method_mut<A, R, M>(&mut self, _meta: MetaMethod, _method: M)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        M: 'static + Send + FnMut(Context<'lua>, &mut T, A) -> rlua::Result<R>,
+    {
+    }
+
+    fn add_meta_function<A, R, F>(&mut self, _meta: MetaMethod, _function: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + Fn(Context<'lua>, A) -> rlua::Result<R>,
+    {
+    }
+
+    fn add_meta_function_mut<A, R, F>(&mut self, _meta: MetaMethod, _function: F)
+    where
+        A: FromLuaMulti<'lua>,
+        R: ToLuaMulti<'lua>,
+        F: 'static + Send + FnMut(Context<'lua>, A) -> rlua::Result<R>,
+    {
+    }
+}
+
+fn game_methods() -> HashSet<String> {
+    let mut names = MethodNames::default();
+    ScriptInterface::add_methods(&mut names);
+    names.0
+}
+
+fn references() -> Vec<Reference> {
+    let mut refs = Vec::new();
+
+    let campaign = Module::campaign();
+    let campaign_scripts = [
+        Some(&campaign.on_party_death_script),
+        campaign.on_tick_script.as_ref(),
+        campaign.on_round_elapsed_script.as_ref(),
+    ];
+    for data in campaign_scripts.into_iter().flatten() {
+        add_script_data(&mut refs, "Campaign", data);
+    }
+
+    for area in Module::all_areas() {
+        for (index, trigger) in area.triggers.iter().enumerate() {
+            let context = format!("Area '{}' trigger {}", area.id, index);
+            add_triggers(&mut refs, &context, &trigger.on_activate);
+        }
+
+        if let OnRest::FireScript { id, func } = &area.on_rest {
+            refs.push(Reference {
+                context: format!("Area '{}' on_rest", area.id),
+                script: id.to_string(),
+                func: func.to_string(),
+            });
+        }
+    }
+
+    for prop in Module::all_props() {
+        if let Interactive::Door { on_activate, .. } = &prop.interactive {
+            add_triggers(&mut refs, &format!("Prop '{}'", prop.id), on_activate);
+        }
+    }
+
+    for convo in Module::all_conversations() {
+        for (node, trigger) in convo.all_triggers() {
+            let context = format!("Conversation '{}' node '{}'", convo.id, node);
+            add_trigger(&mut refs, &context, trigger);
+        }
+    }
+
+    for cutscene in Module::all_cutscenes() {
+        let context = format!("Cutscene '{}'", cutscene.id);
+        add_triggers(&mut refs, &context, &cutscene.on_end);
+    }
+
+    for ai in Module::all_ai_templates() {
+        let context = format!("AI template '{}'", ai.id);
+        if ai.backend == AIBackend::Script && !ai.hooks.contains_key(&FuncKind::AiAction) {
+            refs.push(Reference {
+                context: context.to_string(),
+                script: ai.script.to_string(),
+                func: "ai_action".to_string(),
+            });
+        }
+
+        for (kind, func) in ai.hooks.iter() {
+            refs.push(Reference {
+                context: format!("{context} hook {kind:?}"),
+                script: ai.script.to_string(),
+                func: func.to_string(),
+            });
+        }
+    }
+
+    for ability in Module::all_abilities() {
+        if let Some(active) = &ability.active {
+            refs.push(Reference {
+                context: format!("Ability '{}'", ability.id),
+                script: active.script.to_string(),
+                func: "on_activate".to_string(),
+            });
+        }
+    }
+
+    for item in Module::all_items() {
+        if let Some(usable) = &item.usable {
+            refs.push(Reference {
+                context: format!("Item '{}'", item.id),
+                script: usable.script.to_string(),
+                func: "on_activate".to_string(),
+            });
+        }
+    }
+
+    refs
+}
+
+fn add_script_data(refs: &mut Vec<Reference>, context: &str, data: &ScriptData) {
+    refs.push(Reference {
+        context: context.to_string(),
+        script: data.id.to_string(),
+        func: data.func.to_string(),
+    });
+}
+
+fn add_triggers(refs: &mut Vec<Reference>, context: &str, triggers: &[OnTrigger]) {
+    for trigger in triggers {
+        add_trigger(refs, context, trigger);
+    }
+}
+
+fn add_trigger(refs: &mut Vec<Reference>, context: &str, trigger: &OnTrigger) {
+    match trigger {
+        OnTrigger::FireScript(data) => add_script_data(refs, context, data),
+        OnTrigger::ShowConfirm(dialog) => {
+            if let Some(data) = &dialog.on_accept {
+                add_script_data(refs, context, data);
+            }
+        }
+        OnTrigger::ShowMenu(menu) => {
+            if let Kind::Script(script) = &menu.cb_kind {
+                refs.push(Reference {
+                    context: context.to_string(),
+                    script: script.to_string(),
+                    func: menu.cb_func.to_string(),
+                });
+            }
+        }
+        _ => (),
+    }
+}
@@ -17,6 +17,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::save_file::{self, AutosaveTrigger};
 use crate::{AreaState, EntityState, GameState, Location, TurnManager};
 use sulis_core::util::Point;
 use sulis_module::{
@@ -52,6 +53,10 @@ pub(crate) fn transition_to(area_id: Option<&str>, p: Option<Point>, offset: Poi
 
     // Point of no return - we are actually transitioning now
 
+    if area_id.is_some() {
+        save_file::request_autosave(AutosaveTrigger::AreaTransition);
+    }
+
     let new_area = GameState::set_current_area(&area);
     GameState::set_clear_anims(); // cleanup anims and surfaces
 
@@ -20,13 +20,21 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
+use crate::ai::GroupTacticsState;
+use crate::condition::PendingTick;
+use crate::morale;
+use crate::reaction::PendingReaction;
+use crate::save_file::{self, AutosaveTrigger};
 use crate::script::{CallbackData, FuncKind, TriggeredCallback};
-use crate::{AreaState, ChangeListener, ChangeListenerList, Effect, EntityState, GameState};
+use crate::{
+    AreaState, ChangeListener, ChangeListenerList, Effect, EffectOutcome, EffectStacking,
+    EntityState, GameState,
+};
 use sulis_core::{
     config::Config,
     util::{gen_rand, Point},
 };
-use sulis_module::{Faction, Module, OnTrigger, Time, ROUND_TIME_MILLIS};
+use sulis_module::{Encounter, Faction, Module, OnTrigger, ReactionKind, Time, ROUND_TIME_MILLIS};
 
 fn add_campaign_elapsed_callback(cbs: &mut Vec<Rc<CallbackData>>) {
     let script_data = match Module::campaign().on_round_elapsed_script {
@@ -74,6 +82,14 @@ pub struct TurnManager {
     pub(crate) ai_groups: HashMap<usize, EncounterRef>,
     pub(crate) cur_ai_group_index: usize,
 
+    // the state of each AI group using group tactics in the current combat.
+    // this is not saved, and is recreated as needed
+    pub(crate) ai_group_tactics: HashMap<usize, GroupTacticsState>,
+
+    // reactions to be fired on the next update
+    pending_reactions: Vec<PendingReaction>,
+    pending_condition_ticks: Vec<PendingTick>,
+
     total_elapsed_millis: usize,
 }
 
@@ -87,22 +103,7 @@ impl TurnManager {
     }
 
     pub fn current_time(&self) -> Time {
-        let rules = Module::rules();
-
-        let total_rounds = self.current_round();
-        let total_hours = total_rounds / rules.rounds_per_hour;
-
-        let day = total_hours / rules.hours_per_day;
-        let hour = total_hours % rules.hours_per_day;
-        let round = total_rounds % rules.rounds_per_hour;
-        let millis = (self.total_elapsed_millis % ROUND_TIME_MILLIS as usize) as u32;
-
-        Time {
-            day,
-            hour,
-            round,
-            millis,
-        }
+        Module::rules().compute_time(self.total_elapsed_millis)
     }
 
     pub fn add_time(&mut self, time: Time) {
@@ -142,6 +143,9 @@ impl TurnManager {
         self.order.clear();
         self.cur_ai_group_index = 0;
         self.ai_groups.clear();
+        self.ai_group_tactics.clear();
+        self.pending_reactions.clear();
+        self.pending_condition_ticks.clear();
         self.total_elapsed_millis = total_elapsed_millis;
     }
 
@@ -227,6 +231,15 @@ impl TurnManager {
         value
     }
 
+    /// Returns the encounter that the specified AI group was spawned from
+    pub fn ai_group_encounter(&self, group: usize) -> Option<Rc<Encounter>> {
+        let enc_ref = self.ai_groups.get(&group)?;
+        let area_state = GameState::get_area_state(&enc_ref.area_id)?;
+        let area_state = area_state.borrow();
+        let data = area_state.area.encounters.get(enc_ref.encounter_index)?;
+        Some(Rc::clone(&data.encounter))
+    }
+
     pub fn entity_checked(&self, index: usize) -> Option<Rc<RefCell<EntityState>>> {
         if index >= self.entities.len() {
             return None;
@@ -238,6 +251,31 @@ impl TurnManager {
         Rc::clone(self.entities[index].as_ref().unwrap())
     }
 
+    /// Queues a reaction by the reactor to an action by the target, to be
+    /// fired on the next update.  Does nothing outside of combat.
+    pub(crate) fn queue_reaction(&mut self, kind: ReactionKind, reactor: usize, target: usize) {
+        if !self.combat_active {
+            return;
+        }
+
+        self.pending_reactions.push(PendingReaction {
+            kind,
+            reactor,
+            target,
+        });
+    }
+
+    #[must_use]
+    pub(crate) fn drain_reactions(&mut self) -> Vec<PendingReaction> {
+        self.pending_reactions.drain(..).collect()
+    }
+
+    /// Returns the ticks of condition effects that have had a round elapse
+    /// since the last call, once for each round
+    pub(crate) fn drain_condition_ticks(&mut self) -> Vec<PendingTick> {
+        self.pending_condition_ticks.drain(..).collect()
+    }
+
     #[must_use]
     pub fn drain_triggered_cbs(&mut self) -> Vec<TriggeredCallback> {
         let surface_callbacks = self.surfaces.iter().flat_map(|index| {
@@ -323,22 +361,64 @@ impl TurnManager {
             }
         }
 
+        if self.combat_active && self.deactivate_surrendered() {
+            self.check_combat_end();
+            self.listeners.notify(self);
+        }
+
         cbs
     }
 
+    /// Ends the AI of any entities that have surrendered since the last
+    /// update.  Returns true if there were any.
+    fn deactivate_surrendered(&self) -> bool {
+        let mut surrendered = Vec::new();
+        for entity in self.entity_iter() {
+            {
+                let mut entity = entity.borrow_mut();
+                if !entity.is_ai_active() || !entity.actor.has_surrendered() {
+                    continue;
+                }
+                entity.set_ai_active(false);
+            }
+            surrendered.push(entity);
+        }
+
+        for entity in surrendered.iter() {
+            let area_id = entity.borrow().location.area_id.to_string();
+            if let Some(area_state) = GameState::get_area_state(&area_id) {
+                area_state.borrow().update_threatened(entity, self);
+            }
+        }
+
+        !surrendered.is_empty()
+    }
+
     #[must_use]
     fn update_effect(
         &mut self,
         index: usize,
         elapsed_millis: u32,
     ) -> (bool, Vec<Rc<CallbackData>>) {
-        self.effects[index]
-            .as_mut()
-            .map(|effect| {
-                let cbs = effect.update(elapsed_millis);
-                (effect.is_removal(), cbs)
-            })
-            .unwrap_or((false, Vec::new()))
+        let effect = match self.effects[index].as_mut() {
+            None => return (false, Vec::new()),
+            Some(effect) => effect,
+        };
+
+        let round = effect.cur_duration / ROUND_TIME_MILLIS;
+        let cbs = effect.update(elapsed_millis);
+        let removal = effect.is_removal();
+
+        if round != effect.cur_duration / ROUND_TIME_MILLIS {
+            if let (Some(state), Some(target)) = (&effect.condition, effect.entity) {
+                self.pending_condition_ticks.push(PendingTick {
+                    target,
+                    state: state.clone(),
+                });
+            }
+        }
+
+        (removal, cbs)
     }
 
     #[must_use]
@@ -374,6 +454,9 @@ impl TurnManager {
 
         let cbs = self.iterate_to_next_entity();
         self.init_turn_for_current_entity(&mut GameState::area_state().borrow_mut());
+        if let Some(current) = self.current() {
+            morale::recover(&current);
+        }
 
         self.listeners.notify(self);
         cbs
@@ -559,7 +642,7 @@ impl TurnManager {
             };
 
             let mut entity = entity.borrow_mut();
-            if entity.is_ai_active() {
+            if entity.is_ai_active() || entity.actor.has_surrendered() {
                 continue;
             }
             if !entity.location.is_in(area_state) {
@@ -643,6 +726,7 @@ impl TurnManager {
 
         if !active {
             self.end_combat();
+            save_file::request_autosave(AutosaveTrigger::CombatEnd);
         } else {
             self.initiate_combat();
         }
@@ -678,6 +762,8 @@ impl TurnManager {
     }
 
     fn end_combat(&mut self) {
+        self.ai_group_tactics.clear();
+
         for entity in self.entities.iter() {
             let entity = match entity {
                 None => continue,
@@ -689,6 +775,7 @@ impl TurnManager {
             entity.set_ai_active(false);
 
             if !entity.is_party_member() {
+                entity.actor.reset_morale();
                 continue;
             }
 
@@ -927,12 +1014,85 @@ impl TurnManager {
         index
     }
 
+    /// Adds the effect to the entity, unless the entity is immune to one of
+    /// the effect's categories or the effect's stacking prevents it.  If the
+    /// effect is not added, the removal markers are set immediately.
     pub fn add_effect(
         &mut self,
         effect: Effect,
         entity: &Rc<RefCell<EntityState>>,
         cbs: Vec<CallbackData>,
         removal_markers: Vec<Rc<Cell<bool>>>,
+    ) -> EffectOutcome {
+        if let Some(outcome) = self.check_add_effect(&effect, &entity.borrow()) {
+            for marker in removal_markers {
+                marker.set(true);
+            }
+            return outcome;
+        }
+
+        EffectOutcome::Added(self.add_loaded_effect(effect, entity, cbs, removal_markers))
+    }
+
+    /// Returns the outcome of adding the effect to the entity if it should not
+    /// be added, marking any effects it replaces for removal
+    fn check_add_effect(&mut self, effect: &Effect, entity: &EntityState) -> Option<EffectOutcome> {
+        if let Some(category) = effect
+            .categories()
+            .find(|category| entity.actor.actor.is_immune(category))
+        {
+            return Some(EffectOutcome::Resisted(category.to_string()));
+        }
+
+        let existing: Vec<usize> = entity
+            .actor
+            .effects_iter()
+            .copied()
+            .filter(|index| {
+                let other = self.effect(*index);
+                other.tag == effect.tag && !other.is_marked_for_removal()
+            })
+            .collect();
+
+        match effect.stacking {
+            EffectStacking::Stack => None,
+            EffectStacking::Refresh => {
+                let index = *existing.first()?;
+                self.effect_mut(index).refresh(effect);
+                Some(EffectOutcome::Refreshed(index))
+            }
+            EffectStacking::ReplaceIfStronger => {
+                if existing
+                    .iter()
+                    .any(|index| self.effect(*index).strength >= effect.strength)
+                {
+                    return Some(EffectOutcome::Ignored);
+                }
+
+                for index in existing {
+                    self.effect_mut(index).mark_for_removal();
+                }
+                None
+            }
+            EffectStacking::UniquePerSource => {
+                for index in existing {
+                    if self.effect(index).source == effect.source {
+                        self.effect_mut(index).mark_for_removal();
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Adds the effect to the entity without checking immunities or stacking,
+    /// as when loading a save
+    pub(crate) fn add_loaded_effect(
+        &mut self,
+        effect: Effect,
+        entity: &Rc<RefCell<EntityState>>,
+        cbs: Vec<CallbackData>,
+        removal_markers: Vec<Rc<Cell<bool>>>,
     ) -> usize {
         let index = self.add_effect_internal(effect, cbs, removal_markers);
 
@@ -1007,7 +1167,7 @@ impl TurnManager {
         debug!("Check encounter cleared: {}", ai_group);
         for other in self.entity_iter() {
             let other = other.borrow();
-            if other.actor.hp() <= 0 {
+            if other.actor.hp() <= 0 || other.actor.has_surrendered() {
                 continue;
             }
             if let Some(index) = other.ai_group() {
@@ -1025,6 +1185,21 @@ impl TurnManager {
         Some(ai_group)
     }
 
+    /// Ends combat if no active hostiles remain, including when the last of
+    /// them have surrendered
+    fn check_combat_end(&mut self) {
+        if self.order.iter().all(|e| match e {
+            Entry::Effect(_) => true,
+            Entry::Entity(index) => {
+                let entity = self.entities[*index].as_ref().unwrap().borrow();
+                !entity.is_ai_active() || entity.actor.faction() != Faction::Hostile
+            }
+            Entry::TurnChange => true,
+        }) {
+            self.set_combat_active(false);
+        }
+    }
+
     fn remove_entity(&mut self, index: usize) {
         let entity = Rc::clone(self.entities[index].as_ref().unwrap());
         let area_state = GameState::get_area_state(&entity.borrow().location.area_id).unwrap();
@@ -1060,16 +1235,9 @@ impl TurnManager {
             Entry::TurnChange => true,
         });
 
-        if self.order.iter().all(|e| match e {
-            Entry::Effect(_) => true,
-            Entry::Entity(index) => {
-                let entity = self.entities[*index].as_ref().unwrap().borrow();
-                !entity.is_ai_active() || entity.actor.faction() != Faction::Hostile
-            }
-            Entry::TurnChange => true,
-        }) {
-            self.set_combat_active(false);
-        }
+        morale::on_death(self, &entity);
+        self.deactivate_surrendered();
+        self.check_combat_end();
 
         if let Some(ai_group) = self.check_encounter_cleared(&entity) {
             let enc_ref = self.ai_groups.get(&ai_group).unwrap().clone();
@@ -1159,3 +1327,53 @@ impl<'a> Iterator for EffectIterator<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sulis_core::util::ExtInt;
+    use sulis_module::BonusList;
+
+    use super::*;
+    use crate::condition::ConditionState;
+
+    #[test]
+    fn condition_ticks_every_round_in_combat() {
+        let mut mgr = TurnManager {
+            combat_active: true,
+            ..Default::default()
+        };
+
+        let mut effect = Effect::new(
+            "Poison",
+            "poison",
+            ExtInt::Int(3 * ROUND_TIME_MILLIS),
+            BonusList::default(),
+            None,
+        );
+        effect.set_owning_entity(7);
+        effect.condition = Some(ConditionState {
+            id: "poison".to_string(),
+            stacks: 1,
+            applier: None,
+        });
+        let index = mgr.add_effect_internal(effect, Vec::new(), Vec::new());
+
+        // each round in combat, as when the effect comes up in the turn order
+        for _ in 0..3 {
+            let (removal, _) = mgr.update_effect(index, ROUND_TIME_MILLIS);
+            if removal {
+                mgr.queue_remove_effect(index);
+            }
+        }
+
+        // the effect is removed before the ticks are fired
+        let _ = mgr.update(0);
+        assert!(mgr.effect_checked(index).is_none());
+
+        let ticks = mgr.drain_condition_ticks();
+        assert_eq!(ticks.len(), 3);
+        assert!(ticks
+            .iter()
+            .all(|tick| tick.target == 7 && tick.state.id() == "poison"));
+    }
+}
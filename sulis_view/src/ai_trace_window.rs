@@ -0,0 +1,154 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use sulis_core::ui::{Callback, Widget, WidgetKind};
+use sulis_core::widgets::{Button, Label, ScrollDirection, ScrollPane};
+use sulis_state::ai_trace::{self, TurnTrace};
+use sulis_state::{ChangeListener, GameState};
+
+pub const NAME: &str = "ai_trace_window";
+
+/// Shows the decisions made by the AI during recent turns
+pub struct AITraceWindow {
+    // the id of the selected turn, or None to show the most recent turn
+    selected: Option<usize>,
+    status: String,
+}
+
+impl AITraceWindow {
+    pub fn new() -> Rc<RefCell<AITraceWindow>> {
+        Rc::new(RefCell::new(AITraceWindow {
+            selected: None,
+            status: String::new(),
+        }))
+    }
+}
+
+impl WidgetKind for AITraceWindow {
+    widget_kind!(NAME);
+
+    fn on_remove(&mut self, _widget: &Rc<RefCell<Widget>>) {
+        GameState::turn_manager()
+            .borrow_mut()
+            .listeners
+            .remove(NAME);
+    }
+
+    fn on_add(&mut self, widget: &Rc<RefCell<Widget>>) -> Vec<Rc<RefCell<Widget>>> {
+        GameState::turn_manager()
+            .borrow_mut()
+            .listeners
+            .add(ChangeListener::invalidate(NAME, widget));
+
+        let close = Widget::with_theme(Button::empty(), "close");
+        close
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (parent, _) = Widget::parent::<AITraceWindow>(widget);
+                parent.borrow_mut().mark_for_removal();
+            })));
+
+        let save = Widget::with_theme(Button::empty(), "save");
+        save.borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (parent, window) = Widget::parent_mut::<AITraceWindow>(widget);
+                window.status = match ai_trace::write_to_trace_dir() {
+                    Ok(path) => format!("Wrote {}", path.to_string_lossy()),
+                    Err(e) => {
+                        warn!("Unable to write AI trace: {}", e);
+                        format!("Unable to write AI trace: {e}")
+                    }
+                };
+                parent.borrow_mut().invalidate_children();
+            })));
+
+        let clear = Widget::with_theme(Button::empty(), "clear");
+        clear
+            .borrow_mut()
+            .state
+            .add_callback(Callback::new(Rc::new(|widget, _| {
+                let (parent, window) = Widget::parent_mut::<AITraceWindow>(widget);
+                ai_trace::clear();
+                window.selected = None;
+                window.status.clear();
+                parent.borrow_mut().invalidate_children();
+            })));
+
+        let status = Widget::with_theme(Label::new(&self.status), "status");
+
+        let turns = ai_trace::turns();
+        let selected = match self.selected {
+            None => turns.last(),
+            Some(id) => turns.iter().find(|turn| turn.id == id),
+        };
+        let selected_id = selected.map(|turn| turn.id);
+
+        let turn_list_pane = ScrollPane::new(ScrollDirection::Vertical);
+        let turn_list = Widget::with_theme(turn_list_pane.clone(), "turn_list");
+        for turn in turns.iter().rev() {
+            let button = Widget::with_theme(Button::empty(), "turn_button");
+            {
+                let state = &mut button.borrow_mut().state;
+                state.add_text_arg("round", &turn.round.to_string());
+                state.add_text_arg("name", &turn.entity_name);
+                state.set_active(selected_id == Some(turn.id));
+            }
+
+            let id = turn.id;
+            button
+                .borrow_mut()
+                .state
+                .add_callback(Callback::new(Rc::new(move |widget, _| {
+                    let (parent, window) = Widget::parent_mut::<AITraceWindow>(widget);
+                    window.selected = Some(id);
+                    parent.borrow_mut().invalidate_children();
+                })));
+            turn_list_pane.borrow().add_to_content(button);
+        }
+
+        let events_pane = ScrollPane::new(ScrollDirection::Vertical);
+        let events = Widget::with_theme(events_pane.clone(), "events");
+        if let Some(turn) = selected {
+            events_pane
+                .borrow()
+                .add_to_content(Widget::with_theme(Label::new(&header(turn)), "header"));
+
+            for event in turn.events.iter() {
+                let text = event.to_string();
+                events_pane
+                    .borrow()
+                    .add_to_content(Widget::with_theme(Label::new(&text), "event"));
+            }
+        }
+
+        vec![close, save, clear, status, turn_list, events]
+    }
+}
+
+fn header(turn: &TurnTrace) -> String {
+    let template = turn.template.as_deref().unwrap_or("none");
+    let backend = turn.backend.as_deref().unwrap_or("none");
+    format!(
+        "{} ({}), round {}, AI '{}' ({})",
+        turn.entity_name, turn.entity_id, turn.round, template, backend
+    )
+}
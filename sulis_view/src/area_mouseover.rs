@@ -22,6 +22,7 @@ use sulis_core::io::{event::ClickKind, GraphicsRenderer};
 use sulis_core::ui::{Widget, WidgetKind, WidgetState};
 use sulis_core::util::Point;
 use sulis_core::widgets::TextArea;
+use sulis_module::{CoverKind, ElevationKind};
 use sulis_state::{ChangeListener, EntityState, GameState};
 
 const NAME: &str = "area_mouseover";
@@ -54,6 +55,11 @@ impl PartialEq for AreaMouseover {
 pub struct AreaMouseover {
     kind: Kind,
     text_area: Rc<RefCell<TextArea>>,
+
+    // the entity whose attack the cover and elevation of the moused over
+    // entity is shown for
+    attacker: Option<Rc<RefCell<EntityState>>>,
+    show_cover: bool,
 }
 
 impl AreaMouseover {
@@ -61,6 +67,20 @@ impl AreaMouseover {
         AreaMouseover::new(Kind::Entity(Rc::clone(entity)))
     }
 
+    /// Creates a mouseover for an entity that is the target of an attack by
+    /// the attacker, showing whether the attacker has higher or lower ground
+    /// and, for ranged and spell attacks, the entity's cover
+    pub fn new_target(
+        entity: &Rc<RefCell<EntityState>>,
+        attacker: &Rc<RefCell<EntityState>>,
+        show_cover: bool,
+    ) -> Rc<RefCell<AreaMouseover>> {
+        let mouseover = AreaMouseover::new_entity(entity);
+        mouseover.borrow_mut().attacker = Some(Rc::clone(attacker));
+        mouseover.borrow_mut().show_cover = show_cover;
+        mouseover
+    }
+
     pub fn new_prop(index: usize) -> Rc<RefCell<AreaMouseover>> {
         AreaMouseover::new(Kind::Prop(index))
     }
@@ -73,6 +93,8 @@ impl AreaMouseover {
         Rc::new(RefCell::new(AreaMouseover {
             kind,
             text_area: TextArea::empty(),
+            attacker: None,
+            show_cover: false,
         }))
     }
 
@@ -85,6 +107,25 @@ impl AreaMouseover {
                 state.add_text_arg("name", &actor.actor.name);
                 state.add_text_arg("cur_hp", &actor.hp().to_string());
                 state.add_text_arg("max_hp", &actor.stats.max_hp.to_string());
+
+                if let Some(attacker) = &self.attacker {
+                    let area_state = GameState::area_state();
+                    let area_state = area_state.borrow();
+                    let attacker = attacker.borrow();
+                    let entity = entity.borrow();
+
+                    let elevation = area_state.elevation_kind(&attacker, &*entity);
+                    if elevation != ElevationKind::Level {
+                        state.add_text_arg("elevation", &format!("{elevation:?}"));
+                    }
+
+                    if self.show_cover {
+                        let cover = area_state.cover(&attacker, &entity);
+                        if cover != CoverKind::None {
+                            state.add_text_arg("cover", &format!("{cover:?}"));
+                        }
+                    }
+                }
             }
             Kind::Prop(index) => {
                 let area_state = GameState::area_state();
@@ -125,10 +125,11 @@ impl AreaOverlayHandler {
 
         if let Some(ref targeter) = targeter {
             let mut targeter = targeter.borrow_mut();
+            let parent = Rc::clone(targeter.parent());
             let mouse_over = targeter.on_mouse_move(x, y);
 
             if let Some(entity) = mouse_over {
-                return Some(AreaMouseover::new_entity(entity));
+                return Some(AreaMouseover::new_target(entity, &parent, true));
             } else {
                 return None;
             }
@@ -137,8 +138,12 @@ impl AreaOverlayHandler {
         let area_state = area_state.borrow();
         if let Some(entity) = area_state.get_entity_at(x, y) {
             let pc = GameState::player();
-            if pc.borrow().is_hostile(&entity.borrow()) && entity.borrow().actor.stats.hidden {
+            let is_hostile = pc.borrow().is_hostile(&entity.borrow());
+            if is_hostile && entity.borrow().actor.stats.hidden {
                 None
+            } else if is_hostile {
+                let ranged = pc.borrow().actor.stats.attack_is_ranged();
+                Some(AreaMouseover::new_target(&entity, &pc, ranged))
             } else {
                 Some(AreaMouseover::new_entity(&entity))
             }
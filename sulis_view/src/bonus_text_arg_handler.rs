@@ -178,6 +178,8 @@ fn add_bonus(
         MovementRate(amount) => state.add_text_arg("movement_rate", &format!("{amount:.2}")),
         MoveAnimRate(amount) => state.add_text_arg("move_anim_rate", &format!("{amount:.2}")),
         CasterLevel(amount) => add(state, "caster_level", amount),
+        Morale(amount) => add(state, "morale", amount),
+        Reactions(amount) => add(state, "reactions", amount),
         AttackCost(amount) => {
             let cost = Module::rules().to_display_ap(*amount);
             add(state, "attack_cost", cost);
@@ -227,6 +229,8 @@ fn add_bonus(
         FlankedImmunity => add(state, "flanked_immunity", true),
         SneakAttackImmunity => add(state, "sneak_attack_immunity", true),
         CritImmunity => add(state, "crit_immunity", true),
+        MoraleImmunity => add(state, "morale_immunity", true),
+        Reaction(kind) => add(state, &format!("reaction_{kind:?}"), "true"),
     }
 }
 
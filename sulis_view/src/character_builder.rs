@@ -327,6 +327,7 @@ impl BuilderSet for CharacterCreator {
             reward: None,
             abilities,
             ai: None,
+            immunities: Vec::new(),
         };
 
         if let Err(e) = write_character_to_file(&filename, &actor) {
@@ -260,6 +260,7 @@ fn export_character(pc: &ActorState) {
         xp: Some(pc.xp()),
         reward: None,
         ai: None,
+        immunities: pc.actor.immunities.clone(),
     };
 
     if let Err(e) = write_character_to_file(&filename, &actor) {
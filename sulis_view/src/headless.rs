@@ -0,0 +1,158 @@
+//  This file is part of Sulis, a turn based RPG written in Rust.
+//  Copyright 2018 Jared Stephen
+//
+//  Sulis is free software: you can redistribute it and/or modify
+//  it under the terms of the GNU General Public License as published by
+//  the Free Software Foundation, either version 3 of the License, or
+//  (at your option) any later version.
+//
+//  Sulis is distributed in the hope that it will be useful,
+//  but WITHOUT ANY WARRANTY; without even the implied warranty of
+//  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//  GNU General Public License for more details.
+//
+//  You should have received a copy of the GNU General Public License
+//  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Error;
+use std::rc::Rc;
+
+use sulis_core::io::{ControlFlowUpdater, HeadlessSystem};
+use sulis_core::ui::{self, Widget};
+use sulis_module::Actor;
+use sulis_state::{GameState, HeadlessReport, NextGameStep, Replay, SaveState};
+
+use crate::{trigger_activator, RootView};
+
+/// A `ControlFlowUpdater` for running a game without a display.  The full
+/// in game UI tree is created and updated, so that triggers, dialogs, and
+/// cutscenes behave as they do in the game, but nothing is ever rendered.
+pub struct HeadlessUpdater {
+    root: Rc<RefCell<Widget>>,
+    view: Rc<RefCell<RootView>>,
+    exit: bool,
+    errors: Vec<String>,
+}
+
+impl HeadlessUpdater {
+    /// Initializes a new game in the currently loaded campaign
+    pub fn new_campaign(
+        pc_actor: Rc<Actor>,
+        party_actors: Vec<Rc<Actor>>,
+        flags: HashMap<String, String>,
+    ) -> Result<HeadlessUpdater, Error> {
+        info!("Initializing headless game state.");
+        GameState::init(pc_actor, party_actors, flags)?;
+        Ok(HeadlessUpdater::new())
+    }
+
+    /// Loads the specified save into the currently loaded campaign
+    pub fn load_campaign(save_state: SaveState) -> Result<HeadlessUpdater, Error> {
+        info!("Loading headless game state.");
+        GameState::load(save_state)?;
+        Ok(HeadlessUpdater::new())
+    }
+
+    fn new() -> HeadlessUpdater {
+        let view = RootView::new();
+        let root = ui::create_ui_tree(view.clone());
+        HeadlessUpdater {
+            root,
+            view,
+            exit: false,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// Runs this updater to completion using the specified `system`, and
+    /// then reports on the resulting game state.
+    pub fn run(mut self, system: &HeadlessSystem) -> HeadlessReport {
+        let result = system.main_loop(&mut self);
+        HeadlessReport::create(result, self.errors)
+    }
+
+    /// Loads the starting state of the replay, replays all of its input, and
+    /// then reports on the resulting game state.  Any differences between the
+    /// final state and the replay's recorded final state are reported as
+    /// errors.
+    pub fn replay(replay: &Replay, system: &HeadlessSystem) -> Result<HeadlessReport, Error> {
+        let mut updater = HeadlessUpdater::load_campaign(replay.start_state()?)?;
+        let result = system.replay_loop(&mut updater, &replay.frames);
+
+        for diff in replay.verify() {
+            updater
+                .errors
+                .push(format!("Replay state mismatch at {diff}"));
+        }
+
+        Ok(HeadlessReport::create(result, updater.errors))
+    }
+
+    fn handle_next_step(&mut self, step: NextGameStep) {
+        match step {
+            NextGameStep::LoadCampaign { save_state } => {
+                info!("Reloading headless game state.");
+                if let Err(e) = GameState::load(*save_state) {
+                    self.fail(format!("Error loading game state: {e}"));
+                    return;
+                }
+
+                let view = RootView::new();
+                self.root = ui::create_ui_tree(view.clone());
+                self.view = view;
+            }
+            _ => {
+                info!("Game requested a return to the menu; exiting headless game.");
+                self.exit = true;
+            }
+        }
+    }
+
+    fn fail(&mut self, error: String) {
+        error!("{}", error);
+        self.errors.push(error);
+        self.exit = true;
+    }
+}
+
+impl ControlFlowUpdater for HeadlessUpdater {
+    fn update(&mut self, millis: u32) -> Rc<RefCell<Widget>> {
+        if self.exit {
+            return self.root();
+        }
+
+        let ui_cb = GameState::update(millis);
+        if let Some(cb) = ui_cb {
+            trigger_activator::activate(&self.root, &cb.on_trigger, &cb.parent, &cb.target);
+        }
+
+        let next_step = self.view.borrow_mut().next_step();
+        if let Some(step) = next_step {
+            self.handle_next_step(step);
+        }
+
+        if let Err(e) = Widget::update(&self.root, millis) {
+            self.fail(format!("Fatal error updating the UI tree state: {e}"));
+        }
+
+        self.root()
+    }
+
+    fn recreate_window(&mut self) -> bool {
+        false
+    }
+
+    fn root(&self) -> Rc<RefCell<Widget>> {
+        Rc::clone(&self.root)
+    }
+
+    fn is_exit(&self) -> bool {
+        self.exit
+    }
+}
@@ -22,6 +22,9 @@ extern crate log;
 mod area_overlay_handler;
 pub use self::area_overlay_handler::AreaOverlayHandler;
 
+mod ai_trace_window;
+pub use self::ai_trace_window::AITraceWindow;
+
 mod abilities_bar;
 pub use self::abilities_bar::AbilitiesBar;
 
@@ -69,6 +72,9 @@ pub use self::formation_window::FormationWindow;
 mod game_over_window;
 pub use self::game_over_window::GameOverWindow;
 
+pub mod headless;
+pub use self::headless::HeadlessUpdater;
+
 mod in_game_menu;
 pub use self::in_game_menu::InGameMenu;
 
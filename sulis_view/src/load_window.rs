@@ -23,7 +23,7 @@ use sulis_core::widgets::{
     Button, ConfirmationWindow, Label, ScrollDirection, ScrollPane, TextArea,
 };
 use sulis_state::save_file::{delete_save, get_available_save_files, load_state};
-use sulis_state::{NextGameStep, SaveFileMetaData, SaveState};
+use sulis_state::{ManifestCheck, NextGameStep, SaveFileMetaData, SaveState};
 
 use crate::{main_menu::MainMenu, LoadingScreen, RootView};
 
@@ -34,6 +34,7 @@ pub struct LoadWindow {
     delete: Rc<RefCell<Widget>>,
     pub(crate) cancel: Rc<RefCell<Widget>>,
     pub(crate) entries: Vec<SaveFileMetaData>,
+    checks: Vec<ManifestCheck>,
     pub(crate) selected_entry: Option<usize>,
     main_menu_mode: bool,
 }
@@ -51,12 +52,14 @@ impl LoadWindow {
                 Vec::new()
             }
         };
+        let checks = entries.iter().map(|meta| meta.check_manifest()).collect();
 
         Rc::new(RefCell::new(LoadWindow {
             accept,
             delete,
             cancel,
             entries,
+            checks,
             selected_entry: None,
             main_menu_mode,
         }))
@@ -68,6 +71,15 @@ impl LoadWindow {
             Some(index) => index,
         };
 
+        let check = &self.checks[index];
+        if let Some(error) = &check.error {
+            error!("Unable to load save: {}", error);
+            return;
+        }
+        for warning in check.warnings.iter() {
+            warn!("{}", warning);
+        }
+
         match load_state(&self.entries[index]) {
             Err(e) => {
                 error!("Error reading game state");
@@ -110,6 +122,7 @@ impl LoadWindow {
         }
 
         self.entries.remove(index);
+        self.checks.remove(index);
     }
 
     fn set_button_state(&self) {
@@ -120,7 +133,9 @@ impl LoadWindow {
 
         let accept_enabled = match self.selected_entry {
             None => false,
-            Some(index) => self.entries[index].error.is_none(),
+            Some(index) => {
+                self.entries[index].error.is_none() && self.checks[index].error.is_none()
+            }
         };
 
         self.accept.borrow_mut().state.set_enabled(accept_enabled);
@@ -189,6 +204,10 @@ impl WidgetKind for LoadWindow {
                 area.add_text_arg("datetime", &meta.datetime);
                 area.add_text_arg("current_area_name", &meta.current_area_name);
 
+                if meta.autosave {
+                    area.add_text_arg("autosave", "true");
+                }
+
                 if let Some(level) = meta.level {
                     area.add_text_arg("level", &format!("{level}"));
                 }
@@ -203,6 +222,24 @@ impl WidgetKind for LoadWindow {
             }
 
             let widget = Widget::with_theme(Button::empty(), "entry");
+            let check = &self.checks[index];
+            if let Some(error) = &check.error {
+                text_area
+                    .borrow_mut()
+                    .state
+                    .add_text_arg("manifest_error", error);
+                widget.borrow_mut().state.add_text_arg("manifest", error);
+            } else if !check.warnings.is_empty() {
+                text_area
+                    .borrow_mut()
+                    .state
+                    .add_text_arg("mods_warning", "true");
+                widget
+                    .borrow_mut()
+                    .state
+                    .add_text_arg("manifest", &check.warnings.join("\n"));
+            }
+
             widget
                 .borrow_mut()
                 .state
@@ -21,7 +21,7 @@ use std::rc::Rc;
 use sulis_core::ui::*;
 use sulis_core::util::ActiveResources;
 use sulis_core::widgets::{Button, Label, ScrollDirection, ScrollPane, TextArea};
-use sulis_module::ModificationInfo;
+use sulis_module::{modification, ModificationInfo, Module};
 use sulis_state::NextGameStep;
 
 use crate::main_menu::MainMenu;
@@ -69,6 +69,25 @@ impl WidgetKind for ModsSelector {
 
         self.available_mods.sort_by(|a, b| a.name.cmp(&b.name));
 
+        let campaign = if Module::is_initialized() {
+            Some(Module::campaign().id.to_string())
+        } else {
+            None
+        };
+        let order = modification::resolve_load_order(
+            std::mem::take(&mut self.active_mods),
+            campaign.as_deref(),
+        );
+        self.active_mods = order.mods;
+
+        let warnings = Widget::with_theme(TextArea::empty(), "warnings");
+        if !order.warnings.is_empty() {
+            warnings
+                .borrow_mut()
+                .state
+                .add_text_arg("warnings", &order.warnings.join("\n"));
+        }
+
         let len = self.available_mods.len();
         for (index, modif) in self.available_mods.iter().enumerate() {
             let widget = Widget::with_defaults(ModPane::new(modif.clone(), false, index, len));
@@ -113,11 +132,8 @@ impl WidgetKind for ModsSelector {
                 let (_, sel) = Widget::parent_mut::<ModsSelector>(widget);
 
                 let mut resources = ActiveResources::read();
-                resources.mods.clear();
-                for modif in sel.active_mods.iter() {
-                    resources.mods.push(modif.dir.to_string());
-                }
-
+                let mods = sel.active_mods.iter().map(|m| m.dir.to_string()).collect();
+                resources.set_mods(mods);
                 resources.write();
 
                 let (root, menu) = Widget::parent_mut::<MainMenu>(widget);
@@ -134,6 +150,7 @@ impl WidgetKind for ModsSelector {
             active_title,
             available,
             active,
+            warnings,
             clear,
             cancel,
             apply,
@@ -175,6 +192,9 @@ impl WidgetKind for ModPane {
             state.add_text_arg("name", &self.modif.name);
             state.add_text_arg("description", &self.modif.description);
             state.add_text_arg("dir", &self.modif.dir);
+            if !self.modif.version.is_empty() {
+                state.add_text_arg("version", &self.modif.version);
+            }
         }
 
         let toggle = Widget::with_theme(Button::empty(), "toggle");
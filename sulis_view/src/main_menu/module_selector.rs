@@ -134,7 +134,7 @@ impl WidgetKind for ModuleSelector {
 
                 let (root, menu) = Widget::parent_mut::<MainMenu>(widget);
                 let mut active = ActiveResources::read();
-                active.campaign = Some(module.dir);
+                active.set_campaign(module.dir);
                 active.write();
                 menu.next_step = Some(NextGameStep::MainMenuReloadResources);
 
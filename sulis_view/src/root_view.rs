@@ -18,10 +18,11 @@ use std::collections::HashMap;
 use std::{any::Any, cell::RefCell, rc::Rc, time::Instant};
 
 use crate::{
-    character_window, formation_window, in_game_menu, inventory_window, merchant_window,
-    prop_window, quest_window, world_map_window, AbilitiesBar, ApBar, AreaView, CharacterWindow,
-    ConsoleWindow, FormationWindow, GameOverWindow, InGameMenu, InitiativeTicker, InventoryWindow,
-    MerchantWindow, PortraitPane, PropWindow, QuestWindow, QuickItemBar, WorldMapWindow,
+    ai_trace_window, character_window, formation_window, in_game_menu, inventory_window,
+    merchant_window, prop_window, quest_window, world_map_window, AITraceWindow, AbilitiesBar,
+    ApBar, AreaView, CharacterWindow, ConsoleWindow, FormationWindow, GameOverWindow, InGameMenu,
+    InitiativeTicker, InventoryWindow, MerchantWindow, PortraitPane, PropWindow, QuestWindow,
+    QuickItemBar, WorldMapWindow,
 };
 use sulis_core::config::Config;
 use sulis_core::io::{keyboard_event::Key, InputActionKind};
@@ -35,7 +36,7 @@ use sulis_state::{
     Script,
 };
 
-const WINDOW_NAMES: [&str; 8] = [
+const WINDOW_NAMES: [&str; 9] = [
     formation_window::NAME,
     inventory_window::NAME,
     character_window::NAME,
@@ -44,6 +45,7 @@ const WINDOW_NAMES: [&str; 8] = [
     merchant_window::NAME,
     prop_window::NAME,
     in_game_menu::NAME,
+    ai_trace_window::NAME,
 ];
 
 const NAME: &str = "game";
@@ -186,6 +188,12 @@ impl RootView {
         });
     }
 
+    pub fn set_ai_trace_window(&mut self, widget: &Rc<RefCell<Widget>>, desired_state: bool) {
+        self.set_window(widget, ai_trace_window::NAME, desired_state, &|| {
+            Some(AITraceWindow::new())
+        });
+    }
+
     pub fn set_formation_window(&mut self, widget: &Rc<RefCell<Widget>>, desired_state: bool) {
         self.set_window(widget, formation_window::NAME, desired_state, &|| {
             Some(FormationWindow::new())
@@ -267,6 +275,11 @@ impl RootView {
         self.set_quest_window(widget, desired_state);
     }
 
+    pub fn toggle_ai_trace_window(&mut self, widget: &Rc<RefCell<Widget>>) {
+        let desired_state = !Widget::has_child_with_name(widget, ai_trace_window::NAME);
+        self.set_ai_trace_window(widget, desired_state);
+    }
+
     pub fn toggle_map_window(&mut self, widget: &Rc<RefCell<Widget>>) {
         let desired_state = !Widget::has_child_with_name(widget, world_map_window::NAME);
         self.set_map_window(widget, desired_state, false);
@@ -472,6 +485,7 @@ impl WidgetKind for RootView {
             ToggleMap => self.toggle_map_window(widget),
             ToggleJournal => self.toggle_quest_window(widget),
             ToggleFormation => self.toggle_formation_window(widget),
+            ToggleAITrace => self.toggle_ai_trace_window(widget),
             EndTurn => self.end_turn(),
             Rest => self.rest(),
             Exit => self.show_exit(widget),
@@ -14,7 +14,7 @@
 //  You should have received a copy of the GNU General Public License
 //  along with Sulis.  If not, see <http://www.gnu.org/licenses/>
 
-use sulis_core::util::{gen_rand, Offset};
+use sulis_core::util::{gen_visual_rand, Offset};
 
 const CLAMP_SHAKES: u32 = 4;
 const TOTAL_SHAKES: u32 = 7;
@@ -84,8 +84,9 @@ impl ScreenShake {
             self.last_millis -= SHAKE_MILLIS;
 
             let mut scroll = Offset {
-                x: -1.0 * self.last_scroll.x.signum() * gen_rand(1.0, 1.8) - self.last_scroll.x,
-                y: gen_rand(-0.1, 0.1) - self.last_scroll.y,
+                x: -1.0 * self.last_scroll.x.signum() * gen_visual_rand(1.0, 1.8)
+                    - self.last_scroll.x,
+                y: gen_visual_rand(-0.1, 0.1) - self.last_scroll.y,
             };
 
             if self.total_shakes > CLAMP_SHAKES {